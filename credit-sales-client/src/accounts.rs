@@ -0,0 +1,202 @@
+use solana_sdk::pubkey::Pubkey;
+
+fn read_i64(data: &[u8], offset: usize) -> Option<i64> {
+    Some(i64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    Pubkey::try_from(data.get(offset..offset + 32)?).ok()
+}
+
+/// Decoded `CreditsAccount`. Integers are little-endian.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CreditsAccount {
+    /// Time of the last purchase
+    pub timestamp: i64,
+    /// Credits bought over all purchases
+    pub credits_amount: u64,
+    pub credits_amount_refunded: u64,
+    pub bump: u8,
+    pub owner: Pubkey,
+    pub purchase_count: u64,
+    /// Usage settled by `ConsumeCredits`
+    pub credits_consumed: u64,
+    /// Time of the first purchase, which opens the refund window
+    pub first_purchase_at: i64,
+    /// Base units received over all purchases
+    pub amount_paid: u64,
+    /// Mint the purchases were paid in
+    pub mint: Pubkey,
+}
+
+impl CreditsAccount {
+    pub const LEN: usize = 8 // timestamp i64
+        + 8  // credits_amount u64
+        + 8  // credits_amount_refunded u64
+        + 1  // bump u8
+        + 32 // owner
+        + 8  // purchase_count u64
+        + 8  // credits_consumed u64
+        + 8  // first_purchase_at i64
+        + 8  // amount_paid u64
+        + 32; // mint
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN {
+            return None;
+        }
+
+        Some(Self {
+            timestamp: read_i64(data, 0)?,
+            credits_amount: read_u64(data, 8)?,
+            credits_amount_refunded: read_u64(data, 16)?,
+            bump: data[24],
+            owner: read_pubkey(data, 25)?,
+            purchase_count: read_u64(data, 57)?,
+            credits_consumed: read_u64(data, 65)?,
+            first_purchase_at: read_i64(data, 73)?,
+            amount_paid: read_u64(data, 81)?,
+            mint: read_pubkey(data, 89)?,
+        })
+    }
+
+    /// Credits neither refunded nor consumed yet.
+    pub fn credits_available(&self) -> u64 {
+        self.credits_amount
+            .saturating_sub(self.credits_amount_refunded)
+            .saturating_sub(self.credits_consumed)
+    }
+}
+
+/// Decoded program `Config`. Integers are little-endian.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub mint: Pubkey,
+    pub settlement_authority: Pubkey,
+    pub credits_per_usdc: u64,
+    pub paused: bool,
+    pub bump: u8,
+    pub treasury_bump: u8,
+    /// Seconds after the first purchase during which credits can be refunded
+    pub refund_window: i64,
+    /// Legacy token program or Token-2022, as the mint's
+    pub token_program: Pubkey,
+    pub decimals: u8,
+}
+
+impl Config {
+    pub const LEN: usize = 32 // admin
+        + 32 // treasury
+        + 32 // mint
+        + 32 // settlement_authority
+        + 8  // credits_per_usdc u64
+        + 1  // paused bool
+        + 1  // bump u8
+        + 1  // treasury_bump u8
+        + 8  // refund_window i64
+        + 32 // token_program
+        + 1; // decimals u8
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN {
+            return None;
+        }
+
+        Some(Self {
+            admin: read_pubkey(data, 0)?,
+            treasury: read_pubkey(data, 32)?,
+            mint: read_pubkey(data, 64)?,
+            settlement_authority: read_pubkey(data, 96)?,
+            credits_per_usdc: read_u64(data, 128)?,
+            paused: data[136] != 0,
+            bump: data[137],
+            treasury_bump: data[138],
+            refund_window: read_i64(data, 139)?,
+            token_program: read_pubkey(data, 147)?,
+            decimals: data[179],
+        })
+    }
+}
+
+/// Decoded `Plan`. Integers are little-endian.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Plan {
+    pub bump: u8,
+    pub plan_id: u8,
+    /// Whole tokens of the config's mint per period, 0 when withdrawn
+    pub price: u64,
+}
+
+impl Plan {
+    pub const LEN: usize = 1 // bump u8
+        + 1  // plan_id u8
+        + 8; // price u64
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN {
+            return None;
+        }
+
+        Some(Self {
+            bump: data[0],
+            plan_id: data[1],
+            price: read_u64(data, 2)?,
+        })
+    }
+}
+
+/// Decoded `PlanAccount`. Integers are little-endian.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanAccount {
+    pub bump: u8,
+    pub owner: Pubkey,
+    pub plan_id: u8,
+    pub auto_renew: bool,
+    /// Start of the first paid period in a row
+    pub period_start: i64,
+    /// End of the last paid period
+    pub period_end: i64,
+    /// Credits per monthly period
+    pub monthly_quota: u64,
+}
+
+impl PlanAccount {
+    pub const LEN: usize = 1 // bump u8
+        + 32 // owner
+        + 1  // plan_id u8
+        + 1  // auto_renew bool
+        + 8  // period_start i64
+        + 8  // period_end i64
+        + 8; // monthly_quota u64
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN {
+            return None;
+        }
+
+        Some(Self {
+            bump: data[0],
+            owner: read_pubkey(data, 1)?,
+            plan_id: data[33],
+            auto_renew: data[34] != 0,
+            period_start: read_i64(data, 35)?,
+            period_end: read_i64(data, 43)?,
+            monthly_quota: read_u64(data, 51)?,
+        })
+    }
+
+    /// Whether a paid period covers `now`.
+    pub fn is_active(&self, now: i64) -> bool {
+        self.period_start <= now && now < self.period_end
+    }
+}
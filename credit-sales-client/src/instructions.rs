@@ -0,0 +1,297 @@
+use {
+    crate::{
+        pda::{
+            config_address, credits_account_address, plan_account_address, plan_address,
+            treasury_address,
+        },
+        CreditSalesInstruction,
+    },
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        system_program, sysvar,
+    },
+};
+
+/// Config values set by `InitializeConfig` and `UpdateConfig`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigParams {
+    /// Credits bought per base unit of the mint
+    pub credits_per_usdc: u64,
+    /// Signer of `ConsumeCredits`
+    pub settlement_authority: Pubkey,
+    /// Seconds after the first purchase during which credits can be refunded
+    pub refund_window: i64,
+}
+
+fn data(instruction: CreditSalesInstruction, args: &[&[u8]]) -> Vec<u8> {
+    let mut data = vec![instruction as u8];
+    for arg in args {
+        data.extend_from_slice(arg);
+    }
+    data
+}
+
+/// Creates the buyer's credits account.
+pub fn initialize_credits_account(program_id: &Pubkey, buyer: &Pubkey) -> Instruction {
+    let (credits_account, bump) = credits_account_address(program_id, buyer);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(credits_account, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(config_address(program_id).0, false),
+        ],
+        data: data(CreditSalesInstruction::InitializeCreditsAccount, &[&[bump]]),
+    }
+}
+
+/// Buys credits for `amount_usdc` base units of `mint`, paid from
+/// `buyer_token_account`.
+pub fn buy_credits(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    buyer_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount_usdc: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(*buyer_token_account, false),
+            AccountMeta::new(treasury_address(program_id, mint).0, false),
+            AccountMeta::new(credits_account_address(program_id, buyer).0, false),
+            AccountMeta::new_readonly(sysvar::clock::ID, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(config_address(program_id).0, false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: data(
+            CreditSalesInstruction::BuyCredits,
+            &[&amount_usdc.to_le_bytes()],
+        ),
+    }
+}
+
+/// Pays for a period of plan `plan_id`, creating the buyer's plan account on
+/// their first purchase.
+pub fn buy_plan(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    buyer_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    plan_id: u8,
+    auto_renew: bool,
+) -> Instruction {
+    let (plan_account, bump) = plan_account_address(program_id, buyer);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(*buyer_token_account, false),
+            AccountMeta::new(treasury_address(program_id, mint).0, false),
+            AccountMeta::new(plan_account, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(config_address(program_id).0, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(plan_address(program_id, plan_id).0, false),
+        ],
+        data: data(
+            CreditSalesInstruction::BuyPlan,
+            &[&[bump, plan_id, auto_renew as u8]],
+        ),
+    }
+}
+
+/// Pays `credits` back to `buyer_token_account` at the price the buyer paid,
+/// within the refund window of their first purchase. Signed by the admin.
+pub fn refund_credits(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    buyer: &Pubkey,
+    buyer_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    credits: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(*buyer, false),
+            AccountMeta::new(*buyer_token_account, false),
+            AccountMeta::new(treasury_address(program_id, mint).0, false),
+            AccountMeta::new(credits_account_address(program_id, buyer).0, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(config_address(program_id).0, false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: data(
+            CreditSalesInstruction::RefundCredits,
+            &[&credits.to_le_bytes()],
+        ),
+    }
+}
+
+/// Moves `amount` base units from the treasury to the admin's token account.
+pub fn withdraw_usdc(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    admin_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(treasury_address(program_id, mint).0, false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(*admin_token_account, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(config_address(program_id).0, false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: data(
+            CreditSalesInstruction::WithdrawUSDC,
+            &[&amount.to_le_bytes()],
+        ),
+    }
+}
+
+/// Settles usage for each `(buyer, credits_consumed)`, where
+/// `credits_consumed` is the buyer's new cumulative total.
+pub fn consume_credits(
+    program_id: &Pubkey,
+    settlement_authority: &Pubkey,
+    settlements: &[(Pubkey, u64)],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*settlement_authority, true),
+        AccountMeta::new_readonly(config_address(program_id).0, false),
+    ];
+    let mut data = vec![CreditSalesInstruction::ConsumeCredits as u8];
+    for (buyer, credits_consumed) in settlements {
+        accounts.push(AccountMeta::new(
+            credits_account_address(program_id, buyer).0,
+            false,
+        ));
+        data.extend_from_slice(&credits_consumed.to_le_bytes());
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+fn config_accounts(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new(config_address(program_id).0, false),
+        AccountMeta::new(treasury_address(program_id, mint).0, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ]
+}
+
+/// Creates the config and the treasury of `mint`, with `admin` as admin.
+pub fn initialize_config(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    params: &ConfigParams,
+) -> Instruction {
+    let (_, bump) = config_address(program_id);
+    let (_, treasury_bump) = treasury_address(program_id, mint);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: config_accounts(program_id, admin, mint, token_program),
+        data: data(
+            CreditSalesInstruction::InitializeConfig,
+            &[
+                &[bump, treasury_bump],
+                &params.credits_per_usdc.to_le_bytes(),
+                params.settlement_authority.as_ref(),
+                &params.refund_window.to_le_bytes(),
+            ],
+        ),
+    }
+}
+
+/// Replaces the config's mint, settlement authority, price, pause flag and
+/// refund window. Signed by the admin.
+pub fn update_config(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    params: &ConfigParams,
+    paused: bool,
+) -> Instruction {
+    let (_, treasury_bump) = treasury_address(program_id, mint);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: config_accounts(program_id, admin, mint, token_program),
+        data: data(
+            CreditSalesInstruction::UpdateConfig,
+            &[
+                &[treasury_bump],
+                &params.credits_per_usdc.to_le_bytes(),
+                &[paused as u8],
+                params.settlement_authority.as_ref(),
+                &params.refund_window.to_le_bytes(),
+            ],
+        ),
+    }
+}
+
+/// Sets plan `plan_id`'s price per period in whole tokens of the config's
+/// mint, 0 to withdraw it. Signed by the admin.
+pub fn set_plan(program_id: &Pubkey, admin: &Pubkey, plan_id: u8, price: u64) -> Instruction {
+    let (plan, bump) = plan_address(program_id, plan_id);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(config_address(program_id).0, false),
+            AccountMeta::new(plan, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data(
+            CreditSalesInstruction::SetPlan,
+            &[&[bump, plan_id], &price.to_le_bytes()],
+        ),
+    }
+}
+
+/// Hands the config over to `new_admin`, who signs alongside the admin.
+pub fn transfer_admin(program_id: &Pubkey, admin: &Pubkey, new_admin: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(config_address(program_id).0, false),
+            AccountMeta::new_readonly(*new_admin, true),
+        ],
+        data: data(CreditSalesInstruction::TransferAdmin, &[]),
+    }
+}
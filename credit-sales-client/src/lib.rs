@@ -0,0 +1,26 @@
+//! Client for the credit-sales program: instruction builders, PDA helpers
+//! and decoders for its accounts. Every builder takes the program id, since
+//! deployments (and tests) use their own.
+
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
+
+pub use {accounts::*, instructions::*, pda::*};
+
+/// Discriminators of the program's instructions, the first byte of their
+/// data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CreditSalesInstruction {
+    BuyCredits = 0,
+    RefundCredits = 1,
+    WithdrawUSDC = 2,
+    InitializeCreditsAccount = 3,
+    ConsumeCredits = 4,
+    InitializeConfig = 5,
+    UpdateConfig = 6,
+    TransferAdmin = 7,
+    BuyPlan = 8,
+    SetPlan = 9,
+}
@@ -0,0 +1,32 @@
+use solana_sdk::pubkey::Pubkey;
+
+pub const CONFIG_SEED: &[u8] = b"config";
+pub const TREASURY_SEED: &[u8] = b"treasury";
+pub const CREDITS_ACCOUNT_SEED: &[u8] = b"credits_account";
+pub const PLAN_SEED: &[u8] = b"plan";
+pub const PLAN_ACCOUNT_SEED: &[u8] = b"plan_account";
+
+/// The program's `["config"]` PDA and its canonical bump.
+pub fn config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+}
+
+/// The `["treasury", mint]` token account, which is its own authority.
+pub fn treasury_address(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TREASURY_SEED, mint.as_ref()], program_id)
+}
+
+/// The buyer's `["credits_account", buyer]` PDA.
+pub fn credits_account_address(program_id: &Pubkey, buyer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CREDITS_ACCOUNT_SEED, buyer.as_ref()], program_id)
+}
+
+/// The buyer's `["plan_account", buyer]` PDA.
+pub fn plan_account_address(program_id: &Pubkey, buyer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PLAN_ACCOUNT_SEED, buyer.as_ref()], program_id)
+}
+
+/// Plan `plan_id`'s `["plan", [plan_id]]` PDA, set by the admin.
+pub fn plan_address(program_id: &Pubkey, plan_id: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PLAN_SEED, &[plan_id]], program_id)
+}
@@ -1,10 +1,7 @@
+/// Deployer key, the only one allowed to create the program's `Config`.
+/// Everything else (admin, treasury, mint, price) lives in the config.
 pub const ADMIN: [u8; 32] =
     five8_const::decode_32_const("3n5KbkZv1Zyu661dTzPNCqKzLyeYu9uuaqLExpLnz3w4");
-// pub const USDC_MINT: [u8; 32] =
-// five8_const::decode_32_const("111111111111111111111111111111111111111111");
-pub const TREASURY: [u8; 32] =
-    five8_const::decode_32_const("CyKcXpxkyai33oMXq3GBXHrkACP1QPjALcg3i3xzEQCZ");
 
-// pub const TIME_TO_REFUND: i64 = 60 * 60 * 24 * 7; // 1 week
-
-pub const USDC_TO_CREDIT: u64 = 100_000; // 1 USD -> 100.000 credits
+/// Length of a plan's monthly period, in seconds.
+pub const PLAN_PERIOD: i64 = 60 * 60 * 24 * 30;
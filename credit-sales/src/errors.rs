@@ -6,6 +6,18 @@ pub enum CreditSalesError {
     UninitializedAccount,
     InvalidAccountData,
     Unauthorized,
+    InvalidPda,
+    AccountAlreadyInitialized,
+    InsufficientCredits,
+    ProgramPaused,
+    RefundExceedsAvailable,
+    RefundWindowExpired,
+    InvalidTokenAccount,
+    InvalidTokenProgram,
+    InvalidMint,
+    UnsupportedMint,
+    InvalidPlan,
+    PlanActive,
 }
 
 impl From<CreditSalesError> for ProgramError {
@@ -0,0 +1,96 @@
+use pinocchio::{log::sol_log_data, pubkey::Pubkey};
+
+/// Emitted by `BuyCredits` as a `Program data:` log, so indexers can follow
+/// purchases without diffing accounts. Fields are little-endian, in order:
+///
+/// | discriminator | buyer | amount_usdc | credits | credits_amount | purchase_count | timestamp |
+/// |---------------|-------|-------------|---------|----------------|----------------|-----------|
+/// | 8             | 32    | u64         | u64     | u64            | u64            | i64       |
+pub struct CreditsPurchased<'a> {
+    pub buyer: &'a Pubkey,
+    /// Amount received by the treasury, net of any transfer fee
+    pub amount_usdc: u64,
+    /// Credits bought by this purchase
+    pub credits: u64,
+    /// Credits bought by the account over all purchases
+    pub credits_amount: u64,
+    pub purchase_count: u64,
+    pub timestamp: i64,
+}
+
+impl CreditsPurchased<'_> {
+    pub const DISCRIMINATOR: [u8; 8] = *b"crdtsbuy";
+
+    #[inline(always)]
+    pub fn emit(&self) {
+        sol_log_data(&[
+            &Self::DISCRIMINATOR,
+            self.buyer,
+            &self.amount_usdc.to_le_bytes(),
+            &self.credits.to_le_bytes(),
+            &self.credits_amount.to_le_bytes(),
+            &self.purchase_count.to_le_bytes(),
+            &self.timestamp.to_le_bytes(),
+        ]);
+    }
+}
+
+/// Emitted by `ConsumeCredits` for each account it settles. Fields are
+/// little-endian, in order:
+///
+/// | discriminator | buyer | credits | credits_consumed |
+/// |---------------|-------|---------|------------------|
+/// | 8             | 32    | u64     | u64              |
+pub struct CreditsConsumed<'a> {
+    pub buyer: &'a Pubkey,
+    /// Credits settled by this instruction
+    pub credits: u64,
+    /// Credits consumed by the account over all settlements
+    pub credits_consumed: u64,
+}
+
+impl CreditsConsumed<'_> {
+    pub const DISCRIMINATOR: [u8; 8] = *b"crdtsuse";
+
+    #[inline(always)]
+    pub fn emit(&self) {
+        sol_log_data(&[
+            &Self::DISCRIMINATOR,
+            self.buyer,
+            &self.credits.to_le_bytes(),
+            &self.credits_consumed.to_le_bytes(),
+        ]);
+    }
+}
+
+/// Emitted by `BuyPlan`. Fields are little-endian, in order:
+///
+/// | discriminator | buyer | plan_id | amount_usdc | monthly_quota | period_start | period_end |
+/// |---------------|-------|---------|-------------|---------------|--------------|------------|
+/// | 8             | 32    | u8      | u64         | u64           | i64          | i64        |
+pub struct PlanPurchased<'a> {
+    pub buyer: &'a Pubkey,
+    pub plan_id: u8,
+    /// Amount received by the treasury, net of any transfer fee
+    pub amount_usdc: u64,
+    pub monthly_quota: u64,
+    pub period_start: i64,
+    pub period_end: i64,
+}
+
+impl PlanPurchased<'_> {
+    pub const DISCRIMINATOR: [u8; 8] = *b"planbuy_";
+
+    #[inline(always)]
+    pub fn emit(&self) {
+        sol_log_data(&[
+            &Self::DISCRIMINATOR,
+            self.buyer,
+            &[self.plan_id],
+            &self.amount_usdc.to_le_bytes(),
+            &self.monthly_quota.to_le_bytes(),
+            &self.period_start.to_le_bytes(),
+            &self.period_end.to_le_bytes(),
+        ]);
+    }
+}
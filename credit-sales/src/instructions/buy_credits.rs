@@ -1,49 +1,113 @@
 use {
     crate::{
-        constants::{TREASURY, USDC_TO_CREDIT},
         errors::CreditSalesError,
+        events::CreditsPurchased,
+        parsing::InstructionData,
+        state::{Config, CreditsAccount},
+        token::{TokenAccountData, TransferChecked},
     },
     pinocchio::{
-        account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+        account_info::AccountInfo,
+        program_error::ProgramError,
+        sysvars::{clock::Clock, Sysvar},
+        ProgramResult,
     },
-    pinocchio_token::instructions::Transfer,
 };
 
+/// Buys credits with `amount_usdc` of the config's mint, at the config's
+/// price. With a Token-2022 transfer fee, credits are bought only with what
+/// reaches the treasury. The first purchase opens the refund window; top-ups
+/// don't extend it.
 pub fn process_buy_credits_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    if data.len() != 9 {
-        return Err(ProgramError::InvalidInstructionData);
-    }
+    let mut data = InstructionData::new(data);
+    let amount_usdc = data.read_u64()?;
+    data.finish()?;
 
-    let [buyer, _buyer_ta, treasury, credits_account, _clock_sysvar, _token_program] = accounts
+    let [buyer, buyer_ta, treasury, credits_account, _clock_sysvar, token_program, config, mint] =
+        accounts
     else {
-        return Err(ProgramError::NotEnoughAccountKeys);
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
     };
-    assert!(buyer.is_signer());
+    if !buyer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    if treasury.key() != &TREASURY {
+    let config = Config::from_account_info_unpaused(config)?;
+    if treasury.key() != &config.treasury() {
         return Err(CreditSalesError::InvalidAccountData.into());
     }
+    config.check_token_accounts(token_program, mint)?;
+
+    // Created by `InitializeCreditsAccount`, which set its owner and bump
+    let credits_account = CreditsAccount::from_account_info_for(credits_account, buyer.key())?;
+
+    if amount_usdc == 0 {
+        return Err(CreditSalesError::InvalidInstructionData.into());
+    }
 
-    let amount_usdc = unsafe { *(data.as_ptr().add(0) as *const u64) };
-    let bump = unsafe { *(data.as_ptr().add(8) as *const [u8; 1]) };
+    let treasury_balance = TokenAccountData::load(treasury, token_program.key())?.amount;
 
-    Transfer {
-        from: _buyer_ta,
+    TransferChecked {
+        token_program: token_program.key(),
+        from: buyer_ta,
+        mint,
         to: treasury,
         authority: buyer,
         amount: amount_usdc,
+        decimals: config.decimals(),
     }
-    .invoke()?; // 5,949 CUs -  Can happen off of the instruction???
-
-    let mut credits_account_data = credits_account.try_borrow_mut_data()?;
-    let credits_account_ptr = credits_account_data.as_mut_ptr();
-    unsafe {
-        *(credits_account_ptr.add(0) as *mut i64) = 0; // timestamp
-        *(credits_account_ptr.add(8) as *mut u64) = amount_usdc * USDC_TO_CREDIT; // credits_amount
-        *(credits_account_ptr.add(16) as *mut u64) = 0; // credits_amount_refunded
-        *(credits_account_ptr.add(24) as *mut [u8; 1]) = bump; // bump
-        *(credits_account_ptr.add(25) as *mut Pubkey) = *buyer.key(); // owner
+    .invoke()?;
+
+    // Net of any transfer fee withheld by the mint
+    let amount_received = TokenAccountData::load(treasury, token_program.key())?
+        .amount
+        .checked_sub(treasury_balance)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if amount_received == 0 {
+        return Err(CreditSalesError::InvalidInstructionData.into());
+    }
+
+    // Purchases top the account up rather than replacing earlier ones
+    let credits = amount_received
+        .checked_mul(config.credits_per_usdc())
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let credits_amount = credits_account
+        .credits_amount()
+        .checked_add(credits)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let purchase_count = credits_account
+        .purchase_count()
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let amount_paid = credits_account
+        .amount_paid()
+        .checked_add(amount_received)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let timestamp = Clock::get()?.unix_timestamp;
+
+    if credits_account.purchase_count() == 0 {
+        credits_account.set_first_purchase_at(timestamp);
+        credits_account.set_mint(mint.key());
+    } else if &credits_account.mint() != mint.key() {
+        // Credits paid in different mints have no single price to refund
+        // them at, so the window closes for good
+        credits_account.set_first_purchase_at(i64::MIN);
+        credits_account.set_mint(mint.key());
+    }
+    credits_account.set_timestamp(timestamp);
+    credits_account.set_credits_amount(credits_amount);
+    credits_account.set_purchase_count(purchase_count);
+    credits_account.set_amount_paid(amount_paid);
+
+    CreditsPurchased {
+        buyer: buyer.key(),
+        amount_usdc: amount_received,
+        credits,
+        credits_amount,
+        purchase_count,
+        timestamp,
     }
+    .emit();
 
     Ok(())
 }
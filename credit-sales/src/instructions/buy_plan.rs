@@ -0,0 +1,142 @@
+use {
+    crate::{
+        constants::PLAN_PERIOD,
+        errors::CreditSalesError,
+        events::PlanPurchased,
+        parsing::InstructionData,
+        state::{Config, Plan, PlanAccount},
+        token::{TokenAccountData, TransferChecked},
+        utils::create_pda_account,
+    },
+    pinocchio::{
+        account_info::AccountInfo,
+        instruction::{Seed, Signer},
+        program_error::ProgramError,
+        pubkey::find_program_address,
+        sysvars::{clock::Clock, Sysvar},
+        ProgramResult,
+    },
+};
+
+/// Pays for a period of a plan set by `SetPlan`, creating the buyer's
+/// `["plan_account", buyer]` PDA on their first purchase. Buying the active
+/// plan again extends it by a period; another plan can only be bought once
+/// the active one has ended.
+///
+/// `auto_renew` is only recorded for the server, which reminds the buyer to
+/// renew; funds never move without their signature.
+/// Accounts: the buyer, their token account, the treasury, the plan account,
+/// the system program, the token program, the config, the mint and the plan.
+/// Data: the plan account's canonical bump, the plan id (u8) and auto_renew
+/// (u8).
+pub fn process_buy_plan_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let mut data = InstructionData::new(data);
+    let bump = data.read_u8()?;
+    let plan_id = data.read_u8()?;
+    let auto_renew = data.read_bool()?;
+    data.finish()?;
+
+    let [buyer, buyer_ta, treasury, plan_account, _system_program, token_program, config, mint, plan] =
+        accounts
+    else {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
+    };
+    if !buyer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = Config::from_account_info_unpaused(config)?;
+    if treasury.key() != &config.treasury() {
+        return Err(CreditSalesError::InvalidAccountData.into());
+    }
+    config.check_token_accounts(token_program, mint)?;
+
+    let plan = Plan::from_account_info(plan, plan_id)?;
+    if plan.price() == 0 {
+        return Err(CreditSalesError::InvalidPlan.into());
+    }
+
+    let (pda, canonical_bump) = find_program_address(&[PlanAccount::SEED, buyer.key()], &crate::ID);
+    if plan_account.key() != &pda || bump != canonical_bump {
+        return Err(CreditSalesError::InvalidPda.into());
+    }
+
+    if *plan_account.owner() == pinocchio_system::ID {
+        let bump_seed = [bump];
+        let seeds = [
+            Seed::from(PlanAccount::SEED),
+            Seed::from(buyer.key()),
+            Seed::from(&bump_seed),
+        ];
+        create_pda_account(
+            buyer,
+            plan_account,
+            PlanAccount::LEN,
+            &crate::ID,
+            &[Signer::from(&seeds)],
+        )?;
+
+        let plan_account = PlanAccount::from_account_info_unchecked(plan_account);
+        plan_account.set_bump(bump);
+        plan_account.set_owner(buyer.key());
+    }
+    let plan_account = PlanAccount::from_account_info_for(plan_account, buyer.key())?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let (period_start, period_end) = if plan_account.is_active(now) {
+        if plan_account.plan_id() != plan_id {
+            return Err(CreditSalesError::PlanActive.into());
+        }
+        (plan_account.period_start(), plan_account.period_end())
+    } else {
+        (now, now)
+    };
+    let period_end = period_end
+        .checked_add(PLAN_PERIOD)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    let price = 10u64
+        .checked_pow(config.decimals() as u32)
+        .and_then(|unit| plan.price().checked_mul(unit))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    let treasury_balance = TokenAccountData::load(treasury, token_program.key())?.amount;
+
+    TransferChecked {
+        token_program: token_program.key(),
+        from: buyer_ta,
+        mint,
+        to: treasury,
+        authority: buyer,
+        amount: price,
+        decimals: config.decimals(),
+    }
+    .invoke()?;
+
+    // Net of any transfer fee withheld by the mint
+    let amount_received = TokenAccountData::load(treasury, token_program.key())?
+        .amount
+        .checked_sub(treasury_balance)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let monthly_quota = amount_received
+        .checked_mul(config.credits_per_usdc())
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    plan_account.set_plan_id(plan_id);
+    plan_account.set_auto_renew(auto_renew);
+    plan_account.set_period_start(period_start);
+    plan_account.set_period_end(period_end);
+    plan_account.set_monthly_quota(monthly_quota);
+
+    PlanPurchased {
+        buyer: buyer.key(),
+        plan_id,
+        amount_usdc: amount_received,
+        monthly_quota,
+        period_start,
+        period_end,
+    }
+    .emit();
+
+    Ok(())
+}
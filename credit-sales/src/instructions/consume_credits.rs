@@ -0,0 +1,67 @@
+use {
+    crate::{
+        errors::CreditSalesError,
+        events::CreditsConsumed,
+        parsing::InstructionData,
+        state::{Config, CreditsAccount},
+    },
+    pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult},
+};
+
+/// Settles off-chain usage for a batch of credits accounts.
+/// Accounts: the settlement authority, the config, then the credits accounts.
+/// Data: for each credits account, its new total of consumed credits (u64).
+///
+/// Totals are cumulative so a settlement can be retried safely: a total at or
+/// below what the account already records has been settled and is skipped.
+pub fn process_consume_credits_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority, config, credits_accounts @ ..] = accounts else {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
+    };
+    if credits_accounts.is_empty() {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
+    }
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let config = Config::from_account_info_unpaused(config)?;
+    if authority.key() != &config.settlement_authority() {
+        return Err(CreditSalesError::Unauthorized.into());
+    }
+
+    // A failed read aborts the whole batch, settled accounts included
+    let mut data = InstructionData::new(data);
+    for credits_account in credits_accounts {
+        let credits_consumed = data.read_u64()?;
+
+        // Settled accounts must still be a buyer's PDA
+        let owner = CreditsAccount::from_account_info(credits_account)?.owner();
+        let credits_account = CreditsAccount::from_account_info_for(credits_account, &owner)?;
+
+        let settled = credits_account.credits_consumed();
+        if credits_consumed <= settled {
+            continue;
+        }
+
+        // Refunded credits can't be consumed
+        let available = credits_account
+            .credits_amount()
+            .saturating_sub(credits_account.credits_amount_refunded());
+        if credits_consumed > available {
+            return Err(CreditSalesError::InsufficientCredits.into());
+        }
+
+        credits_account.set_credits_consumed(credits_consumed);
+
+        CreditsConsumed {
+            buyer: &owner,
+            credits: credits_consumed - settled,
+            credits_consumed,
+        }
+        .emit();
+    }
+    data.finish()?;
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use {
+    crate::{
+        constants::ADMIN,
+        errors::CreditSalesError,
+        parsing::InstructionData,
+        state::Config,
+        token::{check_token_program, mint_decimals},
+        utils::{create_or_check_treasury, create_pda_account},
+    },
+    pinocchio::{
+        account_info::AccountInfo,
+        instruction::{Seed, Signer},
+        program_error::ProgramError,
+        pubkey::find_program_address,
+        ProgramResult,
+    },
+};
+
+/// Creates the `["config"]` PDA, once, signed and funded by the deployer's
+/// `ADMIN` key, which becomes the first admin. Creates the mint's treasury
+/// too if it doesn't exist yet.
+/// Accounts: the admin, the config, the treasury, the mint, the system
+/// program and the mint's token program, legacy or Token-2022, which
+/// transfers then go through.
+/// Data: the config's and the treasury's canonical bumps, credits_per_usdc
+/// (u64), the settlement authority and the refund window in seconds (i64).
+pub fn process_initialize_config_instruction(
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let mut data = InstructionData::new(data);
+    let bump = data.read_u8()?;
+    let treasury_bump = data.read_u8()?;
+    let credits_per_usdc = data.read_u64()?;
+    let settlement_authority = data.read_pubkey()?;
+    let refund_window = data.read_i64()?;
+    data.finish()?;
+
+    let [admin, config, treasury, mint, _system_program, token_program] = accounts else {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
+    };
+    if !admin.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if admin.key() != &ADMIN {
+        return Err(CreditSalesError::Unauthorized.into());
+    }
+    if credits_per_usdc == 0 || refund_window < 0 {
+        return Err(CreditSalesError::InvalidInstructionData.into());
+    }
+
+    let (pda, canonical_bump) = find_program_address(&[Config::SEED], &crate::ID);
+    if config.key() != &pda || bump != canonical_bump {
+        return Err(CreditSalesError::InvalidPda.into());
+    }
+    let (treasury_pda, canonical_treasury_bump) =
+        find_program_address(&[Config::TREASURY_SEED, mint.key()], &crate::ID);
+    if treasury.key() != &treasury_pda || treasury_bump != canonical_treasury_bump {
+        return Err(CreditSalesError::InvalidPda.into());
+    }
+
+    if *config.owner() == crate::ID {
+        return Err(CreditSalesError::AccountAlreadyInitialized.into());
+    }
+    if *config.owner() != pinocchio_system::ID {
+        return Err(CreditSalesError::InvalidAccountData.into());
+    }
+
+    check_token_program(token_program.key())?;
+    let decimals = mint_decimals(mint, token_program.key())?;
+    create_or_check_treasury(admin, treasury, mint, token_program.key(), treasury_bump)?;
+
+    let bump_seed = [bump];
+    let seeds = [Seed::from(Config::SEED), Seed::from(&bump_seed)];
+    create_pda_account(
+        admin,
+        config,
+        Config::LEN,
+        &crate::ID,
+        &[Signer::from(&seeds)],
+    )?;
+
+    let config = Config::from_account_info_unchecked(config);
+    config.set_admin(admin.key());
+    config.set_treasury(treasury.key());
+    config.set_mint(mint.key());
+    config.set_token_program(token_program.key());
+    config.set_decimals(decimals);
+    config.set_settlement_authority(settlement_authority);
+    config.set_credits_per_usdc(credits_per_usdc);
+    config.set_paused(false);
+    config.set_bump(bump);
+    config.set_treasury_bump(treasury_bump);
+    config.set_refund_window(refund_window);
+
+    Ok(())
+}
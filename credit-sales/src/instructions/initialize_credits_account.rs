@@ -0,0 +1,67 @@
+use {
+    crate::{
+        errors::CreditSalesError,
+        parsing::InstructionData,
+        state::{Config, CreditsAccount},
+        utils::create_pda_account,
+    },
+    pinocchio::{
+        account_info::AccountInfo,
+        instruction::{Seed, Signer},
+        program_error::ProgramError,
+        pubkey::find_program_address,
+        ProgramResult,
+    },
+};
+
+/// Creates the buyer's `["credits_account", buyer]` PDA, funded by the buyer.
+/// Data: the PDA's canonical bump.
+pub fn process_initialize_credits_account_instruction(
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let mut data = InstructionData::new(data);
+    let bump = data.read_u8()?;
+    data.finish()?;
+
+    let [buyer, credits_account, _system_program, config] = accounts else {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
+    };
+    if !buyer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Config::from_account_info_unpaused(config)?;
+
+    let (pda, canonical_bump) =
+        find_program_address(&[CreditsAccount::SEED, buyer.key()], &crate::ID);
+    if credits_account.key() != &pda || bump != canonical_bump {
+        return Err(CreditSalesError::InvalidPda.into());
+    }
+
+    if *credits_account.owner() == crate::ID {
+        return Err(CreditSalesError::AccountAlreadyInitialized.into());
+    }
+    if *credits_account.owner() != pinocchio_system::ID {
+        return Err(CreditSalesError::InvalidAccountData.into());
+    }
+
+    let bump_seed = [bump];
+    let seeds = [
+        Seed::from(CreditsAccount::SEED),
+        Seed::from(buyer.key()),
+        Seed::from(&bump_seed),
+    ];
+    create_pda_account(
+        buyer,
+        credits_account,
+        CreditsAccount::LEN,
+        &crate::ID,
+        &[Signer::from(&seeds)],
+    )?;
+
+    let credits_account = CreditsAccount::from_account_info_unchecked(credits_account);
+    credits_account.set_bump(bump);
+    credits_account.set_owner(buyer.key());
+
+    Ok(())
+}
@@ -1,9 +1,23 @@
 pub mod buy_credits;
 pub use buy_credits::process_buy_credits_instruction;
+pub mod buy_plan;
+pub use buy_plan::process_buy_plan_instruction;
+pub mod consume_credits;
+pub use consume_credits::process_consume_credits_instruction;
+pub mod initialize_config;
+pub use initialize_config::process_initialize_config_instruction;
+pub mod initialize_credits_account;
+pub use initialize_credits_account::process_initialize_credits_account_instruction;
 pub mod refund_credits;
 pub use refund_credits::process_refund_credits_instruction;
+pub mod set_plan;
+pub use set_plan::process_set_plan_instruction;
+pub mod transfer_admin;
+pub use transfer_admin::process_transfer_admin_instruction;
+pub mod update_config;
+pub use update_config::process_update_config_instruction;
 pub mod withdraw_usdc;
-use pinocchio::program_error::ProgramError;
+use {crate::errors::CreditSalesError, pinocchio::program_error::ProgramError};
 pub use withdraw_usdc::process_withdraw_usdc_instruction;
 
 #[derive(Clone, Copy, Debug)]
@@ -11,6 +25,13 @@ pub enum CreditSalesInstruction {
     BuyCredits = 0,
     RefundCredits = 1,
     WithdrawUSDC = 2,
+    InitializeCreditsAccount = 3,
+    ConsumeCredits = 4,
+    InitializeConfig = 5,
+    UpdateConfig = 6,
+    TransferAdmin = 7,
+    BuyPlan = 8,
+    SetPlan = 9,
 }
 
 impl TryFrom<&u8> for CreditSalesInstruction {
@@ -21,7 +42,14 @@ impl TryFrom<&u8> for CreditSalesInstruction {
             0 => Ok(Self::BuyCredits),
             1 => Ok(Self::RefundCredits),
             2 => Ok(Self::WithdrawUSDC),
-            _ => Err(ProgramError::InvalidInstructionData),
+            3 => Ok(Self::InitializeCreditsAccount),
+            4 => Ok(Self::ConsumeCredits),
+            5 => Ok(Self::InitializeConfig),
+            6 => Ok(Self::UpdateConfig),
+            7 => Ok(Self::TransferAdmin),
+            8 => Ok(Self::BuyPlan),
+            9 => Ok(Self::SetPlan),
+            _ => Err(CreditSalesError::InvalidInstructionData.into()),
         }
     }
 }
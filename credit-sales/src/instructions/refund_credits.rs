@@ -1,52 +1,104 @@
 use {
-    crate::{constants::USDC_TO_CREDIT, state::CreditsAccount},
+    crate::{
+        errors::CreditSalesError,
+        parsing::InstructionData,
+        state::{Config, CreditsAccount},
+        token::{TokenAccountData, TransferChecked},
+    },
     pinocchio::{
         account_info::AccountInfo,
         instruction::{Seed, Signer},
         program_error::ProgramError,
+        sysvars::{clock::Clock, Sysvar},
         ProgramResult,
     },
-    pinocchio_token::instructions::Transfer,
 };
 
+/// Pays credits back to the buyer in the mint they paid in, at the average
+/// price they paid. Signed by the admin, within the config's refund window
+/// after the buyer's first purchase, and for no more credits than are neither
+/// refunded nor consumed.
+/// A Token-2022 transfer fee is withheld from what the buyer receives.
+/// Data: the credits to refund (u64), worth a whole number of base units at
+/// the price paid.
 pub fn process_refund_credits_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    if data.len() != 9 {
-        return Err(ProgramError::InvalidInstructionData);
-    }
+    let mut data = InstructionData::new(data);
+    let amount_of_credits = data.read_u64()?;
+    data.finish()?;
 
-    let [admin, buyer, buyer_ta, treasury, credits_account, _token_program] = accounts else {
-        return Err(ProgramError::NotEnoughAccountKeys);
+    let [admin, buyer, buyer_ta, treasury, credits_account, token_program, config, mint] = accounts
+    else {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
     };
-    assert!(admin.is_signer());
+    if !admin.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    let credits_account_data = CreditsAccount::from_account_info(credits_account)?;
-    assert_eq!(buyer.key(), &credits_account_data.owner(),);
+    let config = Config::from_account_info(config)?;
+    if admin.key() != &config.admin() {
+        return Err(CreditSalesError::Unauthorized.into());
+    }
+    if treasury.key() != &config.treasury() {
+        return Err(CreditSalesError::InvalidAccountData.into());
+    }
+    config.check_token_accounts(token_program, mint)?;
 
-    let amount_of_credits = unsafe { *(data.as_ptr().add(0) as *const u64) }; // 8 bytes
-    let bump = unsafe { *(data.as_ptr().add(8)) }; // 1 byte
+    let buyer_token_account = TokenAccountData::load(buyer_ta, token_program.key())?;
+    if &buyer_token_account.owner != buyer.key() || &buyer_token_account.mint != mint.key() {
+        return Err(CreditSalesError::InvalidTokenAccount.into());
+    }
+
+    let credits_account = CreditsAccount::from_account_info_for(credits_account, buyer.key())?;
+
+    // The treasury of another mint can't pay back what was paid in this one
+    if &credits_account.mint() != mint.key() {
+        return Err(CreditSalesError::InvalidMint.into());
+    }
 
-    let binding = [bump];
+    if amount_of_credits == 0 {
+        return Err(CreditSalesError::InvalidInstructionData.into());
+    }
+    if amount_of_credits > credits_account.credits_available() {
+        return Err(CreditSalesError::RefundExceedsAvailable.into());
+    }
+
+    // Price paid over all purchases, which a later config price doesn't change
+    let refund = amount_of_credits as u128 * credits_account.amount_paid() as u128;
+    let credits_amount = credits_account.credits_amount() as u128;
+    if refund == 0 || refund % credits_amount != 0 {
+        return Err(CreditSalesError::InvalidInstructionData.into());
+    }
+    let amount =
+        u64::try_from(refund / credits_amount).map_err(|_| ProgramError::ArithmeticOverflow)?;
+
+    let elapsed = Clock::get()?
+        .unix_timestamp
+        .saturating_sub(credits_account.first_purchase_at());
+    if elapsed > config.refund_window() {
+        return Err(CreditSalesError::RefundWindowExpired.into());
+    }
+
+    let bump = [config.treasury_bump()];
     let seeds = [
-        Seed::from(b"treasury"),
-        Seed::from(admin.key()),
-        Seed::from(&binding[..]),
+        Seed::from(Config::TREASURY_SEED),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
     ];
     let signer = [Signer::from(&seeds)];
 
-    Transfer {
+    TransferChecked {
+        token_program: token_program.key(),
         from: treasury,
+        mint,
         to: buyer_ta,
         authority: treasury,
-        amount: amount_of_credits / USDC_TO_CREDIT,
+        amount,
+        decimals: config.decimals(),
     }
     .invoke_signed(&signer)?;
 
-    unsafe {
-        *(credits_account
-            .borrow_mut_data_unchecked()
-            .as_mut_ptr()
-            .add(16) as *mut u64) += amount_of_credits; // credits_amount_refunded
-    }
+    credits_account
+        .set_credits_amount_refunded(credits_account.credits_amount_refunded() + amount_of_credits);
 
     Ok(())
 }
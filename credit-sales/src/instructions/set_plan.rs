@@ -0,0 +1,66 @@
+use {
+    crate::{
+        errors::CreditSalesError,
+        parsing::InstructionData,
+        state::{Config, Plan},
+        utils::create_pda_account,
+    },
+    pinocchio::{
+        account_info::AccountInfo,
+        instruction::{Seed, Signer},
+        program_error::ProgramError,
+        pubkey::find_program_address,
+        ProgramResult,
+    },
+};
+
+/// Sets the price of plan `plan_id`, creating its `["plan", [plan_id]]` PDA,
+/// funded by the admin, the first time. A price of 0 withdraws the plan from
+/// sale; periods already paid for run to their end.
+/// Accounts: the admin, the config, the plan and the system program.
+/// Data: the plan's canonical bump, the plan id (u8) and the price of
+/// a period in whole tokens of the config's mint (u64).
+pub fn process_set_plan_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let mut data = InstructionData::new(data);
+    let bump = data.read_u8()?;
+    let plan_id = data.read_u8()?;
+    let price = data.read_u64()?;
+    data.finish()?;
+
+    let [admin, config, plan, _system_program] = accounts else {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
+    };
+    if !admin.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = Config::from_account_info(config)?;
+    if admin.key() != &config.admin() {
+        return Err(CreditSalesError::Unauthorized.into());
+    }
+
+    let (pda, canonical_bump) = find_program_address(&[Plan::SEED, &[plan_id]], &crate::ID);
+    if plan.key() != &pda || bump != canonical_bump {
+        return Err(CreditSalesError::InvalidPda.into());
+    }
+
+    if *plan.owner() == pinocchio_system::ID {
+        let plan_id_seed = [plan_id];
+        let bump_seed = [bump];
+        let seeds = [
+            Seed::from(Plan::SEED),
+            Seed::from(&plan_id_seed),
+            Seed::from(&bump_seed),
+        ];
+        create_pda_account(admin, plan, Plan::LEN, &crate::ID, &[Signer::from(&seeds)])?;
+
+        let plan = Plan::from_account_info_unchecked(plan);
+        plan.set_bump(bump);
+        plan.set_plan_id(plan_id);
+    }
+    let plan = Plan::from_account_info(plan, plan_id)?;
+
+    plan.set_price(price);
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use {
+    crate::{errors::CreditSalesError, parsing::InstructionData, state::Config},
+    pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult},
+};
+
+/// Hands the admin role over. Both the current and the new admin sign, so the
+/// role can't be sent to a key nobody holds.
+/// Accounts: the admin, the config and the new admin.
+pub fn process_transfer_admin_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    InstructionData::new(data).finish()?;
+
+    let [admin, config, new_admin] = accounts else {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
+    };
+    if !admin.is_signer() || !new_admin.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = Config::from_account_info(config)?;
+    if admin.key() != &config.admin() {
+        return Err(CreditSalesError::Unauthorized.into());
+    }
+
+    config.set_admin(new_admin.key());
+
+    Ok(())
+}
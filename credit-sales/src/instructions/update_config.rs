@@ -0,0 +1,67 @@
+use {
+    crate::{
+        errors::CreditSalesError,
+        parsing::InstructionData,
+        state::Config,
+        token::{check_token_program, mint_decimals},
+        utils::create_or_check_treasury,
+    },
+    pinocchio::{
+        account_info::AccountInfo, program_error::ProgramError, pubkey::find_program_address,
+        ProgramResult,
+    },
+};
+
+/// Replaces the config's mint (and with it the treasury, token program and
+/// decimals), settlement authority, price, pause flag and refund window.
+/// Signed by the admin, who funds the new mint's treasury if it doesn't
+/// exist yet. Funds left in a previous mint's treasury stay there.
+/// Accounts: the admin, the config, the treasury, the mint, the system
+/// program and the mint's token program.
+/// Data: the treasury's canonical bump, credits_per_usdc (u64), paused (u8),
+/// the settlement authority and the refund window in seconds (i64).
+pub fn process_update_config_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let mut data = InstructionData::new(data);
+    let treasury_bump = data.read_u8()?;
+    let credits_per_usdc = data.read_u64()?;
+    let paused = data.read_bool()?;
+    let settlement_authority = data.read_pubkey()?;
+    let refund_window = data.read_i64()?;
+    data.finish()?;
+
+    let [admin, config, treasury, mint, _system_program, token_program] = accounts else {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
+    };
+    if !admin.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = Config::from_account_info(config)?;
+    if admin.key() != &config.admin() {
+        return Err(CreditSalesError::Unauthorized.into());
+    }
+    if credits_per_usdc == 0 || refund_window < 0 {
+        return Err(CreditSalesError::InvalidInstructionData.into());
+    }
+
+    let (treasury_pda, canonical_treasury_bump) =
+        find_program_address(&[Config::TREASURY_SEED, mint.key()], &crate::ID);
+    if treasury.key() != &treasury_pda || treasury_bump != canonical_treasury_bump {
+        return Err(CreditSalesError::InvalidPda.into());
+    }
+    check_token_program(token_program.key())?;
+    let decimals = mint_decimals(mint, token_program.key())?;
+    create_or_check_treasury(admin, treasury, mint, token_program.key(), treasury_bump)?;
+
+    config.set_treasury(treasury.key());
+    config.set_mint(mint.key());
+    config.set_token_program(token_program.key());
+    config.set_decimals(decimals);
+    config.set_treasury_bump(treasury_bump);
+    config.set_settlement_authority(settlement_authority);
+    config.set_credits_per_usdc(credits_per_usdc);
+    config.set_paused(paused);
+    config.set_refund_window(refund_window);
+
+    Ok(())
+}
@@ -1,40 +1,53 @@
 use {
+    crate::{
+        errors::CreditSalesError, parsing::InstructionData, state::Config, token::TransferChecked,
+    },
     pinocchio::{
         account_info::AccountInfo,
         instruction::{Seed, Signer},
         program_error::ProgramError,
         ProgramResult,
     },
-    pinocchio_token::instructions::Transfer,
 };
 
 pub fn process_withdraw_usdc_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    if data.len() != 9 {
-        return Err(ProgramError::InvalidInstructionData);
-    }
+    let mut data = InstructionData::new(data);
+    let amount = data.read_u64()?;
+    data.finish()?;
 
-    let amount = unsafe { *(data.as_ptr().add(0) as *const u64) };
-    let bump = unsafe { *(data.as_ptr().add(8)) };
-
-    let [treasury_account, admin, admin_usdc_account, _token_program] = accounts else {
-        return Err(ProgramError::NotEnoughAccountKeys);
+    let [treasury_account, admin, admin_usdc_account, token_program, config, mint] = accounts
+    else {
+        return Err(CreditSalesError::NotEnoughAccountKeys.into());
     };
-    // assert_eq!(admin.key(), &ADMIN); // Necessary?
-    assert!(admin.is_signer()); // Necessary
+    if !admin.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = Config::from_account_info(config)?;
+    if admin.key() != &config.admin() {
+        return Err(CreditSalesError::Unauthorized.into());
+    }
+    if treasury_account.key() != &config.treasury() {
+        return Err(CreditSalesError::InvalidAccountData.into());
+    }
+    config.check_token_accounts(token_program, mint)?;
 
-    let binding = [bump];
+    let bump = [config.treasury_bump()];
     let seeds = [
-        Seed::from(b"treasury"),
-        Seed::from(admin.key()),
-        Seed::from(&binding),
+        Seed::from(Config::TREASURY_SEED),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
     ];
     let signer = [Signer::from(&seeds)];
 
-    let transfer_instruction = Transfer {
+    let transfer_instruction = TransferChecked {
+        token_program: token_program.key(),
         from: treasury_account,
+        mint,
         to: admin_usdc_account,
         authority: treasury_account,
         amount,
+        decimals: config.decimals(),
     };
     transfer_instruction.invoke_signed(&signer)?;
 
@@ -7,7 +7,11 @@ mod instructions;
 use instructions::*;
 mod constants;
 mod errors;
+mod events;
+mod parsing;
 mod state;
+mod token;
+mod utils;
 
 #[cfg(test)]
 mod tests;
@@ -21,11 +25,13 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    assert_eq!(program_id, &ID);
+    if program_id != &ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
     let (instruction_discriminant, instruction_data) = instruction_data
         .split_first()
-        .ok_or(ProgramError::InvalidInstructionData)?;
+        .ok_or(errors::CreditSalesError::InvalidInstructionData)?;
 
     let instruction = CreditSalesInstruction::try_from(instruction_discriminant)?;
 
@@ -39,5 +45,26 @@ pub fn process_instruction(
         instructions::CreditSalesInstruction::WithdrawUSDC => {
             process_withdraw_usdc_instruction(accounts, instruction_data)
         }
+        instructions::CreditSalesInstruction::InitializeCreditsAccount => {
+            process_initialize_credits_account_instruction(accounts, instruction_data)
+        }
+        instructions::CreditSalesInstruction::ConsumeCredits => {
+            process_consume_credits_instruction(accounts, instruction_data)
+        }
+        instructions::CreditSalesInstruction::InitializeConfig => {
+            process_initialize_config_instruction(accounts, instruction_data)
+        }
+        instructions::CreditSalesInstruction::UpdateConfig => {
+            process_update_config_instruction(accounts, instruction_data)
+        }
+        instructions::CreditSalesInstruction::TransferAdmin => {
+            process_transfer_admin_instruction(accounts, instruction_data)
+        }
+        instructions::CreditSalesInstruction::BuyPlan => {
+            process_buy_plan_instruction(accounts, instruction_data)
+        }
+        instructions::CreditSalesInstruction::SetPlan => {
+            process_set_plan_instruction(accounts, instruction_data)
+        }
     }
 }
@@ -0,0 +1,106 @@
+use {
+    crate::errors::CreditSalesError,
+    pinocchio::{program_error::ProgramError, pubkey::Pubkey},
+};
+
+/// Cursor over instruction data. Reads are little-endian and copy out of the
+/// byte slice, so they don't depend on its alignment; a read past the end or
+/// bytes left over fail with `InvalidInstructionData`.
+pub struct InstructionData<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> InstructionData<'a> {
+    #[inline(always)]
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    #[inline(always)]
+    fn take<const N: usize>(&mut self) -> Result<&'a [u8; N], ProgramError> {
+        let (bytes, rest) = self
+            .data
+            .split_first_chunk::<N>()
+            .ok_or(CreditSalesError::InvalidInstructionData)?;
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    #[inline(always)]
+    pub fn read_u8(&mut self) -> Result<u8, ProgramError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    /// A `0` or `1` byte.
+    #[inline(always)]
+    pub fn read_bool(&mut self) -> Result<bool, ProgramError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CreditSalesError::InvalidInstructionData.into()),
+        }
+    }
+
+    #[inline(always)]
+    pub fn read_u64(&mut self) -> Result<u64, ProgramError> {
+        Ok(u64::from_le_bytes(*self.take()?))
+    }
+
+    #[inline(always)]
+    pub fn read_i64(&mut self) -> Result<i64, ProgramError> {
+        Ok(i64::from_le_bytes(*self.take()?))
+    }
+
+    #[inline(always)]
+    pub fn read_pubkey(&mut self) -> Result<&'a Pubkey, ProgramError> {
+        self.take()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Rejects data longer than what was read.
+    #[inline(always)]
+    pub fn finish(self) -> Result<(), ProgramError> {
+        if !self.is_empty() {
+            return Err(CreditSalesError::InvalidInstructionData.into());
+        }
+        Ok(())
+    }
+}
+
+/// Little-endian field accessors for account data whose length was checked
+/// against the account's `LEN` when it was loaded.
+#[inline(always)]
+pub fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(read_array(data, offset))
+}
+
+#[inline(always)]
+pub fn write_u64(data: &mut [u8], offset: usize, value: u64) {
+    data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+#[inline(always)]
+pub fn read_i64(data: &[u8], offset: usize) -> i64 {
+    i64::from_le_bytes(read_array(data, offset))
+}
+
+#[inline(always)]
+pub fn write_i64(data: &mut [u8], offset: usize, value: i64) {
+    data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+#[inline(always)]
+pub fn read_array<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    let mut array = [0; N];
+    array.copy_from_slice(&data[offset..offset + N]);
+    array
+}
+
+#[inline(always)]
+pub fn write_array<const N: usize>(data: &mut [u8], offset: usize, value: &[u8; N]) {
+    data[offset..offset + N].copy_from_slice(value);
+}
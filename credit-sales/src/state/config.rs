@@ -0,0 +1,208 @@
+use {
+    crate::{
+        errors::CreditSalesError,
+        parsing::{read_array, read_i64, read_u64, write_array, write_i64, write_u64},
+    },
+    core::slice::{from_raw_parts, from_raw_parts_mut},
+    pinocchio::{
+        account_info::AccountInfo,
+        program_error::ProgramError,
+        pubkey::{create_program_address, Pubkey},
+    },
+};
+
+/// Program-wide settings, created once by `InitializeConfig`.
+pub struct Config(*mut u8);
+
+impl Config {
+    pub const LEN: usize = 32  // admin [u8; 32]
+                         + 32  // treasury [u8; 32], `["treasury", mint]` token account
+                         + 32  // mint [u8; 32], accepted for purchases
+                         + 32  // settlement_authority [u8; 32], signs `ConsumeCredits`
+                         + 8   // credits_per_usdc u64
+                         + 1   // paused bool
+                         + 1   // bump [u8; 1]
+                         + 1   // treasury_bump [u8; 1]
+                         + 8   // refund_window i64, seconds after the first purchase
+                         + 32  // token_program [u8; 32], legacy or Token-2022
+                         + 1; // decimals u8, of the mint
+
+    /// PDA seeds are `[SEED]`.
+    pub const SEED: &'static [u8] = b"config";
+
+    /// Treasury PDA seeds are `[TREASURY_SEED, mint]`. The treasury token
+    /// account is its own authority and doesn't depend on the admin, so
+    /// transferring the admin role keeps the funds reachable.
+    pub const TREASURY_SEED: &'static [u8] = b"treasury";
+
+    #[inline(always)]
+    pub fn from_account_info_unchecked(account_info: &AccountInfo) -> Self {
+        unsafe { Self(account_info.borrow_mut_data_unchecked().as_mut_ptr()) }
+    }
+
+    /// Loads the program's config, rejecting any other account.
+    #[inline(always)]
+    pub fn from_account_info(account_info: &AccountInfo) -> Result<Self, ProgramError> {
+        if account_info.data_len() == 0 {
+            return Err(CreditSalesError::UninitializedAccount.into());
+        }
+        if *account_info.owner() != crate::ID || account_info.data_len() != Self::LEN {
+            return Err(CreditSalesError::InvalidAccountData.into());
+        }
+
+        let config = Self::from_account_info_unchecked(account_info);
+        let pda = create_program_address(&[Self::SEED, &[config.bump()]], &crate::ID)?;
+        if account_info.key() != &pda {
+            return Err(CreditSalesError::InvalidPda.into());
+        }
+
+        Ok(config)
+    }
+
+    /// Loads the config of instructions that are disabled while paused.
+    #[inline(always)]
+    pub fn from_account_info_unpaused(account_info: &AccountInfo) -> Result<Self, ProgramError> {
+        let config = Self::from_account_info(account_info)?;
+        if config.paused() {
+            return Err(CreditSalesError::ProgramPaused.into());
+        }
+        Ok(config)
+    }
+
+    /// Checks the token program and mint passed to an instruction that
+    /// transfers from or to the treasury.
+    #[inline(always)]
+    pub fn check_token_accounts(
+        &self,
+        token_program: &AccountInfo,
+        mint: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        if token_program.key() != &self.token_program() {
+            return Err(CreditSalesError::InvalidTokenProgram.into());
+        }
+        if mint.key() != &self.mint() {
+            return Err(CreditSalesError::InvalidMint.into());
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn data(&self) -> &[u8] {
+        unsafe { from_raw_parts(self.0, Self::LEN) }
+    }
+
+    #[inline(always)]
+    fn with_data_mut(&self, write: impl FnOnce(&mut [u8])) {
+        write(unsafe { from_raw_parts_mut(self.0, Self::LEN) })
+    }
+
+    #[inline(always)]
+    pub fn admin(&self) -> Pubkey {
+        read_array(self.data(), 0)
+    }
+
+    #[inline(always)]
+    pub fn set_admin(&self, admin: &Pubkey) {
+        self.with_data_mut(|data| write_array(data, 0, admin))
+    }
+
+    #[inline(always)]
+    pub fn treasury(&self) -> Pubkey {
+        read_array(self.data(), 32)
+    }
+
+    #[inline(always)]
+    pub fn set_treasury(&self, treasury: &Pubkey) {
+        self.with_data_mut(|data| write_array(data, 32, treasury))
+    }
+
+    #[inline(always)]
+    pub fn mint(&self) -> Pubkey {
+        read_array(self.data(), 64)
+    }
+
+    #[inline(always)]
+    pub fn set_mint(&self, mint: &Pubkey) {
+        self.with_data_mut(|data| write_array(data, 64, mint))
+    }
+
+    #[inline(always)]
+    pub fn settlement_authority(&self) -> Pubkey {
+        read_array(self.data(), 96)
+    }
+
+    #[inline(always)]
+    pub fn set_settlement_authority(&self, settlement_authority: &Pubkey) {
+        self.with_data_mut(|data| write_array(data, 96, settlement_authority))
+    }
+
+    #[inline(always)]
+    pub fn credits_per_usdc(&self) -> u64 {
+        read_u64(self.data(), 128)
+    }
+
+    #[inline(always)]
+    pub fn set_credits_per_usdc(&self, credits_per_usdc: u64) {
+        self.with_data_mut(|data| write_u64(data, 128, credits_per_usdc))
+    }
+
+    #[inline(always)]
+    pub fn paused(&self) -> bool {
+        self.data()[136] != 0
+    }
+
+    #[inline(always)]
+    pub fn set_paused(&self, paused: bool) {
+        self.with_data_mut(|data| data[136] = paused as u8)
+    }
+
+    #[inline(always)]
+    pub fn bump(&self) -> u8 {
+        self.data()[137]
+    }
+
+    #[inline(always)]
+    pub fn set_bump(&self, bump: u8) {
+        self.with_data_mut(|data| data[137] = bump)
+    }
+
+    #[inline(always)]
+    pub fn treasury_bump(&self) -> u8 {
+        self.data()[138]
+    }
+
+    #[inline(always)]
+    pub fn set_treasury_bump(&self, treasury_bump: u8) {
+        self.with_data_mut(|data| data[138] = treasury_bump)
+    }
+
+    #[inline(always)]
+    pub fn refund_window(&self) -> i64 {
+        read_i64(self.data(), 139)
+    }
+
+    #[inline(always)]
+    pub fn set_refund_window(&self, refund_window: i64) {
+        self.with_data_mut(|data| write_i64(data, 139, refund_window))
+    }
+
+    #[inline(always)]
+    pub fn token_program(&self) -> Pubkey {
+        read_array(self.data(), 147)
+    }
+
+    #[inline(always)]
+    pub fn set_token_program(&self, token_program: &Pubkey) {
+        self.with_data_mut(|data| write_array(data, 147, token_program))
+    }
+
+    #[inline(always)]
+    pub fn decimals(&self) -> u8 {
+        self.data()[179]
+    }
+
+    #[inline(always)]
+    pub fn set_decimals(&self, decimals: u8) {
+        self.with_data_mut(|data| data[179] = decimals)
+    }
+}
@@ -1,13 +1,32 @@
-use pinocchio::{account_info::AccountInfo, program_error::ProgramError};
+use {
+    crate::{
+        errors::CreditSalesError,
+        parsing::{read_array, read_i64, read_u64, write_array, write_i64, write_u64},
+    },
+    core::slice::{from_raw_parts, from_raw_parts_mut},
+    pinocchio::{
+        account_info::AccountInfo,
+        program_error::ProgramError,
+        pubkey::{create_program_address, Pubkey},
+    },
+};
 
 pub struct CreditsAccount(*mut u8);
 
 impl CreditsAccount {
-    pub const LEN: usize = 8   // timestamp i64
-                         + 8   // credits_amount u64
+    pub const LEN: usize = 8   // timestamp i64, of the last purchase
+                         + 8   // credits_amount u64, bought over all purchases
                          + 8   // credits_amount_refunded u64
                          + 1   // bump [u8; 1]
-                         + 32; // owner [u8; 32]
+                         + 32  // owner [u8; 32]
+                         + 8   // purchase_count u64
+                         + 8   // credits_consumed u64, settled by the server
+                         + 8   // first_purchase_at i64, opens the refund window
+                         + 8   // amount_paid u64, base units received over all purchases
+                         + 32; // mint [u8; 32], the purchases were paid in
+
+    /// PDA seeds are `[SEED, buyer]`.
+    pub const SEED: &'static [u8] = b"credits_account";
 
     #[inline(always)]
     pub fn from_account_info_unchecked(account_info: &AccountInfo) -> Self {
@@ -16,13 +35,153 @@ impl CreditsAccount {
 
     #[inline(always)]
     pub fn from_account_info(account_info: &AccountInfo) -> Result<Self, ProgramError> {
-        assert_eq!(*account_info.owner(), crate::ID);
-        assert_eq!(account_info.data_len(), Self::LEN);
+        if *account_info.owner() != crate::ID || account_info.data_len() != Self::LEN {
+            return Err(CreditSalesError::InvalidAccountData.into());
+        }
         Ok(Self::from_account_info_unchecked(account_info))
     }
 
+    /// Loads the credits account of `buyer`, rejecting accounts that weren't
+    /// created by `InitializeCreditsAccount` for them.
+    #[inline(always)]
+    pub fn from_account_info_for(
+        account_info: &AccountInfo,
+        buyer: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        let credits_account = Self::from_account_info(account_info)?;
+
+        let owner = credits_account.owner();
+        if owner == [0; 32] {
+            return Err(CreditSalesError::UninitializedAccount.into());
+        }
+        if &owner != buyer {
+            return Err(CreditSalesError::Unauthorized.into());
+        }
+
+        let pda =
+            create_program_address(&[Self::SEED, buyer, &[credits_account.bump()]], &crate::ID)?;
+        if account_info.key() != &pda {
+            return Err(CreditSalesError::InvalidPda.into());
+        }
+
+        Ok(credits_account)
+    }
+
+    #[inline(always)]
+    fn data(&self) -> &[u8] {
+        unsafe { from_raw_parts(self.0, Self::LEN) }
+    }
+
+    #[inline(always)]
+    fn with_data_mut(&self, write: impl FnOnce(&mut [u8])) {
+        write(unsafe { from_raw_parts_mut(self.0, Self::LEN) })
+    }
+
+    #[inline(always)]
+    pub fn timestamp(&self) -> i64 {
+        read_i64(self.data(), 0)
+    }
+
+    #[inline(always)]
+    pub fn set_timestamp(&self, timestamp: i64) {
+        self.with_data_mut(|data| write_i64(data, 0, timestamp))
+    }
+
+    #[inline(always)]
+    pub fn credits_amount(&self) -> u64 {
+        read_u64(self.data(), 8)
+    }
+
+    #[inline(always)]
+    pub fn set_credits_amount(&self, credits_amount: u64) {
+        self.with_data_mut(|data| write_u64(data, 8, credits_amount))
+    }
+
+    #[inline(always)]
+    pub fn credits_amount_refunded(&self) -> u64 {
+        read_u64(self.data(), 16)
+    }
+
+    #[inline(always)]
+    pub fn set_credits_amount_refunded(&self, credits_amount_refunded: u64) {
+        self.with_data_mut(|data| write_u64(data, 16, credits_amount_refunded))
+    }
+
+    /// Credits neither refunded nor consumed yet.
+    #[inline(always)]
+    pub fn credits_available(&self) -> u64 {
+        self.credits_amount()
+            .saturating_sub(self.credits_amount_refunded())
+            .saturating_sub(self.credits_consumed())
+    }
+
+    #[inline(always)]
+    pub fn bump(&self) -> u8 {
+        self.data()[24]
+    }
+
+    #[inline(always)]
+    pub fn set_bump(&self, bump: u8) {
+        self.with_data_mut(|data| data[24] = bump)
+    }
+
+    #[inline(always)]
+    pub fn owner(&self) -> Pubkey {
+        read_array(self.data(), 25)
+    }
+
+    #[inline(always)]
+    pub fn set_owner(&self, owner: &Pubkey) {
+        self.with_data_mut(|data| write_array(data, 25, owner))
+    }
+
+    #[inline(always)]
+    pub fn purchase_count(&self) -> u64 {
+        read_u64(self.data(), 57)
+    }
+
+    #[inline(always)]
+    pub fn set_purchase_count(&self, purchase_count: u64) {
+        self.with_data_mut(|data| write_u64(data, 57, purchase_count))
+    }
+
+    #[inline(always)]
+    pub fn credits_consumed(&self) -> u64 {
+        read_u64(self.data(), 65)
+    }
+
+    #[inline(always)]
+    pub fn set_credits_consumed(&self, credits_consumed: u64) {
+        self.with_data_mut(|data| write_u64(data, 65, credits_consumed))
+    }
+
+    #[inline(always)]
+    pub fn first_purchase_at(&self) -> i64 {
+        read_i64(self.data(), 73)
+    }
+
+    #[inline(always)]
+    pub fn set_first_purchase_at(&self, first_purchase_at: i64) {
+        self.with_data_mut(|data| write_i64(data, 73, first_purchase_at))
+    }
+
+    #[inline(always)]
+    pub fn amount_paid(&self) -> u64 {
+        read_u64(self.data(), 81)
+    }
+
+    #[inline(always)]
+    pub fn set_amount_paid(&self, amount_paid: u64) {
+        self.with_data_mut(|data| write_u64(data, 81, amount_paid))
+    }
+
+    #[inline(always)]
+    pub fn mint(&self) -> Pubkey {
+        read_array(self.data(), 89)
+    }
+
     #[inline(always)]
-    pub fn owner(&self) -> [u8; 32] {
-        unsafe { *(self.0.add(25) as *const [u8; 32]) }
+    pub fn set_mint(&self, mint: &Pubkey) {
+        self.with_data_mut(|data| write_array(data, 89, mint))
     }
 }
@@ -1,2 +1,8 @@
+pub mod config;
+pub use config::*;
 pub mod credits_account;
 pub use credits_account::*;
+pub mod plan;
+pub use plan::*;
+pub mod plan_account;
+pub use plan_account::*;
@@ -0,0 +1,90 @@
+use {
+    crate::{
+        errors::CreditSalesError,
+        parsing::{read_u64, write_u64},
+    },
+    core::slice::{from_raw_parts, from_raw_parts_mut},
+    pinocchio::{
+        account_info::AccountInfo, program_error::ProgramError, pubkey::create_program_address,
+    },
+};
+
+/// A monthly plan sold by `BuyPlan`, created and priced by the admin with
+/// `SetPlan`.
+pub struct Plan(*mut u8);
+
+impl Plan {
+    pub const LEN: usize = 1   // bump [u8; 1]
+                         + 1   // plan_id u8
+                         + 8; // price u64, whole tokens of the mint per period, 0 when withdrawn
+
+    /// PDA seeds are `[SEED, [plan_id]]`.
+    pub const SEED: &'static [u8] = b"plan";
+
+    #[inline(always)]
+    pub fn from_account_info_unchecked(account_info: &AccountInfo) -> Self {
+        unsafe { Self(account_info.borrow_mut_data_unchecked().as_mut_ptr()) }
+    }
+
+    /// Loads plan `plan_id`, rejecting plans the admin never set.
+    #[inline(always)]
+    pub fn from_account_info(
+        account_info: &AccountInfo,
+        plan_id: u8,
+    ) -> Result<Self, ProgramError> {
+        if account_info.data_len() == 0 {
+            return Err(CreditSalesError::InvalidPlan.into());
+        }
+        if *account_info.owner() != crate::ID || account_info.data_len() != Self::LEN {
+            return Err(CreditSalesError::InvalidAccountData.into());
+        }
+
+        let plan = Self::from_account_info_unchecked(account_info);
+        let pda = create_program_address(&[Self::SEED, &[plan_id], &[plan.bump()]], &crate::ID)?;
+        if account_info.key() != &pda {
+            return Err(CreditSalesError::InvalidPda.into());
+        }
+
+        Ok(plan)
+    }
+
+    #[inline(always)]
+    fn data(&self) -> &[u8] {
+        unsafe { from_raw_parts(self.0, Self::LEN) }
+    }
+
+    #[inline(always)]
+    fn with_data_mut(&self, write: impl FnOnce(&mut [u8])) {
+        write(unsafe { from_raw_parts_mut(self.0, Self::LEN) })
+    }
+
+    #[inline(always)]
+    pub fn bump(&self) -> u8 {
+        self.data()[0]
+    }
+
+    #[inline(always)]
+    pub fn set_bump(&self, bump: u8) {
+        self.with_data_mut(|data| data[0] = bump)
+    }
+
+    #[inline(always)]
+    pub fn plan_id(&self) -> u8 {
+        self.data()[1]
+    }
+
+    #[inline(always)]
+    pub fn set_plan_id(&self, plan_id: u8) {
+        self.with_data_mut(|data| data[1] = plan_id)
+    }
+
+    #[inline(always)]
+    pub fn price(&self) -> u64 {
+        read_u64(self.data(), 2)
+    }
+
+    #[inline(always)]
+    pub fn set_price(&self, price: u64) {
+        self.with_data_mut(|data| write_u64(data, 2, price))
+    }
+}
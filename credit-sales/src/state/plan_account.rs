@@ -0,0 +1,143 @@
+use {
+    crate::{
+        errors::CreditSalesError,
+        parsing::{read_array, read_i64, read_u64, write_array, write_i64, write_u64},
+    },
+    core::slice::{from_raw_parts, from_raw_parts_mut},
+    pinocchio::{
+        account_info::AccountInfo,
+        program_error::ProgramError,
+        pubkey::{create_program_address, Pubkey},
+    },
+};
+
+/// A buyer's monthly plan, created by their first `BuyPlan`.
+pub struct PlanAccount(*mut u8);
+
+impl PlanAccount {
+    pub const LEN: usize = 1   // bump [u8; 1]
+                         + 32  // owner [u8; 32]
+                         + 1   // plan_id u8
+                         + 1   // auto_renew bool
+                         + 8   // period_start i64, of the first paid period in a row
+                         + 8   // period_end i64, of the last paid period
+                         + 8; // monthly_quota u64, credits per period
+
+    /// PDA seeds are `[SEED, buyer]`.
+    pub const SEED: &'static [u8] = b"plan_account";
+
+    #[inline(always)]
+    pub fn from_account_info_unchecked(account_info: &AccountInfo) -> Self {
+        unsafe { Self(account_info.borrow_mut_data_unchecked().as_mut_ptr()) }
+    }
+
+    /// Loads the plan account of `buyer`, rejecting accounts that weren't
+    /// created by `BuyPlan` for them.
+    #[inline(always)]
+    pub fn from_account_info_for(
+        account_info: &AccountInfo,
+        buyer: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        if *account_info.owner() != crate::ID || account_info.data_len() != Self::LEN {
+            return Err(CreditSalesError::InvalidAccountData.into());
+        }
+
+        let plan_account = Self::from_account_info_unchecked(account_info);
+        if &plan_account.owner() != buyer {
+            return Err(CreditSalesError::Unauthorized.into());
+        }
+
+        let pda = create_program_address(&[Self::SEED, buyer, &[plan_account.bump()]], &crate::ID)?;
+        if account_info.key() != &pda {
+            return Err(CreditSalesError::InvalidPda.into());
+        }
+
+        Ok(plan_account)
+    }
+
+    #[inline(always)]
+    fn data(&self) -> &[u8] {
+        unsafe { from_raw_parts(self.0, Self::LEN) }
+    }
+
+    #[inline(always)]
+    fn with_data_mut(&self, write: impl FnOnce(&mut [u8])) {
+        write(unsafe { from_raw_parts_mut(self.0, Self::LEN) })
+    }
+
+    /// Whether a paid period covers `now`.
+    #[inline(always)]
+    pub fn is_active(&self, now: i64) -> bool {
+        self.period_start() <= now && now < self.period_end()
+    }
+
+    #[inline(always)]
+    pub fn bump(&self) -> u8 {
+        self.data()[0]
+    }
+
+    #[inline(always)]
+    pub fn set_bump(&self, bump: u8) {
+        self.with_data_mut(|data| data[0] = bump)
+    }
+
+    #[inline(always)]
+    pub fn owner(&self) -> Pubkey {
+        read_array(self.data(), 1)
+    }
+
+    #[inline(always)]
+    pub fn set_owner(&self, owner: &Pubkey) {
+        self.with_data_mut(|data| write_array(data, 1, owner))
+    }
+
+    #[inline(always)]
+    pub fn plan_id(&self) -> u8 {
+        self.data()[33]
+    }
+
+    #[inline(always)]
+    pub fn set_plan_id(&self, plan_id: u8) {
+        self.with_data_mut(|data| data[33] = plan_id)
+    }
+
+    #[inline(always)]
+    pub fn auto_renew(&self) -> bool {
+        self.data()[34] != 0
+    }
+
+    #[inline(always)]
+    pub fn set_auto_renew(&self, auto_renew: bool) {
+        self.with_data_mut(|data| data[34] = auto_renew as u8)
+    }
+
+    #[inline(always)]
+    pub fn period_start(&self) -> i64 {
+        read_i64(self.data(), 35)
+    }
+
+    #[inline(always)]
+    pub fn set_period_start(&self, period_start: i64) {
+        self.with_data_mut(|data| write_i64(data, 35, period_start))
+    }
+
+    #[inline(always)]
+    pub fn period_end(&self) -> i64 {
+        read_i64(self.data(), 43)
+    }
+
+    #[inline(always)]
+    pub fn set_period_end(&self, period_end: i64) {
+        self.with_data_mut(|data| write_i64(data, 43, period_end))
+    }
+
+    #[inline(always)]
+    pub fn monthly_quota(&self) -> u64 {
+        read_u64(self.data(), 51)
+    }
+
+    #[inline(always)]
+    pub fn set_monthly_quota(&self, monthly_quota: u64) {
+        self.with_data_mut(|data| write_u64(data, 51, monthly_quota))
+    }
+}
@@ -0,0 +1,1131 @@
+use {
+    super::*,
+    crate::errors::CreditSalesError,
+    credit_sales_client as client,
+    mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
+        instruction::Instruction,
+        program_error::ProgramError,
+        pubkey::Pubkey,
+        system_program, sysvar,
+    },
+    spl_token::error::TokenError,
+};
+
+/// An instruction that succeeds as built, with the accounts it runs on.
+struct Scenario {
+    mollusk: Mollusk,
+    instruction: Instruction,
+    accounts: Vec<(Pubkey, AccountSharedData)>,
+    /// Most compute units the instruction may use, about 10% above what it
+    /// uses. Raise it on purpose when a change makes the instruction more
+    /// expensive.
+    max_compute_units: u64,
+}
+
+/// A change a caller could make to a valid instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Substitution {
+    /// The account at this index, owned by another program
+    ForeignOwner(usize),
+    /// Another address with the same account at this index
+    OtherKey(usize),
+    /// The account at this index without its signature
+    NotSigner(usize),
+    /// Instruction data missing its last byte, but not its discriminator
+    ShortData,
+    /// Instruction data with a trailing byte
+    LongData,
+    /// The account at `account` replaced by the one at `of`
+    Duplicate { account: usize, of: usize },
+}
+
+impl Scenario {
+    fn account(&self, key: &Pubkey) -> &AccountSharedData {
+        self.accounts
+            .iter()
+            .find(|(address, _)| address == key)
+            .map(|(_, account)| account)
+            .expect("Missing account")
+    }
+
+    /// Every substitution of every kind at every account index. Signers,
+    /// programs and sysvars keep their owner: the program only reads the
+    /// data of the other accounts.
+    fn substitutions(&self) -> Vec<Substitution> {
+        let metas = &self.instruction.accounts;
+        let mut substitutions = vec![Substitution::LongData];
+        if self.instruction.data.len() > 1 {
+            substitutions.push(Substitution::ShortData);
+        }
+
+        for (index, meta) in metas.iter().enumerate() {
+            let account = self.account(&meta.pubkey);
+            if meta.is_signer {
+                substitutions.push(Substitution::NotSigner(index));
+            } else if !account.executable() && account.owner() != &sysvar::ID {
+                substitutions.push(Substitution::ForeignOwner(index));
+            }
+            substitutions.push(Substitution::OtherKey(index));
+
+            for (of, other) in metas.iter().enumerate() {
+                if other.pubkey != meta.pubkey {
+                    substitutions.push(Substitution::Duplicate { account: index, of });
+                }
+            }
+        }
+
+        substitutions
+    }
+
+    fn substitute(
+        &self,
+        substitution: Substitution,
+    ) -> (Instruction, Vec<(Pubkey, AccountSharedData)>) {
+        let mut instruction = self.instruction.clone();
+        let mut accounts = self.accounts.clone();
+
+        match substitution {
+            Substitution::ForeignOwner(index) => {
+                let key = instruction.accounts[index].pubkey;
+                let (_, account) = accounts
+                    .iter_mut()
+                    .find(|(address, _)| *address == key)
+                    .expect("Missing account");
+                account.set_owner(Pubkey::new_unique());
+            }
+            Substitution::OtherKey(index) => {
+                let other = Pubkey::new_unique();
+                accounts.push((
+                    other,
+                    self.account(&instruction.accounts[index].pubkey).clone(),
+                ));
+                instruction.accounts[index].pubkey = other;
+            }
+            Substitution::NotSigner(index) => instruction.accounts[index].is_signer = false,
+            Substitution::ShortData => {
+                instruction.data.pop();
+            }
+            Substitution::LongData => instruction.data.push(0),
+            Substitution::Duplicate { account, of } => {
+                instruction.accounts[account].pubkey = instruction.accounts[of].pubkey;
+            }
+        }
+
+        // Only the accounts the instruction still references
+        accounts.retain(|(address, _)| {
+            instruction
+                .accounts
+                .iter()
+                .any(|meta| meta.pubkey == *address)
+        });
+
+        (instruction, accounts)
+    }
+
+    /// Checks that the scenario succeeds and fails with every substitution
+    /// but the `accepted` ones, with the error in `errors` where it lists
+    /// one.
+    fn assert_rejects(
+        &self,
+        errors: &[(Substitution, Check)],
+        accepted: impl Fn(Substitution) -> bool,
+    ) {
+        self.mollusk.process_and_validate_instruction(
+            &self.instruction,
+            &self.accounts,
+            &[Check::success()],
+        );
+
+        let substitutions = self.substitutions();
+        for (substitution, _) in errors {
+            assert!(
+                substitutions.contains(substitution),
+                "{:?} isn't a substitution of this instruction",
+                substitution
+            );
+        }
+
+        for substitution in substitutions {
+            if accepted(substitution) {
+                continue;
+            }
+
+            let (instruction, accounts) = self.substitute(substitution);
+            match errors.iter().find(|(listed, _)| *listed == substitution) {
+                Some((_, check)) => {
+                    self.mollusk.process_and_validate_instruction(
+                        &instruction,
+                        &accounts,
+                        std::slice::from_ref(check),
+                    );
+                }
+                None => {
+                    let result = self.mollusk.process_instruction(&instruction, &accounts);
+                    assert!(
+                        result.program_result.is_err(),
+                        "{:?} should be rejected",
+                        substitution
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn token_error(error: TokenError) -> Check<'static> {
+    Check::err(ProgramError::Custom(error as u32))
+}
+
+/// Buyer paying 10 tokens into a treasury holding 1.000.
+fn buy_credits_scenario() -> Scenario {
+    let (program_id, mut mollusk) = setup();
+    mollusk.sysvars.clock.unix_timestamp = 1_700_000_000;
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &Pubkey::new_unique(),
+    );
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+    let (credits_account, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &buyer);
+
+    let instruction = client::buy_credits(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        10,
+    );
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+        ),
+        (buyer_token_account, pack_token_account(&buyer, &mint, 10)),
+        (treasury, pack_token_account(&treasury, &mint, 1_000)),
+        (credits_account, credits_account_data),
+        clock_sysvar(),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 14_000,
+    }
+}
+
+/// Admin refunding 1 of the 10 tokens of credits the buyer bought.
+fn refund_credits_scenario() -> Scenario {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (config, config_data) =
+        config_account(&mollusk, &program_id, &admin, &mint, &Pubkey::new_unique());
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+    let (credits_account, credits_account_data) =
+        purchased_credits_account(&mollusk, &program_id, &buyer, &mint, 10, 0);
+
+    let instruction = client::refund_credits(
+        &program_id,
+        &admin,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        CREDITS_PER_USDC,
+    );
+    let accounts = vec![
+        (admin, AccountSharedData::default()),
+        (buyer, AccountSharedData::default()),
+        (buyer_token_account, pack_token_account(&buyer, &mint, 0)),
+        (treasury, pack_token_account(&treasury, &mint, 1_000)),
+        (credits_account, credits_account_data),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 14_000,
+    }
+}
+
+/// Admin withdrawing 7 of the treasury's 10 tokens.
+fn withdraw_usdc_scenario() -> Scenario {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let admin_token_account = Pubkey::new_unique();
+    let (config, config_data) =
+        config_account(&mollusk, &program_id, &admin, &mint, &Pubkey::new_unique());
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let instruction = client::withdraw_usdc(
+        &program_id,
+        &admin,
+        &admin_token_account,
+        &mint,
+        &token_program,
+        7,
+    );
+    let accounts = vec![
+        (treasury, pack_token_account(&treasury, &mint, 10)),
+        (admin, AccountSharedData::default()),
+        (admin_token_account, pack_token_account(&admin, &mint, 0)),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 12_000,
+    }
+}
+
+/// Settlement of two buyers with 500 credits each.
+fn consume_credits_scenario() -> Scenario {
+    let (program_id, mollusk) = setup();
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &authority,
+    );
+
+    let buyers = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut accounts = vec![
+        (authority, AccountSharedData::default()),
+        (config, config_data),
+    ];
+    for buyer in &buyers {
+        let (credits_account, mut credits_account_data) =
+            initialized_credits_account(&mollusk, &program_id, buyer);
+        credits_account_data.data_as_mut_slice()[8..16].copy_from_slice(&500u64.to_le_bytes());
+        accounts.push((credits_account, credits_account_data));
+    }
+
+    let instruction = client::consume_credits(
+        &program_id,
+        &authority,
+        &[(buyers[0], 100), (buyers[1], 500)],
+    );
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 6_000,
+    }
+}
+
+/// First purchase of the Starter plan.
+fn buy_plan_scenario() -> Scenario {
+    let (program_id, mut mollusk) = setup();
+    mollusk.sysvars.clock.unix_timestamp = 1_700_000_000;
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let price = 10 * 10u64.pow(MINT_DECIMALS as u32);
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &Pubkey::new_unique(),
+    );
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+    let (plan_account, _) = client::plan_account_address(&program_id, &buyer);
+
+    let instruction = client::buy_plan(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        1,
+        false,
+    );
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (
+            buyer_token_account,
+            pack_token_account(&buyer, &mint, price),
+        ),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (plan_account, AccountSharedData::default()),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        plan_for_sale(&mollusk, &program_id, 1, 10),
+    ];
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 22_000,
+    }
+}
+
+fn initialize_credits_account_scenario() -> Scenario {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+
+    let buyer = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+    let (credits_account, _) = client::credits_account_address(&program_id, &buyer);
+
+    let instruction = client::initialize_credits_account(&program_id, &buyer);
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (credits_account, AccountSharedData::default()),
+        (system_program, system_program_account),
+        (config, config_data),
+    ];
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 9_000,
+    }
+}
+
+/// Config and treasury created by the deploy-time admin.
+fn initialize_config_scenario() -> Scenario {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let (config, _) = client::config_address(&program_id);
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let instruction = client::initialize_config(
+        &program_id,
+        &admin,
+        &mint,
+        &token_program,
+        &client::ConfigParams {
+            credits_per_usdc: CREDITS_PER_USDC,
+            settlement_authority: Pubkey::new_unique(),
+            refund_window: REFUND_WINDOW,
+        },
+    );
+    let accounts = vec![
+        (
+            admin,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, AccountSharedData::default()),
+        (treasury, AccountSharedData::default()),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+    ];
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 18_000,
+    }
+}
+
+/// Admin doubling the price, keeping the mint and its treasury.
+fn update_config_scenario() -> Scenario {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let (config, config_data) =
+        config_account(&mollusk, &program_id, &admin, &mint, &Pubkey::new_unique());
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let instruction = client::update_config(
+        &program_id,
+        &admin,
+        &mint,
+        &token_program,
+        &client::ConfigParams {
+            credits_per_usdc: 2 * CREDITS_PER_USDC,
+            settlement_authority: Pubkey::new_unique(),
+            refund_window: REFUND_WINDOW,
+        },
+        false,
+    );
+    let accounts = vec![
+        (
+            admin,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, config_data),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+    ];
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 6_000,
+    }
+}
+
+fn transfer_admin_scenario() -> Scenario {
+    let (program_id, mollusk) = setup();
+    let admin = admin();
+    let new_admin = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    let instruction = client::transfer_admin(&program_id, &admin, &new_admin);
+    let accounts = vec![
+        (admin, AccountSharedData::default()),
+        (config, config_data),
+        (new_admin, AccountSharedData::default()),
+    ];
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 2_000,
+    }
+}
+
+/// Admin raising the price of a plan already for sale.
+fn set_plan_scenario() -> Scenario {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+
+    let admin = admin();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+    let (plan, plan_data) = plan_for_sale(&mollusk, &program_id, 1, 10);
+
+    let instruction = client::set_plan(&program_id, &admin, 1, 20);
+    let accounts = vec![
+        (
+            admin,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, config_data),
+        (plan, plan_data),
+        (system_program, system_program_account),
+    ];
+
+    Scenario {
+        mollusk,
+        instruction,
+        accounts,
+        max_compute_units: 4_000,
+    }
+}
+
+#[test]
+fn test_buy_credits_substitutions() {
+    use Substitution::*;
+
+    // [buyer, buyer_ta, treasury, credits_account, clock, token_program,
+    // config, mint]
+    buy_credits_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(0),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(0), custom_error(CreditSalesError::Unauthorized)),
+            (
+                OtherKey(2),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                ForeignOwner(2),
+                custom_error(CreditSalesError::InvalidTokenAccount),
+            ),
+            (OtherKey(3), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(3),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                OtherKey(5),
+                custom_error(CreditSalesError::InvalidTokenProgram),
+            ),
+            (OtherKey(6), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(6),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(7), custom_error(CreditSalesError::InvalidMint)),
+            (
+                ShortData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            // Paying into the buyer's own token account
+            (
+                Duplicate { account: 2, of: 1 },
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            // Paying the treasury with the treasury's own tokens
+            (
+                Duplicate { account: 1, of: 2 },
+                token_error(TokenError::OwnerMismatch),
+            ),
+            // The config passed as the credits account
+            (
+                Duplicate { account: 3, of: 6 },
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+        ],
+        // Any token account of the buyer can pay. The clock is read from the
+        // sysvar, not the account, and the mint is checked by address.
+        |substitution| {
+            matches!(
+                substitution,
+                OtherKey(1) | OtherKey(4) | Duplicate { account: 4, .. } | ForeignOwner(7)
+            )
+        },
+    );
+}
+
+#[test]
+fn test_refund_credits_substitutions() {
+    use Substitution::*;
+
+    // [admin, buyer, buyer_ta, treasury, credits_account, token_program,
+    // config, mint]
+    refund_credits_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(0),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(0), custom_error(CreditSalesError::Unauthorized)),
+            (
+                OtherKey(1),
+                custom_error(CreditSalesError::InvalidTokenAccount),
+            ),
+            (
+                ForeignOwner(2),
+                custom_error(CreditSalesError::InvalidTokenAccount),
+            ),
+            (
+                OtherKey(3),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(4), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(4),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                OtherKey(5),
+                custom_error(CreditSalesError::InvalidTokenProgram),
+            ),
+            (OtherKey(6), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(6),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(7), custom_error(CreditSalesError::InvalidMint)),
+            (
+                ShortData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            // Refunding into the treasury
+            (
+                Duplicate { account: 2, of: 3 },
+                custom_error(CreditSalesError::InvalidTokenAccount),
+            ),
+        ],
+        // Any token account of the buyer can be paid back. The buyer is only
+        // checked by address, and so is the mint.
+        |substitution| {
+            matches!(
+                substitution,
+                OtherKey(2) | ForeignOwner(1) | ForeignOwner(7)
+            )
+        },
+    );
+}
+
+#[test]
+fn test_withdraw_usdc_substitutions() {
+    use Substitution::*;
+
+    // [treasury, admin, admin_ta, token_program, config, mint]
+    withdraw_usdc_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(1),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(1), custom_error(CreditSalesError::Unauthorized)),
+            (
+                OtherKey(0),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                OtherKey(3),
+                custom_error(CreditSalesError::InvalidTokenProgram),
+            ),
+            (OtherKey(4), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(4),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(5), custom_error(CreditSalesError::InvalidMint)),
+            (
+                ShortData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            // The treasury signing for the admin
+            (
+                Duplicate { account: 1, of: 0 },
+                custom_error(CreditSalesError::Unauthorized),
+            ),
+        ],
+        // The admin withdraws to any token account of the mint, the treasury
+        // itself included, which moves nothing. The mint is checked by
+        // address.
+        |substitution| {
+            matches!(
+                substitution,
+                OtherKey(2) | Duplicate { account: 2, of: 0 } | ForeignOwner(5)
+            )
+        },
+    );
+}
+
+#[test]
+fn test_consume_credits_substitutions() {
+    use Substitution::*;
+
+    // [authority, config, credits_account, credits_account]
+    consume_credits_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(0),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(0), custom_error(CreditSalesError::Unauthorized)),
+            (OtherKey(1), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(1),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(2), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(2),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(3), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(3),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                ShortData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            // The config passed as a credits account
+            (
+                Duplicate { account: 2, of: 1 },
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+        ],
+        // Settling an account twice in a batch is fine, totals are cumulative
+        |substitution| {
+            matches!(
+                substitution,
+                Duplicate { account: 2, of: 3 } | Duplicate { account: 3, of: 2 }
+            )
+        },
+    );
+}
+
+#[test]
+fn test_buy_plan_substitutions() {
+    use Substitution::*;
+
+    // [buyer, buyer_ta, treasury, plan_account, system_program,
+    // token_program, config, mint, plan]
+    buy_plan_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(0),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(0), custom_error(CreditSalesError::InvalidPda)),
+            (
+                OtherKey(2),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                ForeignOwner(2),
+                custom_error(CreditSalesError::InvalidTokenAccount),
+            ),
+            (OtherKey(3), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(3),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                OtherKey(5),
+                custom_error(CreditSalesError::InvalidTokenProgram),
+            ),
+            (OtherKey(6), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(6),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(7), custom_error(CreditSalesError::InvalidMint)),
+            (OtherKey(8), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(8),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                ShortData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            // The buyer's wallet as their plan account
+            (
+                Duplicate { account: 3, of: 0 },
+                custom_error(CreditSalesError::InvalidPda),
+            ),
+        ],
+        // Any token account of the buyer can pay, and the mint is checked by
+        // address
+        |substitution| matches!(substitution, OtherKey(1) | ForeignOwner(7)),
+    );
+}
+
+#[test]
+fn test_initialize_credits_account_substitutions() {
+    use Substitution::*;
+
+    // [buyer, credits_account, system_program, config]
+    initialize_credits_account_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(0),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(0), custom_error(CreditSalesError::InvalidPda)),
+            (OtherKey(1), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(1),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(3), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(3),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                ShortData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                Duplicate { account: 1, of: 0 },
+                custom_error(CreditSalesError::InvalidPda),
+            ),
+        ],
+        |_| false,
+    );
+}
+
+#[test]
+fn test_initialize_config_substitutions() {
+    use Substitution::*;
+
+    // [admin, config, treasury, mint, system_program, token_program]
+    initialize_config_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(0),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(0), custom_error(CreditSalesError::Unauthorized)),
+            (OtherKey(1), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(1),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(2), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(2),
+                custom_error(CreditSalesError::InvalidTokenAccount),
+            ),
+            // Another mint doesn't match the treasury
+            (OtherKey(3), custom_error(CreditSalesError::InvalidPda)),
+            (ForeignOwner(3), custom_error(CreditSalesError::InvalidMint)),
+            (
+                OtherKey(5),
+                custom_error(CreditSalesError::InvalidTokenProgram),
+            ),
+            (
+                ShortData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+        ],
+        |_| false,
+    );
+}
+
+#[test]
+fn test_update_config_substitutions() {
+    use Substitution::*;
+
+    // [admin, config, treasury, mint, system_program, token_program]
+    update_config_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(0),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(0), custom_error(CreditSalesError::Unauthorized)),
+            (OtherKey(1), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(1),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(2), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(2),
+                custom_error(CreditSalesError::InvalidTokenAccount),
+            ),
+            // Another mint doesn't match the treasury
+            (OtherKey(3), custom_error(CreditSalesError::InvalidPda)),
+            (ForeignOwner(3), custom_error(CreditSalesError::InvalidMint)),
+            (
+                OtherKey(5),
+                custom_error(CreditSalesError::InvalidTokenProgram),
+            ),
+            (
+                ShortData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            // The treasury passed as the config
+            (
+                Duplicate { account: 1, of: 2 },
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+        ],
+        // The system program only creates a new mint's treasury, and this
+        // one exists
+        |substitution| matches!(substitution, OtherKey(4) | Duplicate { account: 4, .. }),
+    );
+}
+
+#[test]
+fn test_transfer_admin_substitutions() {
+    use Substitution::*;
+
+    // [admin, config, new_admin]
+    transfer_admin_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(0),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (
+                NotSigner(2),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(0), custom_error(CreditSalesError::Unauthorized)),
+            (OtherKey(1), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(1),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                Duplicate { account: 1, of: 0 },
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+        ],
+        // Any signer can take the role, the admin included. The config
+        // can't sign outside the program.
+        |substitution| matches!(substitution, OtherKey(2) | Duplicate { account: 2, .. }),
+    );
+}
+
+#[test]
+fn test_set_plan_substitutions() {
+    use Substitution::*;
+
+    // [admin, config, plan, system_program]
+    set_plan_scenario().assert_rejects(
+        &[
+            (
+                NotSigner(0),
+                Check::err(ProgramError::MissingRequiredSignature),
+            ),
+            (OtherKey(0), custom_error(CreditSalesError::Unauthorized)),
+            (OtherKey(1), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(1),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (OtherKey(2), custom_error(CreditSalesError::InvalidPda)),
+            (
+                ForeignOwner(2),
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+            (
+                ShortData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            (
+                LongData,
+                custom_error(CreditSalesError::InvalidInstructionData),
+            ),
+            // The config passed as the plan
+            (
+                Duplicate { account: 2, of: 1 },
+                custom_error(CreditSalesError::InvalidPda),
+            ),
+            // The plan passed as the config
+            (
+                Duplicate { account: 1, of: 2 },
+                custom_error(CreditSalesError::InvalidAccountData),
+            ),
+        ],
+        // The system program only creates a new plan, and this one exists
+        |substitution| matches!(substitution, OtherKey(3) | Duplicate { account: 3, .. }),
+    );
+}
+
+/// Guards against compute regressions: each instruction has to stay within
+/// its scenario's budget.
+#[test]
+fn test_compute_unit_budgets() {
+    let scenarios = [
+        ("BuyCredits", buy_credits_scenario()),
+        ("RefundCredits", refund_credits_scenario()),
+        ("WithdrawUSDC", withdraw_usdc_scenario()),
+        ("ConsumeCredits", consume_credits_scenario()),
+        ("BuyPlan", buy_plan_scenario()),
+        (
+            "InitializeCreditsAccount",
+            initialize_credits_account_scenario(),
+        ),
+        ("InitializeConfig", initialize_config_scenario()),
+        ("UpdateConfig", update_config_scenario()),
+        ("TransferAdmin", transfer_admin_scenario()),
+        ("SetPlan", set_plan_scenario()),
+    ];
+
+    for (name, scenario) in scenarios {
+        let result = scenario.mollusk.process_and_validate_instruction(
+            &scenario.instruction,
+            &scenario.accounts,
+            &[Check::success()],
+        );
+        assert!(
+            result.compute_units_consumed <= scenario.max_compute_units,
+            "{} used {} compute units, over its budget of {}",
+            name,
+            result.compute_units_consumed,
+            scenario.max_compute_units
+        );
+    }
+}
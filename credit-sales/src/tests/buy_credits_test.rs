@@ -1,14 +1,13 @@
 use {
     super::*,
-    crate::{constants::USDC_TO_CREDIT, state::*},
+    crate::errors::CreditSalesError,
+    credit_sales_client as client,
     mollusk_svm::result::Check,
     solana_sdk::{
-        account::{AccountSharedData, ReadableAccount},
-        instruction::{AccountMeta, Instruction},
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
         program_pack::Pack,
         pubkey::Pubkey,
         system_program,
-        sysvar::SysvarId,
     },
     spl_token::state::Account as TokenAccount,
 };
@@ -16,21 +15,15 @@ use {
 #[test]
 fn test_buy_credits() {
     // ========= Setup test environment =========
-    let (program_id, mollusk) = setup();
+    let (program_id, mut mollusk) = setup();
+    mollusk.sysvars.clock.unix_timestamp = 1_700_000_000;
     let (token_program, token_program_account) = mollusk_token::token::keyed_account();
 
     // Setup clock sysvar
-    let clock_sysvar = Pubkey::new_unique();
-    let clock_sysvar_account = create_account(
-        0,
-        std::mem::size_of::<solana_sdk::clock::Clock>(),
-        &solana_sdk::clock::Clock::id(),
-    );
+    let (clock_sysvar, clock_sysvar_account) = clock_sysvar();
 
     // ========= Setup accounts =========
-    let admin = Pubkey::new_from_array(five8_const::decode_32_const(
-        "3n5KbkZv1Zyu661dTzPNCqKzLyeYu9uuaqLExpLnz3w4",
-    ));
+    let admin = admin();
     let usdc_mint = Pubkey::new_from_array(five8_const::decode_32_const(
         "111111111111111111111111111111111111111111",
     ));
@@ -51,18 +44,20 @@ fn test_buy_credits() {
     );
 
     // Setup credits account
-    let (credits_account, _) =
-        Pubkey::find_program_address(&[b"credits_account", &buyer.to_bytes()], &program_id);
-    let credits_account_data = AccountSharedData::new(
-        mollusk.sysvars.rent.minimum_balance(CreditsAccount::LEN),
-        CreditsAccount::LEN,
-        &program_id,
-    );
+    let (credits_account, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &buyer);
+    let credits_account_bump = credits_account_data.data()[24];
 
     // Setup treasury
-    let (treasury, bump) =
-        Pubkey::find_program_address(&[b"treasury", &admin.to_bytes()], &program_id);
-    let treasury_account = pack_token_account(&admin, &usdc_mint, 0);
+    let (treasury, _) = client::treasury_address(&program_id, &usdc_mint);
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &usdc_mint,
+        &Pubkey::new_unique(),
+    );
+    let treasury_account = pack_token_account(&treasury, &usdc_mint, 0);
 
     // Verify initial treasury balance
     let initial_treasury_balance = TokenAccount::unpack(treasury_account.data())
@@ -75,25 +70,14 @@ fn test_buy_credits() {
 
     // ========= Create instruction =========
     let amount_to_transfer = 10u64;
-    let instruction_data = [
-        vec![0],
-        amount_to_transfer.to_le_bytes().to_vec(),
-        bump.to_le_bytes().to_vec(),
-    ]
-    .concat();
-
-    let instruction = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(buyer, true),
-            AccountMeta::new(buyer_token_account, false),
-            AccountMeta::new(treasury, false),
-            AccountMeta::new(credits_account, false),
-            AccountMeta::new(clock_sysvar, false),
-            AccountMeta::new_readonly(token_program, false),
-        ],
-        data: instruction_data,
-    };
+    let instruction = client::buy_credits(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &usdc_mint,
+        &token_program,
+        amount_to_transfer,
+    );
 
     let transfer_instruction = spl_token::instruction::transfer(
         &token_program,
@@ -119,6 +103,8 @@ fn test_buy_credits() {
         (credits_account, credits_account_data),
         (clock_sysvar, clock_sysvar_account),
         (token_program, token_program_account),
+        (config, config_data),
+        (usdc_mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
     ];
 
     // ========= Execute instruction =========
@@ -142,22 +128,27 @@ fn test_buy_credits() {
     let credits_amount = u64::from_le_bytes(credits_account_data[8..16].try_into().unwrap());
     let amount_refunded = u64::from_le_bytes(credits_account_data[16..24].try_into().unwrap());
     let credits_bump = credits_account_data[24];
+    let purchase_count = u64::from_le_bytes(credits_account_data[57..65].try_into().unwrap());
 
     assert_eq!(
         credits_amount,
-        amount_to_transfer * USDC_TO_CREDIT,
+        amount_to_transfer * CREDITS_PER_USDC,
         "Credits amount should equal transferred amount"
     );
     assert_eq!(
         amount_refunded, 0,
         "Amount refunded should be 0 after transfer"
     );
-    assert_eq!(credits_bump, bump, "Bump should match");
     assert_eq!(
-        credits_account_data[0..8],
-        [0u8; 8],
-        "Timestamp should be 0 after transfer"
+        credits_bump, credits_account_bump,
+        "Bump should be left untouched"
+    );
+    assert_eq!(
+        i64::from_le_bytes(credits_account_data[0..8].try_into().unwrap()),
+        1_700_000_000,
+        "Timestamp should be the purchase's"
     );
+    assert_eq!(purchase_count, 1, "Purchase should be counted");
 
     // Verify final balances
     let final_buyer_balance = TokenAccount::unpack(
@@ -188,3 +179,151 @@ fn test_buy_credits() {
         "Treasury balance should equal transferred amount"
     );
 }
+
+#[test]
+fn test_buy_credits_tops_up() {
+    let (program_id, mut mollusk) = setup();
+    mollusk.sysvars.clock.unix_timestamp = 1_700_000_000;
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let (clock_sysvar, clock_sysvar_account) = clock_sysvar();
+
+    let admin = admin();
+    let usdc_mint = Pubkey::new_unique();
+    let (treasury, _) = client::treasury_address(&program_id, &usdc_mint);
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &usdc_mint,
+        &Pubkey::new_unique(),
+    );
+
+    let buyer = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+
+    // An earlier purchase, part of it refunded
+    let (credits_account, mut credits_account_data) =
+        purchased_credits_account(&mollusk, &program_id, &buyer, &usdc_mint, 5, 1_600_000_000);
+    credits_account_data.data_as_mut_slice()[16..24]
+        .copy_from_slice(&CREDITS_PER_USDC.to_le_bytes());
+
+    let amount_to_transfer = 3u64;
+    let instruction = client::buy_credits(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &usdc_mint,
+        &token_program,
+        amount_to_transfer,
+    );
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000, 0, &system_program::id()),
+        ),
+        (
+            buyer_token_account,
+            pack_token_account(&buyer, &usdc_mint, amount_to_transfer),
+        ),
+        (treasury, pack_token_account(&treasury, &usdc_mint, 0)),
+        (credits_account, credits_account_data),
+        (clock_sysvar, clock_sysvar_account),
+        (token_program, token_program_account),
+        (config, config_data),
+        (usdc_mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let data = result
+        .get_account(&credits_account)
+        .expect("Failed to get credits account")
+        .data();
+    assert_eq!(
+        i64::from_le_bytes(data[0..8].try_into().unwrap()),
+        1_700_000_000,
+        "Timestamp should be the last purchase's"
+    );
+    assert_eq!(
+        u64::from_le_bytes(data[8..16].try_into().unwrap()),
+        8 * CREDITS_PER_USDC,
+        "Credits should add up over purchases"
+    );
+    assert_eq!(
+        u64::from_le_bytes(data[16..24].try_into().unwrap()),
+        CREDITS_PER_USDC,
+        "Refunded credits should be kept"
+    );
+    assert_eq!(
+        u64::from_le_bytes(data[57..65].try_into().unwrap()),
+        2,
+        "Purchase should be counted"
+    );
+    assert_eq!(
+        i64::from_le_bytes(data[73..81].try_into().unwrap()),
+        1_600_000_000,
+        "First purchase time should be kept"
+    );
+    assert_eq!(
+        u64::from_le_bytes(data[81..89].try_into().unwrap()),
+        8,
+        "Amounts paid should add up over purchases"
+    );
+}
+
+#[test]
+fn test_buy_credits_zero_amount() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let (clock_sysvar, clock_sysvar_account) = clock_sysvar();
+
+    let admin = admin();
+    let usdc_mint = Pubkey::new_unique();
+    let (treasury, _) = client::treasury_address(&program_id, &usdc_mint);
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &usdc_mint,
+        &Pubkey::new_unique(),
+    );
+
+    let buyer = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (credits_account, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &buyer);
+
+    let instruction = client::buy_credits(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &usdc_mint,
+        &token_program,
+        0,
+    );
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000, 0, &system_program::id()),
+        ),
+        (
+            buyer_token_account,
+            pack_token_account(&buyer, &usdc_mint, 10),
+        ),
+        (treasury, pack_token_account(&treasury, &usdc_mint, 0)),
+        (credits_account, credits_account_data),
+        (clock_sysvar, clock_sysvar_account),
+        (token_program, token_program_account),
+        (config, config_data),
+        (usdc_mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidInstructionData)],
+    );
+}
@@ -0,0 +1,286 @@
+use {
+    super::*,
+    crate::{constants::PLAN_PERIOD, errors::CreditSalesError, state::PlanAccount},
+    credit_sales_client as client,
+    mollusk_svm::{program::keyed_account_for_system_program, result::Check},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
+        program_pack::Pack,
+        pubkey::Pubkey,
+    },
+    spl_token::state::Account as TokenAccount,
+};
+
+const NOW: i64 = 1_700_000_000;
+
+/// Price of the Starter plan in base units of a `MINT_DECIMALS` mint.
+const STARTER_PRICE: u64 = 10 * 10u64.pow(MINT_DECIMALS as u32);
+
+/// Buyer with `STARTER_PRICE * 2` tokens, a config accepting `mint` and
+/// plans priced in whole tokens.
+struct PlanFixture {
+    program_id: Pubkey,
+    mollusk: mollusk_svm::Mollusk,
+    buyer: Pubkey,
+    mint: Pubkey,
+    plan_account: Pubkey,
+    plan_account_bump: u8,
+    plan_prices: Vec<(u8, u64)>,
+}
+
+impl PlanFixture {
+    fn new() -> Self {
+        let (program_id, mut mollusk) = setup();
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+        let buyer = Pubkey::new_unique();
+        let (plan_account, plan_account_bump) = client::plan_account_address(&program_id, &buyer);
+
+        Self {
+            program_id,
+            mollusk,
+            buyer,
+            mint: Pubkey::new_unique(),
+            plan_account,
+            plan_account_bump,
+            // Starter, Basic and Pro
+            plan_prices: vec![(1, 10), (2, 30), (3, 50)],
+        }
+    }
+
+    /// Plan account as left by an earlier `BuyPlan` of `plan_id`.
+    fn plan_account_data(
+        &self,
+        plan_id: u8,
+        period_start: i64,
+        period_end: i64,
+    ) -> AccountSharedData {
+        let mut account = create_account(
+            self.mollusk.sysvars.rent.minimum_balance(PlanAccount::LEN),
+            PlanAccount::LEN,
+            &self.program_id,
+        );
+        let data = account.data_as_mut_slice();
+        data[0] = self.plan_account_bump;
+        data[1..33].copy_from_slice(&self.buyer.to_bytes());
+        data[33] = plan_id;
+        data[35..43].copy_from_slice(&period_start.to_le_bytes());
+        data[43..51].copy_from_slice(&period_end.to_le_bytes());
+        account
+    }
+
+    fn buy(
+        &self,
+        plan_id: u8,
+        auto_renew: bool,
+        plan_account_data: AccountSharedData,
+        checks: &[Check],
+    ) -> mollusk_svm::result::InstructionResult {
+        let (system_program, system_program_account) = keyed_account_for_system_program();
+        let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+        let (treasury, _) = client::treasury_address(&self.program_id, &self.mint);
+        let (config, config_data) = config_account(
+            &self.mollusk,
+            &self.program_id,
+            &admin(),
+            &self.mint,
+            &Pubkey::new_unique(),
+        );
+        let buyer_token_account = Pubkey::new_unique();
+        let (plan, plan_data) = match self.plan_prices.iter().find(|(id, _)| *id == plan_id) {
+            Some((_, price)) => plan_for_sale(&self.mollusk, &self.program_id, plan_id, *price),
+            None => (
+                client::plan_address(&self.program_id, plan_id).0,
+                AccountSharedData::default(),
+            ),
+        };
+
+        let instruction = client::buy_plan(
+            &self.program_id,
+            &self.buyer,
+            &buyer_token_account,
+            &self.mint,
+            &token_program,
+            plan_id,
+            auto_renew,
+        );
+        let accounts = vec![
+            (
+                self.buyer,
+                AccountSharedData::new(1_000_000_000, 0, &system_program),
+            ),
+            (
+                buyer_token_account,
+                pack_token_account(&self.buyer, &self.mint, 2 * STARTER_PRICE),
+            ),
+            (treasury, pack_token_account(&treasury, &self.mint, 0)),
+            (self.plan_account, plan_account_data),
+            (system_program, system_program_account),
+            (token_program, token_program_account),
+            (config, config_data),
+            (self.mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+            (plan, plan_data),
+        ];
+
+        self.mollusk
+            .process_and_validate_instruction(&instruction, &accounts, checks)
+    }
+}
+
+#[test]
+fn test_buy_plan() {
+    let fixture = PlanFixture::new();
+    let result = fixture.buy(1, true, AccountSharedData::default(), &[Check::success()]);
+
+    let account = result
+        .get_account(&fixture.plan_account)
+        .expect("Missing plan account");
+    assert_eq!(
+        account.owner(),
+        &fixture.program_id,
+        "Program should own the plan account"
+    );
+    let data = account.data();
+    assert_eq!(data.len(), PlanAccount::LEN);
+    assert_eq!(data[0], fixture.plan_account_bump);
+    assert_eq!(data[1..33], fixture.buyer.to_bytes());
+    assert_eq!(data[33], 1, "Plan id should be recorded");
+    assert_eq!(data[34], 1, "Auto-renew should be recorded");
+    assert_eq!(i64::from_le_bytes(data[35..43].try_into().unwrap()), NOW);
+    assert_eq!(
+        i64::from_le_bytes(data[43..51].try_into().unwrap()),
+        NOW + PLAN_PERIOD
+    );
+    assert_eq!(
+        u64::from_le_bytes(data[51..59].try_into().unwrap()),
+        STARTER_PRICE * CREDITS_PER_USDC,
+        "Quota should be what the price buys at the config's price"
+    );
+
+    let (treasury, _) = client::treasury_address(&fixture.program_id, &fixture.mint);
+    let treasury_balance = TokenAccount::unpack(
+        result
+            .get_account(&treasury)
+            .expect("Missing treasury")
+            .data(),
+    )
+    .expect("Treasury should be a token account")
+    .amount;
+    assert_eq!(treasury_balance, STARTER_PRICE);
+}
+
+#[test]
+fn test_buy_plan_renews_active_plan() {
+    let fixture = PlanFixture::new();
+    let plan_account = fixture.plan_account_data(1, NOW - 10, NOW + 10);
+
+    let result = fixture.buy(1, false, plan_account, &[Check::success()]);
+
+    let data = result
+        .get_account(&fixture.plan_account)
+        .expect("Missing plan account")
+        .data();
+    assert_eq!(data[34], 0, "Auto-renew should be replaced");
+    assert_eq!(
+        i64::from_le_bytes(data[35..43].try_into().unwrap()),
+        NOW - 10,
+        "Renewals should keep the start of the plan"
+    );
+    assert_eq!(
+        i64::from_le_bytes(data[43..51].try_into().unwrap()),
+        NOW + 10 + PLAN_PERIOD,
+        "Renewals should start when the paid period ends"
+    );
+}
+
+#[test]
+fn test_buy_plan_after_expiry() {
+    let fixture = PlanFixture::new();
+    // An ended Pro plan can be replaced by any other
+    let plan_account = fixture.plan_account_data(3, NOW - 2 * PLAN_PERIOD, NOW - PLAN_PERIOD);
+
+    let result = fixture.buy(1, false, plan_account, &[Check::success()]);
+
+    let data = result
+        .get_account(&fixture.plan_account)
+        .expect("Missing plan account")
+        .data();
+    assert_eq!(data[33], 1);
+    assert_eq!(i64::from_le_bytes(data[35..43].try_into().unwrap()), NOW);
+    assert_eq!(
+        i64::from_le_bytes(data[43..51].try_into().unwrap()),
+        NOW + PLAN_PERIOD
+    );
+}
+
+#[test]
+fn test_buy_plan_other_plan_while_active() {
+    let fixture = PlanFixture::new();
+    let plan_account = fixture.plan_account_data(1, NOW - 10, NOW + 10);
+
+    fixture.buy(
+        2,
+        false,
+        plan_account,
+        &[custom_error(CreditSalesError::PlanActive)],
+    );
+}
+
+#[test]
+fn test_buy_plan_unknown_plan() {
+    let fixture = PlanFixture::new();
+
+    for plan_id in [0, 4, u8::MAX] {
+        fixture.buy(
+            plan_id,
+            false,
+            AccountSharedData::default(),
+            &[custom_error(CreditSalesError::InvalidPlan)],
+        );
+    }
+}
+
+#[test]
+fn test_buy_plan_someone_elses_plan_account() {
+    let fixture = PlanFixture::new();
+    let mut plan_account = fixture.plan_account_data(1, NOW - 10, NOW + 10);
+    plan_account.data_as_mut_slice()[1..33].copy_from_slice(&Pubkey::new_unique().to_bytes());
+
+    fixture.buy(
+        1,
+        false,
+        plan_account,
+        &[custom_error(CreditSalesError::Unauthorized)],
+    );
+}
+
+#[test]
+fn test_buy_plan_at_admin_price() {
+    let mut fixture = PlanFixture::new();
+    fixture.plan_prices = vec![(1, 15)];
+
+    let result = fixture.buy(1, false, AccountSharedData::default(), &[Check::success()]);
+
+    let (treasury, _) = client::treasury_address(&fixture.program_id, &fixture.mint);
+    let treasury_balance = TokenAccount::unpack(
+        result
+            .get_account(&treasury)
+            .expect("Missing treasury")
+            .data(),
+    )
+    .expect("Treasury should be a token account")
+    .amount;
+    assert_eq!(treasury_balance, 15 * 10u64.pow(MINT_DECIMALS as u32));
+}
+
+#[test]
+fn test_buy_plan_withdrawn_plan() {
+    let mut fixture = PlanFixture::new();
+    fixture.plan_prices = vec![(1, 0)];
+
+    fixture.buy(
+        1,
+        false,
+        AccountSharedData::default(),
+        &[custom_error(CreditSalesError::InvalidPlan)],
+    );
+}
@@ -0,0 +1,345 @@
+use {
+    super::*,
+    crate::state::{Config, CreditsAccount, Plan, PlanAccount},
+    credit_sales_client as client,
+    mollusk_svm::{program::keyed_account_for_system_program, result::Check},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_program,
+    },
+    spl_token::state::Account as TokenAccount,
+};
+
+#[test]
+fn test_client_addresses_match_program() {
+    let program_id = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    assert_eq!(
+        client::config_address(&program_id),
+        Pubkey::find_program_address(&[Config::SEED], &program_id)
+    );
+    assert_eq!(
+        client::treasury_address(&program_id, &mint),
+        Pubkey::find_program_address(&[Config::TREASURY_SEED, mint.as_ref()], &program_id)
+    );
+    assert_eq!(
+        client::credits_account_address(&program_id, &buyer),
+        Pubkey::find_program_address(&[CreditsAccount::SEED, buyer.as_ref()], &program_id)
+    );
+    assert_eq!(
+        client::plan_account_address(&program_id, &buyer),
+        Pubkey::find_program_address(&[PlanAccount::SEED, buyer.as_ref()], &program_id)
+    );
+    assert_eq!(
+        client::plan_address(&program_id, 1),
+        Pubkey::find_program_address(&[Plan::SEED, &[1]], &program_id)
+    );
+}
+
+#[test]
+fn test_client_decodes_config() {
+    let (program_id, mollusk) = setup();
+    let mint = Pubkey::new_unique();
+    let settlement_authority = Pubkey::new_unique();
+    let (_, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &settlement_authority,
+    );
+
+    let config = client::Config::decode(config_data.data()).expect("Config should decode");
+    assert_eq!(config.admin, admin());
+    assert_eq!(
+        config.treasury,
+        client::treasury_address(&program_id, &mint).0
+    );
+    assert_eq!(config.mint, mint);
+    assert_eq!(config.settlement_authority, settlement_authority);
+    assert_eq!(config.credits_per_usdc, CREDITS_PER_USDC);
+    assert!(!config.paused);
+    assert_eq!(config.refund_window, REFUND_WINDOW);
+    assert_eq!(config.token_program, spl_token::id());
+    assert_eq!(config.decimals, MINT_DECIMALS);
+
+    assert_eq!(client::Config::LEN, crate::state::Config::LEN);
+    assert_eq!(
+        client::Config::decode(&config_data.data()[1..]),
+        None,
+        "Other lengths should not decode"
+    );
+}
+
+#[test]
+fn test_client_initialize_and_buy_credits() {
+    let (program_id, mut mollusk) = setup();
+    mollusk.sysvars.clock.unix_timestamp = 1_700_000_000;
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+    let (clock_sysvar, clock_sysvar_account) = clock_sysvar();
+
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &Pubkey::new_unique(),
+    );
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+    let (credits_account, _) = client::credits_account_address(&program_id, &buyer);
+
+    let instructions = [
+        client::initialize_credits_account(&program_id, &buyer),
+        client::buy_credits(
+            &program_id,
+            &buyer,
+            &buyer_token_account,
+            &mint,
+            &token_program,
+            10,
+        ),
+    ];
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+        ),
+        (buyer_token_account, pack_token_account(&buyer, &mint, 10)),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (credits_account, AccountSharedData::default()),
+        (clock_sysvar, clock_sysvar_account),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    let result = mollusk.process_and_validate_instruction_chain(
+        &instructions,
+        &accounts,
+        &[Check::success()],
+    );
+
+    let credits = client::CreditsAccount::decode(
+        result
+            .get_account(&credits_account)
+            .expect("Missing credits account")
+            .data(),
+    )
+    .expect("Credits account should decode");
+    assert_eq!(credits.owner, buyer);
+    assert_eq!(credits.timestamp, 1_700_000_000);
+    assert_eq!(credits.credits_amount, 10 * CREDITS_PER_USDC);
+    assert_eq!(credits.purchase_count, 1);
+    assert_eq!(credits.credits_available(), 10 * CREDITS_PER_USDC);
+}
+
+#[test]
+fn test_client_consume_credits() {
+    let (program_id, mollusk) = setup();
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &authority,
+    );
+
+    let buyers = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut accounts = vec![
+        (authority, AccountSharedData::default()),
+        (config, config_data),
+    ];
+    for buyer in &buyers {
+        let (credits_account, mut credits_account_data) =
+            initialized_credits_account(&mollusk, &program_id, buyer);
+        credits_account_data.data_as_mut_slice()[8..16].copy_from_slice(&500u64.to_le_bytes());
+        accounts.push((credits_account, credits_account_data));
+    }
+
+    let instruction = client::consume_credits(
+        &program_id,
+        &authority,
+        &[(buyers[0], 100), (buyers[1], 500)],
+    );
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    for (buyer, consumed) in buyers.iter().zip([100, 500]) {
+        let (credits_account, _) = client::credits_account_address(&program_id, buyer);
+        let credits = client::CreditsAccount::decode(
+            result
+                .get_account(&credits_account)
+                .expect("Missing credits account")
+                .data(),
+        )
+        .expect("Credits account should decode");
+        assert_eq!(credits.credits_consumed, consumed);
+        assert_eq!(credits.credits_available(), 500 - consumed);
+    }
+}
+
+#[test]
+fn test_client_buy_plan() {
+    let (program_id, mut mollusk) = setup();
+    mollusk.sysvars.clock.unix_timestamp = 1_700_000_000;
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let price = 10 * 10u64.pow(MINT_DECIMALS as u32);
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &Pubkey::new_unique(),
+    );
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+    let (plan_account, _) = client::plan_account_address(&program_id, &buyer);
+
+    let instruction = client::buy_plan(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        1,
+        true,
+    );
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (
+            buyer_token_account,
+            pack_token_account(&buyer, &mint, price),
+        ),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (plan_account, AccountSharedData::default()),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        plan_for_sale(&mollusk, &program_id, 1, 10),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let plan = client::PlanAccount::decode(
+        result
+            .get_account(&plan_account)
+            .expect("Missing plan account")
+            .data(),
+    )
+    .expect("Plan account should decode");
+    assert_eq!(plan.owner, buyer);
+    assert_eq!(plan.plan_id, 1);
+    assert!(plan.auto_renew);
+    assert_eq!(plan.monthly_quota, price * CREDITS_PER_USDC);
+    assert!(plan.is_active(1_700_000_000));
+    assert!(!plan.is_active(plan.period_end));
+}
+
+#[test]
+fn test_client_refund_credits() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+    let admin = admin();
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (config, config_data) =
+        config_account(&mollusk, &program_id, &admin, &mint, &Pubkey::new_unique());
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+    let (credits_account, credits_account_data) =
+        purchased_credits_account(&mollusk, &program_id, &buyer, &mint, 10, 0);
+
+    let instruction = client::refund_credits(
+        &program_id,
+        &admin,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        4 * CREDITS_PER_USDC,
+    );
+    let accounts = vec![
+        (admin, AccountSharedData::default()),
+        (buyer, AccountSharedData::default()),
+        (buyer_token_account, pack_token_account(&buyer, &mint, 0)),
+        (treasury, pack_token_account(&treasury, &mint, 10)),
+        (credits_account, credits_account_data),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let credits = client::CreditsAccount::decode(
+        result
+            .get_account(&credits_account)
+            .expect("Missing credits account")
+            .data(),
+    )
+    .expect("Credits account should decode");
+    assert_eq!(credits.credits_amount_refunded, 4 * CREDITS_PER_USDC);
+    assert_eq!(credits.credits_available(), 6 * CREDITS_PER_USDC);
+}
+
+#[test]
+fn test_client_withdraw_usdc() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let admin_token_account = Pubkey::new_unique();
+    let (config, config_data) =
+        config_account(&mollusk, &program_id, &admin, &mint, &Pubkey::new_unique());
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let instruction = client::withdraw_usdc(
+        &program_id,
+        &admin,
+        &admin_token_account,
+        &mint,
+        &token_program,
+        7,
+    );
+    let accounts = vec![
+        (treasury, pack_token_account(&treasury, &mint, 10)),
+        (admin, AccountSharedData::default()),
+        (admin_token_account, pack_token_account(&admin, &mint, 0)),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let admin_balance = TokenAccount::unpack(
+        result
+            .get_account(&admin_token_account)
+            .expect("Missing admin token account")
+            .data(),
+    )
+    .expect("Admin token account should unpack")
+    .amount;
+    assert_eq!(admin_balance, 7);
+}
@@ -0,0 +1,671 @@
+use {
+    super::*,
+    crate::{errors::CreditSalesError, state::Config},
+    credit_sales_client as client,
+    mollusk_svm::{program::keyed_account_for_system_program, result::Check},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_program,
+    },
+    spl_token::state::Account as TokenAccount,
+};
+
+/// The test config's values, with `settlement_authority`.
+fn config_params(settlement_authority: Pubkey) -> client::ConfigParams {
+    client::ConfigParams {
+        credits_per_usdc: CREDITS_PER_USDC,
+        settlement_authority,
+        refund_window: REFUND_WINDOW,
+    }
+}
+
+/// Values `UpdateConfig` replaces the test config's with.
+fn updated_params(credits_per_usdc: u64, settlement_authority: Pubkey) -> client::ConfigParams {
+    client::ConfigParams {
+        credits_per_usdc,
+        settlement_authority,
+        refund_window: 2 * REFUND_WINDOW,
+    }
+}
+
+#[test]
+fn test_initialize_config() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let settlement_authority = Pubkey::new_unique();
+    let (config, bump) = client::config_address(&program_id);
+    let (treasury, treasury_bump) = client::treasury_address(&program_id, &mint);
+
+    let instruction = client::initialize_config(
+        &program_id,
+        &admin,
+        &mint,
+        &spl_token::id(),
+        &config_params(settlement_authority),
+    );
+    let accounts = vec![
+        (
+            admin,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, AccountSharedData::default()),
+        (treasury, AccountSharedData::default()),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let account = result.get_account(&config).expect("Missing config");
+    assert_eq!(
+        account.owner(),
+        &program_id,
+        "Program should own the config"
+    );
+    let data = account.data();
+    assert_eq!(data.len(), Config::LEN);
+    assert_eq!(
+        data[0..32],
+        admin.to_bytes(),
+        "Deployer should be the admin"
+    );
+    assert_eq!(data[32..64], treasury.to_bytes());
+    assert_eq!(data[64..96], mint.to_bytes());
+    assert_eq!(data[96..128], settlement_authority.to_bytes());
+    assert_eq!(
+        u64::from_le_bytes(data[128..136].try_into().unwrap()),
+        CREDITS_PER_USDC
+    );
+    assert_eq!(data[136], 0, "Config should start unpaused");
+    assert_eq!(data[137], bump);
+    assert_eq!(data[138], treasury_bump);
+    assert_eq!(
+        i64::from_le_bytes(data[139..147].try_into().unwrap()),
+        REFUND_WINDOW
+    );
+    assert_eq!(data[147..179], spl_token::id().to_bytes());
+    assert_eq!(data[179], MINT_DECIMALS, "Decimals should be the mint's");
+
+    let treasury_account = TokenAccount::unpack(
+        result
+            .get_account(&treasury)
+            .expect("Missing treasury")
+            .data(),
+    )
+    .expect("Treasury should be a token account");
+    assert_eq!(treasury_account.mint, mint);
+    assert_eq!(
+        treasury_account.owner, treasury,
+        "Treasury should be its own authority"
+    );
+}
+
+#[test]
+fn test_initialize_config_twice() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let (config, config_data) =
+        config_account(&mollusk, &program_id, &admin, &mint, &Pubkey::new_unique());
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let instruction = client::initialize_config(
+        &program_id,
+        &admin,
+        &mint,
+        &spl_token::id(),
+        &config_params(Pubkey::new_unique()),
+    );
+    let accounts = vec![
+        (
+            admin,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, config_data),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::AccountAlreadyInitialized)],
+    );
+}
+
+#[test]
+fn test_initialize_config_not_deployer() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    // Anyone could otherwise front-run the deployment and own the program
+    let impostor = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (config, _) = client::config_address(&program_id);
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let instruction = client::initialize_config(
+        &program_id,
+        &impostor,
+        &mint,
+        &spl_token::id(),
+        &config_params(Pubkey::new_unique()),
+    );
+    let accounts = vec![
+        (
+            impostor,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, AccountSharedData::default()),
+        (treasury, AccountSharedData::default()),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::Unauthorized)],
+    );
+}
+
+#[test]
+fn test_initialize_config_other_token_program() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let (config, _) = client::config_address(&program_id);
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    // Transfers would otherwise be made through whatever program is passed
+    let fake_token_program = Pubkey::new_unique();
+    let mut instruction = client::initialize_config(
+        &program_id,
+        &admin,
+        &mint,
+        &spl_token::id(),
+        &config_params(Pubkey::new_unique()),
+    );
+    instruction.accounts[5].pubkey = fake_token_program;
+    let accounts = vec![
+        (
+            admin,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, AccountSharedData::default()),
+        (treasury, AccountSharedData::default()),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        (system_program, system_program_account),
+        (fake_token_program, AccountSharedData::default()),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidTokenProgram)],
+    );
+}
+
+#[test]
+fn test_initialize_config_token_2022_mint_extensions() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let token_2022 = Pubkey::new_from_array(crate::token::TOKEN_2022_PROGRAM_ID);
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let (config, _) = client::config_address(&program_id);
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    for (mint_account, token_program, error) in [
+        // A legacy mint passed with Token-2022
+        (
+            pack_mint(&Pubkey::new_unique(), 1_000_000),
+            token_2022,
+            CreditSalesError::InvalidMint,
+        ),
+        // TransferHook
+        (
+            pack_token_2022_mint(&[(1, 108), (14, 64)]),
+            token_2022,
+            CreditSalesError::UnsupportedMint,
+        ),
+        // PermanentDelegate
+        (
+            pack_token_2022_mint(&[(12, 32)]),
+            token_2022,
+            CreditSalesError::UnsupportedMint,
+        ),
+    ] {
+        let mut instruction = client::initialize_config(
+            &program_id,
+            &admin,
+            &mint,
+            &spl_token::id(),
+            &config_params(Pubkey::new_unique()),
+        );
+        instruction.accounts[5].pubkey = token_program;
+        let accounts = vec![
+            (
+                admin,
+                AccountSharedData::new(1_000_000_000, 0, &system_program),
+            ),
+            (config, AccountSharedData::default()),
+            (treasury, AccountSharedData::default()),
+            (mint, mint_account),
+            (system_program, system_program_account.clone()),
+            (token_program, AccountSharedData::default()),
+        ];
+
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[custom_error(error)]);
+    }
+}
+
+#[test]
+fn test_update_config() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    // Switch to another mint whose treasury already exists, and pause
+    let mint = Pubkey::new_unique();
+    let (treasury, treasury_bump) = client::treasury_address(&program_id, &mint);
+    let settlement_authority = Pubkey::new_unique();
+
+    let instruction = client::update_config(
+        &program_id,
+        &admin,
+        &mint,
+        &spl_token::id(),
+        &updated_params(50_000, settlement_authority),
+        true,
+    );
+    let accounts = vec![
+        (
+            admin,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, config_data),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let data = result.get_account(&config).expect("Missing config").data();
+    assert_eq!(data[0..32], admin.to_bytes(), "Admin should be untouched");
+    assert_eq!(data[32..64], treasury.to_bytes());
+    assert_eq!(data[64..96], mint.to_bytes());
+    assert_eq!(data[96..128], settlement_authority.to_bytes());
+    assert_eq!(
+        u64::from_le_bytes(data[128..136].try_into().unwrap()),
+        50_000
+    );
+    assert_eq!(data[136], 1, "Config should be paused");
+    assert_eq!(data[138], treasury_bump);
+    assert_eq!(
+        i64::from_le_bytes(data[139..147].try_into().unwrap()),
+        2 * REFUND_WINDOW
+    );
+    assert_eq!(data[147..179], spl_token::id().to_bytes());
+    assert_eq!(data[179], MINT_DECIMALS);
+}
+
+#[test]
+fn test_update_config_treasury_of_other_mint() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let (config, config_data) =
+        config_account(&mollusk, &program_id, &admin, &mint, &Pubkey::new_unique());
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    // The treasury's address is right but it holds another token
+    let instruction = client::update_config(
+        &program_id,
+        &admin,
+        &mint,
+        &spl_token::id(),
+        &updated_params(50_000, Pubkey::new_unique()),
+        false,
+    );
+    let accounts = vec![
+        (
+            admin,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, config_data),
+        (
+            treasury,
+            pack_token_account(&treasury, &Pubkey::new_unique(), 0),
+        ),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidAccountData)],
+    );
+}
+
+#[test]
+fn test_update_config_not_admin() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let mint = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &Pubkey::new_unique(),
+    );
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let impostor = Pubkey::new_unique();
+    let instruction = client::update_config(
+        &program_id,
+        &impostor,
+        &mint,
+        &spl_token::id(),
+        &updated_params(1, Pubkey::new_unique()),
+        false,
+    );
+    let accounts = vec![
+        (
+            impostor,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, config_data),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::Unauthorized)],
+    );
+}
+
+#[test]
+fn test_transfer_admin() {
+    let (program_id, mollusk) = setup();
+
+    let admin = admin();
+    let new_admin = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    let instruction = client::transfer_admin(&program_id, &admin, &new_admin);
+    let accounts = vec![
+        (admin, AccountSharedData::default()),
+        (config, config_data),
+        (new_admin, AccountSharedData::default()),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let data = result.get_account(&config).expect("Missing config").data();
+    assert_eq!(
+        data[0..32],
+        new_admin.to_bytes(),
+        "Admin should be replaced"
+    );
+}
+
+#[test]
+fn test_transfer_admin_requires_new_admin_signature() {
+    let (program_id, mollusk) = setup();
+
+    let admin = admin();
+    let new_admin = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    let mut instruction = client::transfer_admin(&program_id, &admin, &new_admin);
+    instruction.accounts[2].is_signer = false;
+    let accounts = vec![
+        (admin, AccountSharedData::default()),
+        (config, config_data),
+        (new_admin, AccountSharedData::default()),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+#[test]
+fn test_withdraw_usdc_not_admin() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let mint = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &Pubkey::new_unique(),
+    );
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let impostor = Pubkey::new_unique();
+    let impostor_usdc = Pubkey::new_unique();
+    let instruction = client::withdraw_usdc(
+        &program_id,
+        &impostor,
+        &impostor_usdc,
+        &mint,
+        &token_program,
+        10,
+    );
+    let accounts = vec![
+        (treasury, pack_token_account(&treasury, &mint, 10)),
+        (impostor, AccountSharedData::default()),
+        (impostor_usdc, pack_token_account(&impostor, &mint, 0)),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::Unauthorized)],
+    );
+}
+
+#[test]
+fn test_config_at_other_address() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    // A program account that looks like the config but isn't its PDA
+    let (_, config_data) =
+        config_account(&mollusk, &program_id, &admin, &mint, &Pubkey::new_unique());
+    let fake_config = Pubkey::new_unique();
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let admin_usdc = Pubkey::new_unique();
+    let mut instruction =
+        client::withdraw_usdc(&program_id, &admin, &admin_usdc, &mint, &token_program, 10);
+    instruction.accounts[4].pubkey = fake_config;
+    let accounts = vec![
+        (treasury, pack_token_account(&treasury, &mint, 10)),
+        (admin, AccountSharedData::default()),
+        (admin_usdc, pack_token_account(&admin, &mint, 0)),
+        (token_program, token_program_account),
+        (fake_config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidPda)],
+    );
+}
+
+#[test]
+fn test_buy_credits_paused() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let mint = Pubkey::new_unique();
+    let (config, mut config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &Pubkey::new_unique(),
+    );
+    config_data.data_as_mut_slice()[136] = 1;
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let buyer = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (credits_account, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &buyer);
+    let (clock_sysvar, clock_sysvar_account) = clock_sysvar();
+
+    let instruction = client::buy_credits(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        10,
+    );
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000, 0, &system_program::id()),
+        ),
+        (buyer_token_account, pack_token_account(&buyer, &mint, 10)),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (credits_account, credits_account_data),
+        (clock_sysvar, clock_sysvar_account),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::ProgramPaused)],
+    );
+}
+
+#[test]
+fn test_buy_credits_other_mint() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let mint = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &Pubkey::new_unique(),
+    );
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let buyer = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (credits_account, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &buyer);
+    let (clock_sysvar, clock_sysvar_account) = clock_sysvar();
+
+    // The buyer's tokens are of another mint than the treasury takes
+    let other_mint = Pubkey::new_unique();
+    let mut instruction = client::buy_credits(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        10,
+    );
+    instruction.accounts[7].pubkey = other_mint;
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000, 0, &system_program::id()),
+        ),
+        (
+            buyer_token_account,
+            pack_token_account(&buyer, &other_mint, 10),
+        ),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (credits_account, credits_account_data),
+        (clock_sysvar, clock_sysvar_account),
+        (token_program, token_program_account),
+        (config, config_data),
+        (other_mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidMint)],
+    );
+}
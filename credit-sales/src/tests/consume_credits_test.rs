@@ -0,0 +1,239 @@
+use {
+    super::*,
+    crate::errors::CreditSalesError,
+    credit_sales_client as client,
+    mollusk_svm::result::Check,
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
+        instruction::AccountMeta,
+        program_error::ProgramError,
+        pubkey::Pubkey,
+    },
+};
+
+/// Credits account of `buyer` with `credits_amount` bought, `refunded` of
+/// them refunded and `consumed` already settled.
+fn funded_credits_account(
+    mollusk: &mollusk_svm::Mollusk,
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    credits_amount: u64,
+    refunded: u64,
+    consumed: u64,
+) -> (Pubkey, AccountSharedData) {
+    let (address, mut account) = initialized_credits_account(mollusk, program_id, buyer);
+    let data = account.data_as_mut_slice();
+    data[8..16].copy_from_slice(&credits_amount.to_le_bytes());
+    data[16..24].copy_from_slice(&refunded.to_le_bytes());
+    data[65..73].copy_from_slice(&consumed.to_le_bytes());
+    (address, account)
+}
+
+fn credits_consumed(account: &AccountSharedData) -> u64 {
+    u64::from_le_bytes(account.data()[65..73].try_into().unwrap())
+}
+
+#[test]
+fn test_consume_credits_batch() {
+    let (program_id, mollusk) = setup();
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &authority,
+    );
+
+    let (first_buyer, second_buyer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (first, first_account) =
+        funded_credits_account(&mollusk, &program_id, &first_buyer, 1_000, 0, 0);
+    let (second, second_account) =
+        funded_credits_account(&mollusk, &program_id, &second_buyer, 1_000, 200, 300);
+
+    let instruction = client::consume_credits(
+        &program_id,
+        &authority,
+        &[(first_buyer, 400), (second_buyer, 800)],
+    );
+    let accounts = vec![
+        (authority, AccountSharedData::default()),
+        (config, config_data),
+        (first, first_account),
+        (second, second_account),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let first_account = result.get_account(&first).expect("Missing account");
+    let second_account = result.get_account(&second).expect("Missing account");
+    assert_eq!(credits_consumed(first_account), 400);
+    assert_eq!(credits_consumed(second_account), 800);
+    assert_eq!(
+        u64::from_le_bytes(first_account.data()[8..16].try_into().unwrap()),
+        1_000,
+        "Bought credits should be left untouched"
+    );
+}
+
+#[test]
+fn test_consume_credits_retry_is_noop() {
+    let (program_id, mollusk) = setup();
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &authority,
+    );
+
+    let buyer = Pubkey::new_unique();
+    let (credits_account, account) =
+        funded_credits_account(&mollusk, &program_id, &buyer, 1_000, 0, 500);
+
+    // Same total as already settled, then a stale one
+    for total in [500, 300] {
+        let instruction = client::consume_credits(&program_id, &authority, &[(buyer, total)]);
+        let accounts = vec![
+            (authority, AccountSharedData::default()),
+            (config, config_data.clone()),
+            (credits_account, account.clone()),
+        ];
+
+        let result =
+            mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+        assert_eq!(
+            credits_consumed(result.get_account(&credits_account).unwrap()),
+            500,
+            "Settled usage should never go down"
+        );
+    }
+}
+
+#[test]
+fn test_consume_credits_insufficient() {
+    let (program_id, mollusk) = setup();
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &authority,
+    );
+
+    // Only 600 credits are left once refunds are taken out
+    let buyer = Pubkey::new_unique();
+    let (credits_account, account) =
+        funded_credits_account(&mollusk, &program_id, &buyer, 1_000, 400, 0);
+
+    let instruction = client::consume_credits(&program_id, &authority, &[(buyer, 601)]);
+    let accounts = vec![
+        (authority, AccountSharedData::default()),
+        (config, config_data),
+        (credits_account, account),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InsufficientCredits)],
+    );
+}
+
+#[test]
+fn test_consume_credits_unauthorized() {
+    let (program_id, mollusk) = setup();
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    let buyer = Pubkey::new_unique();
+    let (credits_account, account) =
+        funded_credits_account(&mollusk, &program_id, &buyer, 1_000, 0, 0);
+
+    let instruction = client::consume_credits(&program_id, &authority, &[(buyer, 100)]);
+    let accounts = vec![
+        (authority, AccountSharedData::default()),
+        (config, config_data),
+        (credits_account, account),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::Unauthorized)],
+    );
+}
+
+#[test]
+fn test_consume_credits_missing_signature() {
+    let (program_id, mollusk) = setup();
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &authority,
+    );
+
+    let buyer = Pubkey::new_unique();
+    let (credits_account, account) =
+        funded_credits_account(&mollusk, &program_id, &buyer, 1_000, 0, 0);
+
+    let mut instruction = client::consume_credits(&program_id, &authority, &[(buyer, 100)]);
+    instruction.accounts[0].is_signer = false;
+    let accounts = vec![
+        (authority, AccountSharedData::default()),
+        (config, config_data),
+        (credits_account, account),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+#[test]
+fn test_consume_credits_data_mismatch() {
+    let (program_id, mollusk) = setup();
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &authority,
+    );
+
+    let buyer = Pubkey::new_unique();
+    let (credits_account, account) =
+        funded_credits_account(&mollusk, &program_id, &buyer, 1_000, 0, 0);
+
+    // One total for two accounts
+    let mut instruction = client::consume_credits(&program_id, &authority, &[(buyer, 100)]);
+    instruction
+        .accounts
+        .push(AccountMeta::new(credits_account, false));
+    let accounts = vec![
+        (authority, AccountSharedData::default()),
+        (config, config_data),
+        (credits_account, account),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidInstructionData)],
+    );
+}
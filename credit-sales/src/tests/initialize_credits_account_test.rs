@@ -0,0 +1,293 @@
+use {
+    super::*,
+    crate::{errors::CreditSalesError, state::CreditsAccount},
+    credit_sales_client as client,
+    mollusk_svm::{program::keyed_account_for_system_program, result::Check},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        pubkey::Pubkey,
+        system_program,
+    },
+};
+
+#[test]
+fn test_initialize_credits_account() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    let buyer = Pubkey::new_unique();
+    let (credits_account, bump) = client::credits_account_address(&program_id, &buyer);
+
+    let instruction = client::initialize_credits_account(&program_id, &buyer);
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (credits_account, AccountSharedData::default()),
+        (system_program, system_program_account),
+        (config, config_data),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let account = result
+        .get_account(&credits_account)
+        .expect("Failed to get credits account");
+    assert_eq!(account.owner(), &program_id, "Program should own the PDA");
+    assert_eq!(account.data().len(), CreditsAccount::LEN);
+    assert_eq!(
+        account.lamports(),
+        mollusk.sysvars.rent.minimum_balance(CreditsAccount::LEN),
+        "PDA should be rent exempt"
+    );
+    assert_eq!(
+        account.data()[0..24],
+        [0u8; 24],
+        "Counters should start at 0"
+    );
+    assert_eq!(account.data()[24], bump, "Bump should be stored");
+    assert_eq!(
+        account.data()[25..57],
+        buyer.to_bytes(),
+        "Buyer should own the credits account"
+    );
+    assert_eq!(
+        account.data()[57..65],
+        [0u8; 8],
+        "No purchases should be counted"
+    );
+}
+
+#[test]
+fn test_initialize_credits_account_prefunded() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    let buyer = Pubkey::new_unique();
+    let (credits_account, _) = client::credits_account_address(&program_id, &buyer);
+
+    // Someone sent lamports to the PDA before it was created
+    let instruction = client::initialize_credits_account(&program_id, &buyer);
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (
+            credits_account,
+            AccountSharedData::new(1_000, 0, &system_program),
+        ),
+        (system_program, system_program_account),
+        (config, config_data),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let account = result
+        .get_account(&credits_account)
+        .expect("Failed to get credits account");
+    assert_eq!(account.owner(), &program_id, "Program should own the PDA");
+    assert_eq!(
+        account.lamports(),
+        mollusk.sysvars.rent.minimum_balance(CreditsAccount::LEN),
+        "PDA should be topped up to rent exemption"
+    );
+}
+
+#[test]
+fn test_initialize_credits_account_wrong_pda() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    let buyer = Pubkey::new_unique();
+    // Another buyer's PDA
+    let (foreign_pda, _) = client::credits_account_address(&program_id, &Pubkey::new_unique());
+
+    let mut instruction = client::initialize_credits_account(&program_id, &buyer);
+    instruction.accounts[1].pubkey = foreign_pda;
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (foreign_pda, AccountSharedData::default()),
+        (system_program, system_program_account),
+        (config, config_data),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidPda)],
+    );
+}
+
+#[test]
+fn test_initialize_credits_account_non_canonical_bump() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    let buyer = Pubkey::new_unique();
+    let (credits_account, bump) = client::credits_account_address(&program_id, &buyer);
+
+    let mut instruction = client::initialize_credits_account(&program_id, &buyer);
+    instruction.data[1] = bump.wrapping_sub(1);
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (credits_account, AccountSharedData::default()),
+        (system_program, system_program_account),
+        (config, config_data),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidPda)],
+    );
+}
+
+#[test]
+fn test_initialize_credits_account_twice() {
+    let (program_id, mollusk) = setup();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+
+    let buyer = Pubkey::new_unique();
+    let (credits_account, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &buyer);
+
+    let instruction = client::initialize_credits_account(&program_id, &buyer);
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (credits_account, credits_account_data),
+        (system_program, system_program_account),
+        (config, config_data),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::AccountAlreadyInitialized)],
+    );
+}
+
+#[test]
+fn test_buy_credits_rejects_invalid_credits_accounts() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let (clock_sysvar, clock_sysvar_account) = clock_sysvar();
+
+    let usdc_mint = Pubkey::new_unique();
+    let (treasury, _) = client::treasury_address(&program_id, &usdc_mint);
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &usdc_mint,
+        &Pubkey::new_unique(),
+    );
+
+    let buyer = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (credits_account, initialized) = initialized_credits_account(&mollusk, &program_id, &buyer);
+
+    let rent = mollusk.sysvars.rent.minimum_balance(CreditsAccount::LEN);
+    let uninitialized = AccountSharedData::new(rent, CreditsAccount::LEN, &program_id);
+    let foreign_owner = AccountSharedData::new(rent, CreditsAccount::LEN, &Pubkey::new_unique());
+    let (_, other_buyers) =
+        initialized_credits_account(&mollusk, &program_id, &Pubkey::new_unique());
+
+    // An account that records the buyer but isn't their PDA
+    let not_pda = Pubkey::new_unique();
+
+    for (address, account, error) in [
+        (
+            credits_account,
+            uninitialized,
+            CreditSalesError::UninitializedAccount,
+        ),
+        (
+            credits_account,
+            foreign_owner,
+            CreditSalesError::InvalidAccountData,
+        ),
+        (
+            credits_account,
+            other_buyers,
+            CreditSalesError::Unauthorized,
+        ),
+        (not_pda, initialized, CreditSalesError::InvalidPda),
+    ] {
+        let mut instruction = client::buy_credits(
+            &program_id,
+            &buyer,
+            &buyer_token_account,
+            &usdc_mint,
+            &token_program,
+            10,
+        );
+        instruction.accounts[3].pubkey = address;
+        let accounts = vec![
+            (
+                buyer,
+                AccountSharedData::new(1_000_000, 0, &system_program::id()),
+            ),
+            (
+                buyer_token_account,
+                pack_token_account(&buyer, &usdc_mint, 10),
+            ),
+            (treasury, pack_token_account(&treasury, &usdc_mint, 0)),
+            (address, account),
+            (clock_sysvar, clock_sysvar_account.clone()),
+            (token_program, token_program_account.clone()),
+            (config, config_data.clone()),
+            (usdc_mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        ];
+
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[custom_error(error)]);
+    }
+}
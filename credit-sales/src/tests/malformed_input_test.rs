@@ -0,0 +1,317 @@
+use {
+    super::*,
+    crate::errors::CreditSalesError,
+    credit_sales_client as client,
+    mollusk_svm::result::Check,
+    solana_sdk::{
+        account::AccountSharedData,
+        instruction::{AccountMeta, Instruction},
+        program_error::ProgramError,
+        pubkey::Pubkey,
+        system_program,
+    },
+};
+
+/// Instruction data that doesn't parse, rejected before any account is read.
+#[test]
+fn test_malformed_instruction_data() {
+    let (program_id, mollusk) = setup();
+
+    let pubkey = [7u8; 32];
+    let cases: Vec<Vec<u8>> = vec![
+        // Empty
+        vec![],
+        // Unknown instruction
+        vec![99],
+        // Buy without amount
+        vec![0],
+        // Buy with short amount
+        vec![0, 1, 0, 0],
+        // Buy with trailing bytes
+        [vec![0], vec![0; 9]].concat(),
+        // Refund with short amount
+        vec![1, 1],
+        // Refund with trailing bytes
+        [vec![1], vec![0; 9]].concat(),
+        // Withdraw with short amount
+        vec![2, 1, 2, 3],
+        // Withdraw with trailing bytes
+        [vec![2], vec![0; 9]].concat(),
+        // Initialize credits account without bump
+        vec![3],
+        // Initialize credits account with trailing bytes
+        vec![3, 255, 0],
+        // Initialize config without data
+        vec![5],
+        // Initialize config without refund window
+        [vec![5, 255, 255], vec![1; 8], pubkey.to_vec()].concat(),
+        // Update config with an invalid paused flag
+        [
+            vec![6, 255],
+            vec![1; 8],
+            vec![2],
+            pubkey.to_vec(),
+            vec![0; 8],
+        ]
+        .concat(),
+        // Update config with trailing bytes
+        [
+            vec![6, 255],
+            vec![1; 8],
+            vec![0],
+            pubkey.to_vec(),
+            vec![0; 9],
+        ]
+        .concat(),
+        // Transfer admin with data
+        vec![7, 0],
+        // Buy plan without auto-renew flag
+        vec![8, 255, 1],
+        // Buy plan with an invalid auto-renew flag
+        vec![8, 255, 1, 2],
+        // Buy plan with trailing bytes
+        vec![8, 255, 1, 0, 0],
+        // Set plan with short price
+        vec![9, 255, 1, 0, 0],
+        // Set plan with trailing bytes
+        [vec![9, 255, 1], vec![0; 9]].concat(),
+    ];
+
+    for data in cases {
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![],
+            data,
+        };
+
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[],
+            &[custom_error(CreditSalesError::InvalidInstructionData)],
+        );
+    }
+}
+
+/// Well-formed data with fewer accounts than the instruction takes.
+#[test]
+fn test_missing_accounts() {
+    let (program_id, mollusk) = setup();
+
+    let amount = 1u64.to_le_bytes().to_vec();
+    let cases: Vec<Vec<u8>> = vec![
+        // Buy
+        [vec![0], amount.clone()].concat(),
+        // Refund
+        [vec![1], amount.clone()].concat(),
+        // Withdraw
+        [vec![2], amount.clone()].concat(),
+        // Initialize credits account
+        vec![3, 255],
+        // Consume
+        vec![4],
+        // Initialize config
+        [vec![5, 255, 255], amount.clone(), vec![0; 40]].concat(),
+        // Update config
+        [vec![6, 255], amount.clone(), vec![0], vec![0; 40]].concat(),
+        // Transfer admin
+        vec![7],
+        // Buy plan
+        vec![8, 255, 1, 0],
+        // Set plan
+        [vec![9, 255, 1], amount].concat(),
+    ];
+
+    let signer = Pubkey::new_unique();
+    for data in cases {
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![AccountMeta::new(signer, true)],
+            data,
+        };
+
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[(signer, AccountSharedData::default())],
+            &[custom_error(CreditSalesError::NotEnoughAccountKeys)],
+        );
+    }
+}
+
+/// Consuming takes at least one credits account.
+#[test]
+fn test_consume_credits_without_credits_accounts() {
+    let (program_id, mollusk) = setup();
+
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &authority,
+    );
+
+    let instruction = client::consume_credits(&program_id, &authority, &[]);
+    let accounts = vec![
+        (authority, AccountSharedData::default()),
+        (config, config_data),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::NotEnoughAccountKeys)],
+    );
+}
+
+#[test]
+fn test_buy_credits_without_buyer_signature() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let mint = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &mint,
+        &Pubkey::new_unique(),
+    );
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let buyer = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (credits_account, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &buyer);
+    let (clock_sysvar, clock_sysvar_account) = clock_sysvar();
+
+    let mut instruction = client::buy_credits(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        10,
+    );
+    instruction.accounts[0].is_signer = false;
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000, 0, &system_program::id()),
+        ),
+        (buyer_token_account, pack_token_account(&buyer, &mint, 10)),
+        (treasury, pack_token_account(&treasury, &mint, 0)),
+        (credits_account, credits_account_data),
+        (clock_sysvar, clock_sysvar_account),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+/// Refunds go to the buyer recorded in the credits account only.
+#[test]
+fn test_refund_credits_to_other_buyer() {
+    let (program_id, mollusk) = setup();
+    let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let (config, config_data) =
+        config_account(&mollusk, &program_id, &admin, &mint, &Pubkey::new_unique());
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let (credits_account, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &Pubkey::new_unique());
+    let other_buyer = Pubkey::new_unique();
+    let other_buyer_token_account = Pubkey::new_unique();
+
+    let mut instruction = client::refund_credits(
+        &program_id,
+        &admin,
+        &other_buyer,
+        &other_buyer_token_account,
+        &mint,
+        &token_program,
+        CREDITS_PER_USDC,
+    );
+    instruction.accounts[4].pubkey = credits_account;
+    let accounts = vec![
+        (admin, AccountSharedData::default()),
+        (other_buyer, AccountSharedData::default()),
+        (
+            other_buyer_token_account,
+            pack_token_account(&other_buyer, &mint, 0),
+        ),
+        (treasury, pack_token_account(&treasury, &mint, 1_000)),
+        (credits_account, credits_account_data),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::Unauthorized)],
+    );
+}
+
+/// Program accounts of the wrong size aren't credits accounts.
+#[test]
+fn test_credits_account_with_wrong_length() {
+    let (program_id, mollusk) = setup();
+
+    let authority = Pubkey::new_unique();
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &authority,
+    );
+
+    let buyer = Pubkey::new_unique();
+    let (credits_account, _) = client::credits_account_address(&program_id, &buyer);
+    let truncated = AccountSharedData::new(1_000_000, 57, &program_id);
+
+    let instruction = client::consume_credits(&program_id, &authority, &[(buyer, 1)]);
+    let accounts = vec![
+        (authority, AccountSharedData::default()),
+        (config, config_data),
+        (credits_account, truncated),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidAccountData)],
+    );
+}
+
+/// Accounts of the wrong size aren't the config.
+#[test]
+fn test_config_with_wrong_length() {
+    let (program_id, mollusk) = setup();
+
+    let admin = admin();
+    let (config, _) = client::config_address(&program_id);
+
+    let instruction = client::transfer_admin(&program_id, &admin, &admin);
+    let accounts = vec![
+        (admin, AccountSharedData::default()),
+        (config, AccountSharedData::new(1_000_000, 32, &program_id)),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[custom_error(CreditSalesError::InvalidAccountData)],
+    );
+}
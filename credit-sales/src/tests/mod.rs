@@ -1,14 +1,27 @@
+mod adversarial_test;
 mod buy_credits_test;
+mod buy_plan_test;
+mod client_test;
+mod config_test;
+mod consume_credits_test;
+mod initialize_credits_account_test;
+mod malformed_input_test;
 mod refund_credits_test;
+mod set_plan_test;
+mod token_2022_test;
 mod withdraw_usdc_test;
 
 use {
-    mollusk_svm::Mollusk,
+    crate::errors::CreditSalesError,
+    credit_sales_client as client,
+    mollusk_svm::{result::Check, Mollusk},
     solana_sdk::{
-        account::{AccountSharedData, WritableAccount},
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
+        program_error::ProgramError,
         program_option::COption,
         program_pack::Pack,
         pubkey::Pubkey,
+        sysvar,
     },
     spl_token::state::AccountState,
 };
@@ -23,16 +36,149 @@ fn setup() -> (Pubkey, Mollusk) {
     (program_id, mollusk)
 }
 
+/// Price set in the test config; 1 USD -> 100.000 credits.
+pub const CREDITS_PER_USDC: u64 = 100_000;
+
+/// Decimals of the mints packed by `pack_mint`.
+pub const MINT_DECIMALS: u8 = 9;
+
+/// Refund window set in the test config, one week.
+pub const REFUND_WINDOW: i64 = 60 * 60 * 24 * 7;
+
+pub fn admin() -> Pubkey {
+    Pubkey::new_from_array(crate::constants::ADMIN)
+}
+
+/// Config as left by `InitializeConfig`, accepting `mint`.
+pub fn config_account(
+    mollusk: &Mollusk,
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    mint: &Pubkey,
+    settlement_authority: &Pubkey,
+) -> (Pubkey, AccountSharedData) {
+    let (config, bump) = client::config_address(program_id);
+    let (treasury, treasury_bump) = client::treasury_address(program_id, mint);
+
+    let mut account = create_account(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(crate::state::Config::LEN),
+        crate::state::Config::LEN,
+        program_id,
+    );
+    let data = account.data_as_mut_slice();
+    data[0..32].copy_from_slice(&admin.to_bytes());
+    data[32..64].copy_from_slice(&treasury.to_bytes());
+    data[64..96].copy_from_slice(&mint.to_bytes());
+    data[96..128].copy_from_slice(&settlement_authority.to_bytes());
+    data[128..136].copy_from_slice(&CREDITS_PER_USDC.to_le_bytes());
+    data[136] = 0; // paused
+    data[137] = bump;
+    data[138] = treasury_bump;
+    data[139..147].copy_from_slice(&REFUND_WINDOW.to_le_bytes());
+    data[147..179].copy_from_slice(&spl_token::id().to_bytes());
+    data[179] = MINT_DECIMALS;
+
+    (config, account)
+}
+
+/// Check that the program failed with `error`.
+pub fn custom_error(error: CreditSalesError) -> Check<'static> {
+    Check::err(ProgramError::Custom(error as u32))
+}
+
 pub fn create_account(lamports: u64, data_len: usize, owner: &Pubkey) -> AccountSharedData {
     AccountSharedData::new(lamports, data_len, owner)
 }
 
+/// The clock sysvar as passed by `client::buy_credits`.
+pub fn clock_sysvar() -> (Pubkey, AccountSharedData) {
+    (
+        sysvar::clock::ID,
+        create_account(
+            0,
+            std::mem::size_of::<solana_sdk::clock::Clock>(),
+            &sysvar::ID,
+        ),
+    )
+}
+
+/// Credits account of `buyer` as left by `InitializeCreditsAccount`.
+pub fn initialized_credits_account(
+    mollusk: &Mollusk,
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+) -> (Pubkey, AccountSharedData) {
+    let (credits_account, bump) = client::credits_account_address(program_id, buyer);
+    let mut account = create_account(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(crate::state::CreditsAccount::LEN),
+        crate::state::CreditsAccount::LEN,
+        program_id,
+    );
+    let data = account.data_as_mut_slice();
+    data[24] = bump;
+    data[25..57].copy_from_slice(&buyer.to_bytes());
+
+    (credits_account, account)
+}
+
+/// Credits account of `buyer` after a single purchase of `amount_usdc` base
+/// units of `mint` at `timestamp`, at the test config's price.
+pub fn purchased_credits_account(
+    mollusk: &Mollusk,
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    mint: &Pubkey,
+    amount_usdc: u64,
+    timestamp: i64,
+) -> (Pubkey, AccountSharedData) {
+    let (credits_account, mut account) = initialized_credits_account(mollusk, program_id, buyer);
+    let data = account.data_as_mut_slice();
+    data[0..8].copy_from_slice(&timestamp.to_le_bytes());
+    data[8..16].copy_from_slice(&(amount_usdc * CREDITS_PER_USDC).to_le_bytes());
+    data[57..65].copy_from_slice(&1u64.to_le_bytes());
+    data[73..81].copy_from_slice(&timestamp.to_le_bytes());
+    data[81..89].copy_from_slice(&amount_usdc.to_le_bytes());
+    data[89..121].copy_from_slice(&mint.to_bytes());
+
+    (credits_account, account)
+}
+
+/// Plan `plan_id` as left by `SetPlan` with `price`.
+pub fn plan_for_sale(
+    mollusk: &Mollusk,
+    program_id: &Pubkey,
+    plan_id: u8,
+    price: u64,
+) -> (Pubkey, AccountSharedData) {
+    let (plan, bump) = client::plan_address(program_id, plan_id);
+    let mut account = create_account(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(crate::state::Plan::LEN),
+        crate::state::Plan::LEN,
+        program_id,
+    );
+    let data = account.data_as_mut_slice();
+    data[0] = bump;
+    data[1] = plan_id;
+    data[2..10].copy_from_slice(&price.to_le_bytes());
+
+    (plan, account)
+}
+
 pub fn pack_mint(mint_authority: &Pubkey, supply: u64) -> AccountSharedData {
     let mut account = create_account(0, spl_token::state::Mint::LEN, &spl_token::id());
     spl_token::state::Mint {
         mint_authority: COption::Some(*mint_authority),
         supply,
-        decimals: 9,
+        decimals: MINT_DECIMALS,
         is_initialized: true,
         freeze_authority: COption::None,
     }
@@ -40,6 +186,23 @@ pub fn pack_mint(mint_authority: &Pubkey, supply: u64) -> AccountSharedData {
     account
 }
 
+/// Token-2022 mint with zeroed `(extension type, length)` TLV entries.
+pub fn pack_token_2022_mint(extensions: &[(u16, u16)]) -> AccountSharedData {
+    let token_2022 = Pubkey::new_from_array(crate::token::TOKEN_2022_PROGRAM_ID);
+    let mut data = pack_mint(&Pubkey::new_unique(), 1_000_000).data().to_vec();
+    data.resize(crate::token::ACCOUNT_LEN, 0);
+    data.push(1); // AccountType::Mint
+    for (extension, len) in extensions {
+        data.extend_from_slice(&extension.to_le_bytes());
+        data.extend_from_slice(&len.to_le_bytes());
+        data.resize(data.len() + *len as usize, 0);
+    }
+
+    let mut account = create_account(0, data.len(), &token_2022);
+    account.data_as_mut_slice().copy_from_slice(&data);
+    account
+}
+
 pub fn pack_token_account(authority: &Pubkey, mint: &Pubkey, amount: u64) -> AccountSharedData {
     let mut account = create_account(0, spl_token::state::Account::LEN, &spl_token::id());
     spl_token::state::Account {
@@ -1,14 +1,13 @@
 use {
     super::*,
-    crate::state::CreditsAccount,
-    mollusk_svm::result::Check,
+    crate::{errors::CreditSalesError, state::CreditsAccount},
+    credit_sales_client as client,
+    mollusk_svm::result::{Check, InstructionResult},
     solana_sdk::{
-        account::{AccountSharedData, ReadableAccount},
-        instruction::{AccountMeta, Instruction},
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
         program_pack::Pack,
         pubkey::Pubkey,
         system_program,
-        sysvar::SysvarId,
     },
     spl_token::state::Account as TokenAccount,
 };
@@ -18,12 +17,6 @@ fn test_refund_credits() {
     // Setup the initial environment
     let (program_id, mollusk) = setup();
     let (token_program, token_program_account) = mollusk_token::token::keyed_account();
-    let _clock_sysvar = Pubkey::new_unique();
-    let _clock_sysvar_account = create_account(
-        0,
-        std::mem::size_of::<solana_sdk::clock::Clock>(),
-        &solana_sdk::clock::Clock::id(),
-    );
 
     // Admin and buyer accounts
     let admin = Pubkey::new_from_array(five8_const::decode_32_const(
@@ -36,11 +29,17 @@ fn test_refund_credits() {
     let usdc_mint = Pubkey::new_from_array(five8_const::decode_32_const(
         "11111111111111111111111111111111111111111111",
     ));
-    let _usdc_mint_account = pack_mint(&mint_authority, 1_000_000);
+    let usdc_mint_account = pack_mint(&mint_authority, 1_000_000);
 
     // Initialize the treasury PDA with initial balance
-    let (treasury_pda, treasury_bump) =
-        Pubkey::find_program_address(&[b"treasury", &admin.to_bytes()], &program_id);
+    let (treasury_pda, _) = client::treasury_address(&program_id, &usdc_mint);
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &usdc_mint,
+        &Pubkey::new_unique(),
+    );
     let treasury_account = pack_token_account(
         &treasury_pda, // Owner is treasury PDA
         &usdc_mint,    // USDC mint
@@ -56,8 +55,8 @@ fn test_refund_credits() {
     );
 
     // Initialize credits account with some existing credits
-    let (credits_account, _) =
-        Pubkey::find_program_address(&[b"credits_account", &buyer.to_bytes()], &program_id);
+    let (credits_account, credits_account_bump) =
+        client::credits_account_address(&program_id, &buyer);
     let mut credits_account_data = AccountSharedData::new(
         mollusk.sysvars.rent.minimum_balance(CreditsAccount::LEN),
         CreditsAccount::LEN,
@@ -67,32 +66,26 @@ fn test_refund_credits() {
     // Set initial credits state
     let credits_data = credits_account_data.data_as_mut_slice();
     credits_data[0..8].copy_from_slice(&0i64.to_le_bytes()); // timestamp
-    credits_data[8..16].copy_from_slice(&1_000u64.to_le_bytes()); // credits_amount
+    credits_data[8..16].copy_from_slice(&1_000_000u64.to_le_bytes()); // credits_amount
     credits_data[16..24].copy_from_slice(&0u64.to_le_bytes()); // credits_amount_refunded
-    credits_data[24] = treasury_bump; // bump
+    credits_data[24] = credits_account_bump; // bump
     credits_data[25..57].copy_from_slice(&buyer.to_bytes()); // owner
+    credits_data[57..65].copy_from_slice(&1u64.to_le_bytes()); // purchase_count
+    credits_data[73..81].copy_from_slice(&0i64.to_le_bytes()); // first_purchase_at
+    credits_data[81..89].copy_from_slice(&10u64.to_le_bytes()); // amount_paid
+    credits_data[89..121].copy_from_slice(&usdc_mint.to_bytes()); // mint
 
     // Create refund instruction
     let refund_amount = 500_000u64;
-    let instruction_data = [
-        vec![1],
-        refund_amount.to_le_bytes().to_vec(),
-        vec![treasury_bump],
-    ]
-    .concat();
-
-    let instruction = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(admin, true),
-            AccountMeta::new(buyer, false),
-            AccountMeta::new(buyer_token_account, false),
-            AccountMeta::new(treasury_pda, false),
-            AccountMeta::new(credits_account, false),
-            AccountMeta::new_readonly(token_program, false),
-        ],
-        data: instruction_data,
-    };
+    let instruction = client::refund_credits(
+        &program_id,
+        &admin,
+        &buyer,
+        &buyer_token_account,
+        &usdc_mint,
+        &token_program,
+        refund_amount,
+    );
 
     let accounts = vec![
         (
@@ -107,6 +100,8 @@ fn test_refund_credits() {
         (treasury_pda, treasury_account.clone()),
         (credits_account, credits_account_data),
         (token_program, token_program_account),
+        (config, config_data),
+        (usdc_mint, usdc_mint_account),
     ];
 
     // Log initial treasury amount
@@ -126,6 +121,236 @@ fn test_refund_credits() {
 
     assert!(
         final_treasury_amount < initial_treasury_amount,
-        "Treasury balance should decrease by refund amount divided by USDC_TO_CREDIT"
+        "Treasury balance should decrease by refund amount divided by the config's credits per USDC"
+    );
+}
+
+/// Buyer with 10 USDC of credits bought at `timestamp`, `refunded` and
+/// `consumed` of them already used up.
+struct RefundFixture {
+    program_id: Pubkey,
+    mollusk: mollusk_svm::Mollusk,
+    buyer: Pubkey,
+    mint: Pubkey,
+    credits_per_usdc: u64,
+    buyer_token_account: Pubkey,
+    buyer_token_account_data: AccountSharedData,
+    credits_account: Pubkey,
+    credits_account_data: AccountSharedData,
+}
+
+impl RefundFixture {
+    fn new(timestamp: i64, refunded: u64, consumed: u64) -> Self {
+        let (program_id, mollusk) = setup();
+        let buyer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let (credits_account, mut credits_account_data) =
+            purchased_credits_account(&mollusk, &program_id, &buyer, &mint, 10, timestamp);
+        let data = credits_account_data.data_as_mut_slice();
+        data[16..24].copy_from_slice(&refunded.to_le_bytes());
+        data[65..73].copy_from_slice(&consumed.to_le_bytes());
+
+        Self {
+            program_id,
+            mollusk,
+            buyer,
+            mint,
+            credits_per_usdc: CREDITS_PER_USDC,
+            buyer_token_account: Pubkey::new_unique(),
+            buyer_token_account_data: pack_token_account(&buyer, &mint, 0),
+            credits_account,
+            credits_account_data,
+        }
+    }
+
+    fn config(&self) -> (Pubkey, AccountSharedData) {
+        let (config, mut config_data) = config_account(
+            &self.mollusk,
+            &self.program_id,
+            &admin(),
+            &self.mint,
+            &Pubkey::new_unique(),
+        );
+        config_data.data_as_mut_slice()[128..136]
+            .copy_from_slice(&self.credits_per_usdc.to_le_bytes());
+        (config, config_data)
+    }
+
+    /// Buys `amount_usdc` more credits at the current clock and price.
+    fn top_up(&mut self, amount_usdc: u64) {
+        let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+        let (treasury, _) = client::treasury_address(&self.program_id, &self.mint);
+        let (config, config_data) = self.config();
+
+        let instruction = client::buy_credits(
+            &self.program_id,
+            &self.buyer,
+            &self.buyer_token_account,
+            &self.mint,
+            &token_program,
+            amount_usdc,
+        );
+        let accounts = vec![
+            (self.buyer, AccountSharedData::default()),
+            (
+                self.buyer_token_account,
+                pack_token_account(&self.buyer, &self.mint, amount_usdc),
+            ),
+            (treasury, pack_token_account(&treasury, &self.mint, 0)),
+            (self.credits_account, self.credits_account_data.clone()),
+            clock_sysvar(),
+            (token_program, token_program_account),
+            (config, config_data),
+            (self.mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        ];
+
+        let result = self.mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[Check::success()],
+        );
+        self.credits_account_data = result
+            .get_account(&self.credits_account)
+            .expect("Missing credits account")
+            .clone();
+    }
+
+    fn refund(&self, amount_of_credits: u64, check: Check) -> InstructionResult {
+        let (token_program, token_program_account) = mollusk_token::token::keyed_account();
+        let admin = admin();
+        let (treasury, _) = client::treasury_address(&self.program_id, &self.mint);
+        let (config, config_data) = self.config();
+
+        let instruction = client::refund_credits(
+            &self.program_id,
+            &admin,
+            &self.buyer,
+            &self.buyer_token_account,
+            &self.mint,
+            &token_program,
+            amount_of_credits,
+        );
+        let accounts = vec![
+            (admin, AccountSharedData::default()),
+            (self.buyer, AccountSharedData::default()),
+            (
+                self.buyer_token_account,
+                self.buyer_token_account_data.clone(),
+            ),
+            (treasury, pack_token_account(&treasury, &self.mint, 1_000)),
+            (self.credits_account, self.credits_account_data.clone()),
+            (token_program, token_program_account),
+            (config, config_data),
+            (self.mint, pack_mint(&Pubkey::new_unique(), 1_000_000)),
+        ];
+
+        self.mollusk
+            .process_and_validate_instruction(&instruction, &accounts, &[check])
+    }
+}
+
+#[test]
+fn test_refund_credits_up_to_available() {
+    // 3 USDC refunded and 2 consumed leave 5 to refund
+    let fixture = RefundFixture::new(0, 3 * CREDITS_PER_USDC, 2 * CREDITS_PER_USDC);
+
+    fixture.refund(5 * CREDITS_PER_USDC, Check::success());
+    fixture.refund(
+        6 * CREDITS_PER_USDC,
+        custom_error(CreditSalesError::RefundExceedsAvailable),
+    );
+}
+
+#[test]
+fn test_refund_credits_window() {
+    let mut fixture = RefundFixture::new(1_700_000_000, 0, 0);
+
+    fixture.mollusk.sysvars.clock.unix_timestamp = 1_700_000_000 + REFUND_WINDOW;
+    fixture.refund(CREDITS_PER_USDC, Check::success());
+
+    fixture.mollusk.sysvars.clock.unix_timestamp = 1_700_000_000 + REFUND_WINDOW + 1;
+    fixture.refund(
+        CREDITS_PER_USDC,
+        custom_error(CreditSalesError::RefundWindowExpired),
+    );
+}
+
+#[test]
+fn test_refund_credits_partial_usdc() {
+    let fixture = RefundFixture::new(0, 0, 0);
+
+    // Half a USDC can't be paid back in whole units
+    fixture.refund(
+        CREDITS_PER_USDC / 2,
+        custom_error(CreditSalesError::InvalidInstructionData),
+    );
+}
+
+#[test]
+fn test_refund_credits_to_someone_elses_token_account() {
+    let mut fixture = RefundFixture::new(0, 0, 0);
+    fixture.buyer_token_account_data = pack_token_account(&Pubkey::new_unique(), &fixture.mint, 0);
+
+    fixture.refund(
+        CREDITS_PER_USDC,
+        custom_error(CreditSalesError::InvalidTokenAccount),
+    );
+}
+
+#[test]
+fn test_refund_credits_to_token_account_of_other_mint() {
+    let mut fixture = RefundFixture::new(0, 0, 0);
+    fixture.buyer_token_account_data = pack_token_account(&fixture.buyer, &Pubkey::new_unique(), 0);
+
+    fixture.refund(
+        CREDITS_PER_USDC,
+        custom_error(CreditSalesError::InvalidTokenAccount),
+    );
+}
+
+#[test]
+fn test_refund_credits_window_not_reopened_by_top_up() {
+    let mut fixture = RefundFixture::new(1_700_000_000, 0, 0);
+
+    fixture.mollusk.sysvars.clock.unix_timestamp = 1_700_000_000 + REFUND_WINDOW + 1;
+    fixture.top_up(5);
+
+    // The window runs from the first purchase, not the last
+    fixture.refund(
+        CREDITS_PER_USDC,
+        custom_error(CreditSalesError::RefundWindowExpired),
+    );
+}
+
+#[test]
+fn test_refund_credits_at_price_paid() {
+    let mut fixture = RefundFixture::new(0, 0, 0);
+    // Credits got twice as cheap since the purchase
+    fixture.credits_per_usdc = 2 * CREDITS_PER_USDC;
+
+    let result = fixture.refund(2 * CREDITS_PER_USDC, Check::success());
+
+    let refunded = TokenAccount::unpack(
+        result
+            .get_account(&fixture.buyer_token_account)
+            .expect("Missing buyer token account")
+            .data(),
+    )
+    .expect("Buyer token account should unpack")
+    .amount;
+    assert_eq!(refunded, 2, "Refund should pay back what was paid");
+}
+
+#[test]
+fn test_refund_credits_after_mint_change() {
+    let mut fixture = RefundFixture::new(0, 0, 0);
+    // The config now takes another mint than the one the buyer paid in
+    fixture.mint = Pubkey::new_unique();
+    fixture.buyer_token_account_data = pack_token_account(&fixture.buyer, &fixture.mint, 0);
+
+    fixture.refund(
+        CREDITS_PER_USDC,
+        custom_error(CreditSalesError::InvalidMint),
     );
 }
@@ -0,0 +1,124 @@
+use {
+    super::*,
+    crate::{errors::CreditSalesError, state::Plan},
+    credit_sales_client as client,
+    mollusk_svm::{program::keyed_account_for_system_program, result::Check},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        instruction::Instruction,
+        pubkey::Pubkey,
+    },
+};
+
+/// Runs `instruction` built by `client::set_plan` with `plan_data` at the
+/// plan's address.
+fn set_plan(
+    program_id: &Pubkey,
+    mollusk: &mollusk_svm::Mollusk,
+    instruction: &Instruction,
+    plan_data: AccountSharedData,
+    checks: &[Check],
+) -> mollusk_svm::result::InstructionResult {
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (config, config_data) = config_account(
+        mollusk,
+        program_id,
+        &admin(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    );
+    let signer = instruction.accounts[0].pubkey;
+    let plan = instruction.accounts[2].pubkey;
+
+    let accounts = vec![
+        (
+            signer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, config_data),
+        (plan, plan_data),
+        (system_program, system_program_account),
+    ];
+
+    mollusk.process_and_validate_instruction(instruction, &accounts, checks)
+}
+
+#[test]
+fn test_set_plan_creates_plan() {
+    let (program_id, mollusk) = setup();
+    let (plan, bump) = client::plan_address(&program_id, 4);
+
+    let instruction = client::set_plan(&program_id, &admin(), 4, 25);
+    let result = set_plan(
+        &program_id,
+        &mollusk,
+        &instruction,
+        AccountSharedData::default(),
+        &[Check::success()],
+    );
+
+    let account = result.get_account(&plan).expect("Missing plan");
+    assert_eq!(account.owner(), &program_id);
+    assert_eq!(account.data().len(), Plan::LEN);
+    assert_eq!(
+        client::Plan::decode(account.data()),
+        Some(client::Plan {
+            bump,
+            plan_id: 4,
+            price: 25,
+        })
+    );
+}
+
+#[test]
+fn test_set_plan_reprices_plan() {
+    let (program_id, mollusk) = setup();
+    let (plan, plan_data) = plan_for_sale(&mollusk, &program_id, 1, 10);
+
+    // 0 withdraws the plan from sale
+    for price in [20, 0] {
+        let instruction = client::set_plan(&program_id, &admin(), 1, price);
+        let result = set_plan(
+            &program_id,
+            &mollusk,
+            &instruction,
+            plan_data.clone(),
+            &[Check::success()],
+        );
+
+        let account = result.get_account(&plan).expect("Missing plan");
+        assert_eq!(
+            client::Plan::decode(account.data()).map(|plan| plan.price),
+            Some(price)
+        );
+    }
+}
+
+#[test]
+fn test_set_plan_not_admin() {
+    let (program_id, mollusk) = setup();
+
+    let instruction = client::set_plan(&program_id, &Pubkey::new_unique(), 1, 1);
+    set_plan(
+        &program_id,
+        &mollusk,
+        &instruction,
+        AccountSharedData::default(),
+        &[custom_error(CreditSalesError::Unauthorized)],
+    );
+}
+
+#[test]
+fn test_set_plan_other_plans_address() {
+    let (program_id, mollusk) = setup();
+
+    let mut instruction = client::set_plan(&program_id, &admin(), 1, 1);
+    instruction.accounts[2].pubkey = client::plan_address(&program_id, 2).0;
+    set_plan(
+        &program_id,
+        &mollusk,
+        &instruction,
+        AccountSharedData::default(),
+        &[custom_error(CreditSalesError::InvalidPda)],
+    );
+}
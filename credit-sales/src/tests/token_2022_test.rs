@@ -0,0 +1,285 @@
+use {
+    super::*,
+    credit_sales_client as client,
+    mollusk_svm::{program::keyed_account_for_system_program, result::Check},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
+        program_option::COption,
+        pubkey::Pubkey,
+        system_program,
+    },
+    spl_token_2022::{
+        extension::{
+            transfer_fee::{TransferFee, TransferFeeAmount, TransferFeeConfig},
+            BaseStateWithExtensions, BaseStateWithExtensionsMut, ExtensionType,
+            StateWithExtensions, StateWithExtensionsMut,
+        },
+        state::{Account, AccountState, Mint},
+    },
+};
+
+/// Transfer fee of the test mint, 1%.
+const FEE_BASIS_POINTS: u16 = 100;
+
+fn token_2022() -> Pubkey {
+    Pubkey::new_from_array(crate::token::TOKEN_2022_PROGRAM_ID)
+}
+
+fn setup_token_2022() -> (Pubkey, mollusk_svm::Mollusk) {
+    let (program_id, mut mollusk) = setup();
+    mollusk_token::token2022::add_program(&mut mollusk);
+    mollusk.sysvars.clock.unix_timestamp = 1_700_000_000;
+    (program_id, mollusk)
+}
+
+/// Token-2022 mint charging `FEE_BASIS_POINTS` on every transfer.
+fn transfer_fee_mint() -> AccountSharedData {
+    let len = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig])
+        .unwrap();
+    let mut data = vec![0; len];
+    let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+
+    let fee = TransferFee {
+        epoch: 0.into(),
+        maximum_fee: u64::MAX.into(),
+        transfer_fee_basis_points: FEE_BASIS_POINTS.into(),
+    };
+    let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+    config.older_transfer_fee = fee;
+    config.newer_transfer_fee = fee;
+
+    state.base = Mint {
+        mint_authority: COption::Some(Pubkey::new_unique()),
+        supply: u64::MAX,
+        decimals: MINT_DECIMALS,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+
+    let mut account = create_account(0, len, &token_2022());
+    account.data_as_mut_slice().copy_from_slice(&data);
+    account
+}
+
+/// Token account of a `transfer_fee_mint`, holding `amount`.
+fn transfer_fee_token_account(authority: &Pubkey, mint: &Pubkey, amount: u64) -> AccountSharedData {
+    let len =
+        ExtensionType::try_calculate_account_len::<Account>(&[ExtensionType::TransferFeeAmount])
+            .unwrap();
+    let mut data = vec![0; len];
+    let mut state = StateWithExtensionsMut::<Account>::unpack_uninitialized(&mut data).unwrap();
+    state.init_extension::<TransferFeeAmount>(true).unwrap();
+
+    state.base = Account {
+        mint: *mint,
+        owner: *authority,
+        amount,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+
+    let mut account = create_account(0, len, &token_2022());
+    account.data_as_mut_slice().copy_from_slice(&data);
+    account
+}
+
+/// Config accepting `mint` through Token-2022.
+fn token_2022_config(
+    mollusk: &mollusk_svm::Mollusk,
+    program_id: &Pubkey,
+    mint: &Pubkey,
+) -> (Pubkey, AccountSharedData) {
+    let (config, mut config_data) =
+        config_account(mollusk, program_id, &admin(), mint, &Pubkey::new_unique());
+    config_data.data_as_mut_slice()[147..179].copy_from_slice(&token_2022().to_bytes());
+    (config, config_data)
+}
+
+fn token_amount(result: &mollusk_svm::result::InstructionResult, address: &Pubkey) -> u64 {
+    let account = result.get_account(address).expect("Missing token account");
+    StateWithExtensions::<Account>::unpack(account.data())
+        .expect("Token account should unpack")
+        .base
+        .amount
+}
+
+#[test]
+fn test_initialize_config_token_2022_treasury() {
+    let (program_id, mollusk) = setup_token_2022();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token2022::keyed_account();
+
+    let admin = admin();
+    let mint = Pubkey::new_unique();
+    let (config, _) = client::config_address(&program_id);
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+
+    let instruction = client::initialize_config(
+        &program_id,
+        &admin,
+        &mint,
+        &token_program,
+        &client::ConfigParams {
+            credits_per_usdc: CREDITS_PER_USDC,
+            settlement_authority: Pubkey::new_unique(),
+            refund_window: REFUND_WINDOW,
+        },
+    );
+    let accounts = vec![
+        (
+            admin,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (config, AccountSharedData::default()),
+        (treasury, AccountSharedData::default()),
+        (mint, transfer_fee_mint()),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    // Sized by `token_account_len` for the mint's TransferFeeAmount
+    let treasury_account = result
+        .get_account(&treasury)
+        .expect("Missing treasury account");
+    assert_eq!(treasury_account.owner(), &token_program);
+    assert_eq!(
+        treasury_account.data().len(),
+        ExtensionType::try_calculate_account_len::<Account>(&[ExtensionType::TransferFeeAmount])
+            .unwrap()
+    );
+    let treasury_state = StateWithExtensions::<Account>::unpack(treasury_account.data())
+        .expect("Treasury should unpack");
+    assert_eq!(treasury_state.base.mint, mint);
+    assert_eq!(treasury_state.base.owner, treasury);
+    assert!(treasury_state.get_extension::<TransferFeeAmount>().is_ok());
+
+    let config = client::Config::decode(
+        result
+            .get_account(&config)
+            .expect("Missing config account")
+            .data(),
+    )
+    .expect("Config should decode");
+    assert_eq!(config.token_program, token_program);
+    assert_eq!(config.decimals, MINT_DECIMALS);
+}
+
+#[test]
+fn test_buy_credits_token_2022_transfer_fee() {
+    let (program_id, mollusk) = setup_token_2022();
+    let (token_program, token_program_account) = mollusk_token::token2022::keyed_account();
+
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let (config, config_data) = token_2022_config(&mollusk, &program_id, &mint);
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+    let (credits_account, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &buyer);
+
+    let instruction = client::buy_credits(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        1_000,
+    );
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+        ),
+        (
+            buyer_token_account,
+            transfer_fee_token_account(&buyer, &mint, 1_000),
+        ),
+        (treasury, transfer_fee_token_account(&treasury, &mint, 0)),
+        (credits_account, credits_account_data),
+        clock_sysvar(),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, transfer_fee_mint()),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    // 1% of 1.000 is withheld in the treasury
+    assert_eq!(token_amount(&result, &treasury), 990);
+    let credits = client::CreditsAccount::decode(
+        result
+            .get_account(&credits_account)
+            .expect("Missing credits account")
+            .data(),
+    )
+    .expect("Credits account should decode");
+    assert_eq!(credits.credits_amount, 990 * CREDITS_PER_USDC);
+    assert_eq!(credits.amount_paid, 990);
+}
+
+#[test]
+fn test_buy_plan_token_2022_transfer_fee() {
+    let (program_id, mollusk) = setup_token_2022();
+    let (system_program, system_program_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = mollusk_token::token2022::keyed_account();
+
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let buyer_token_account = Pubkey::new_unique();
+    let price = 10 * 10u64.pow(MINT_DECIMALS as u32);
+    let (config, config_data) = token_2022_config(&mollusk, &program_id, &mint);
+    let (treasury, _) = client::treasury_address(&program_id, &mint);
+    let (plan_account, _) = client::plan_account_address(&program_id, &buyer);
+
+    let instruction = client::buy_plan(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &mint,
+        &token_program,
+        1,
+        false,
+    );
+    let accounts = vec![
+        (
+            buyer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program),
+        ),
+        (
+            buyer_token_account,
+            transfer_fee_token_account(&buyer, &mint, price),
+        ),
+        (treasury, transfer_fee_token_account(&treasury, &mint, 0)),
+        (plan_account, AccountSharedData::default()),
+        (system_program, system_program_account),
+        (token_program, token_program_account),
+        (config, config_data),
+        (mint, transfer_fee_mint()),
+        plan_for_sale(&mollusk, &program_id, 1, 10),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let received = price - price / 100;
+    assert_eq!(token_amount(&result, &treasury), received);
+    let plan = client::PlanAccount::decode(
+        result
+            .get_account(&plan_account)
+            .expect("Missing plan account")
+            .data(),
+    )
+    .expect("Plan account should decode");
+    assert_eq!(plan.monthly_quota, received * CREDITS_PER_USDC);
+}
@@ -1,13 +1,12 @@
 use {
     super::*,
+    credit_sales_client as client,
     mollusk_svm::result::Check,
     solana_sdk::{
         account::{AccountSharedData, ReadableAccount},
-        instruction::{AccountMeta, Instruction},
         program_pack::Pack,
         pubkey::Pubkey,
         system_program,
-        sysvar::SysvarId,
     },
     spl_token::state::Account as TokenAccount,
 };
@@ -17,12 +16,7 @@ fn test_withdraw_usdc() {
     // Setup the initial environment
     let (program_id, mollusk) = setup();
     let (token_program, token_program_account) = mollusk_token::token::keyed_account();
-    let clock_sysvar = Pubkey::new_unique();
-    let clock_sysvar_account = create_account(
-        0,
-        std::mem::size_of::<solana_sdk::clock::Clock>(),
-        &solana_sdk::clock::Clock::id(),
-    );
+    let (clock_sysvar, clock_sysvar_account) = clock_sysvar();
 
     // Admin and buyer accounts
     let admin = Pubkey::new_from_array(five8_const::decode_32_const(
@@ -38,8 +32,14 @@ fn test_withdraw_usdc() {
     let usdc_mint_account = pack_mint(&mint_authority, 1_000_000);
 
     // Initialize the treasury PDA
-    let (treasury_pda, treasury_bump) =
-        Pubkey::find_program_address(&[b"treasury", &admin.to_bytes()], &program_id);
+    let (treasury_pda, _) = client::treasury_address(&program_id, &usdc_mint);
+    let (config, config_data) = config_account(
+        &mollusk,
+        &program_id,
+        &admin,
+        &usdc_mint,
+        &Pubkey::new_unique(),
+    );
     let treasury_account = pack_token_account(
         &treasury_pda, // owner is treasury PDA itself
         &usdc_mint,    // USDC mint
@@ -55,33 +55,19 @@ fn test_withdraw_usdc() {
     );
 
     // Credits account PDA for the buyer
-    let (credits_account_pubkey, _) =
-        Pubkey::find_program_address(&[b"credits_account", &buyer.to_bytes()], &program_id);
-    let credits_account_data =
-        AccountSharedData::new(mollusk.sysvars.rent.minimum_balance(32), 32, &program_id);
-
-    // Prepare data for the BuyCredits instruction
-    let amount_usdc = 500_000u64;
-    let buy_instruction_data = [
-        vec![0],                            // Instruction discriminant for BuyCredits
-        amount_usdc.to_le_bytes().to_vec(), // amount_usdc
-        vec![treasury_bump],                // bump
-    ]
-    .concat();
+    let (credits_account_pubkey, credits_account_data) =
+        initialized_credits_account(&mollusk, &program_id, &buyer);
 
     // Create the BuyCredits instruction
-    let buy_instruction = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(buyer, true),
-            AccountMeta::new(buyer_token_account, false),
-            AccountMeta::new(treasury_pda, false),
-            AccountMeta::new(credits_account_pubkey, false),
-            AccountMeta::new(clock_sysvar, false),
-            AccountMeta::new_readonly(token_program, false),
-        ],
-        data: buy_instruction_data,
-    };
+    let amount_usdc = 500_000u64;
+    let buy_instruction = client::buy_credits(
+        &program_id,
+        &buyer,
+        &buyer_token_account,
+        &usdc_mint,
+        &token_program,
+        amount_usdc,
+    );
 
     // Prepare accounts for the BuyCredits instruction
     let mut accounts = vec![
@@ -95,6 +81,7 @@ fn test_withdraw_usdc() {
         (clock_sysvar, clock_sysvar_account.clone()),
         (token_program, token_program_account.clone()),
         (usdc_mint, usdc_mint_account.clone()),
+        (config, config_data.clone()),
     ];
 
     // Process the BuyCredits instruction
@@ -125,6 +112,7 @@ fn test_withdraw_usdc() {
         (clock_sysvar, clock_sysvar_account.clone()),
         (token_program, token_program_account.clone()),
         (usdc_mint, usdc_mint_account.clone()),
+        (config, config_data.clone()),
     ];
 
     // Admin's USDC token account
@@ -135,14 +123,8 @@ fn test_withdraw_usdc() {
         0,          // initial amount
     );
 
-    // Prepare data for the WithdrawUSDC instruction
-    let withdraw_amount = amount_usdc; // Withdraw the amount received from buyer
-    let withdraw_instruction_data = [
-        vec![2],                                // Instruction discriminant for WithdrawUSDC
-        withdraw_amount.to_le_bytes().to_vec(), // amount to withdraw
-        vec![treasury_bump],                    // bump
-    ]
-    .concat();
+    // Withdraw the amount received from buyer
+    let withdraw_amount = amount_usdc;
 
     // After BuyCredits processing - get updated treasury state
     let updated_treasury = buy_result.get_account(&treasury_pda).unwrap();
@@ -164,16 +146,14 @@ fn test_withdraw_usdc() {
     );
 
     // Create the WithdrawUSDC instruction
-    let withdraw_instruction = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(treasury_pda, false), // Treasury account
-            AccountMeta::new(admin, true),         // Admin (signer)
-            AccountMeta::new(admin_usdc, false),   // Admin's USDC account
-            AccountMeta::new_readonly(token_program, false), // Token program
-        ],
-        data: withdraw_instruction_data,
-    };
+    let withdraw_instruction = client::withdraw_usdc(
+        &program_id,
+        &admin,
+        &admin_usdc,
+        &usdc_mint,
+        &token_program,
+        withdraw_amount,
+    );
 
     // Add admin and admin_usdc accounts
     accounts.push((
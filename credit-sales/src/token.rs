@@ -0,0 +1,213 @@
+use {
+    crate::{errors::CreditSalesError, parsing::read_u64},
+    pinocchio::{
+        account_info::AccountInfo,
+        instruction::{AccountMeta, Instruction, Signer},
+        program::invoke_signed,
+        program_error::ProgramError,
+        pubkey::Pubkey,
+        ProgramResult,
+    },
+};
+
+/// Legacy SPL Token program.
+pub const TOKEN_PROGRAM_ID: Pubkey = pinocchio_token::ID;
+
+/// Token-2022 program, whose mints may carry extensions.
+pub const TOKEN_2022_PROGRAM_ID: Pubkey =
+    five8_const::decode_32_const("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Size of a mint and of a token account without extensions. Token-2022 pads
+/// mints with extensions to `ACCOUNT_LEN` and follows both with an account
+/// type byte and the extensions' TLV entries.
+pub const MINT_LEN: usize = 82;
+pub const ACCOUNT_LEN: usize = 165;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+/// Token-2022 mint extensions that don't change how transfers are made
+/// (the transfer fee is accounted for by the caller), and the size of the
+/// extension they require on token accounts, if any.
+const SUPPORTED_MINT_EXTENSIONS: [(u16, Option<usize>); 9] = [
+    (1, Some(8)), // TransferFeeConfig -> TransferFeeAmount
+    (3, None),    // MintCloseAuthority
+    (10, None),   // InterestBearingConfig
+    (18, None),   // MetadataPointer
+    (19, None),   // TokenMetadata
+    (20, None),   // GroupPointer
+    (21, None),   // TokenGroup
+    (22, None),   // GroupMemberPointer
+    (23, None),   // TokenGroupMember
+];
+
+/// Checks that `token_program` is one the program can transfer with.
+#[inline(always)]
+pub fn check_token_program(token_program: &Pubkey) -> ProgramResult {
+    if token_program != &TOKEN_PROGRAM_ID && token_program != &TOKEN_2022_PROGRAM_ID {
+        return Err(CreditSalesError::InvalidTokenProgram.into());
+    }
+    Ok(())
+}
+
+/// Decimals of an initialized `mint` of `token_program`.
+pub fn mint_decimals(mint: &AccountInfo, token_program: &Pubkey) -> Result<u8, ProgramError> {
+    if mint.owner() != token_program {
+        return Err(CreditSalesError::InvalidMint.into());
+    }
+
+    let data = mint.try_borrow_data()?;
+    if data.len() < MINT_LEN || data[45] != 1 {
+        return Err(CreditSalesError::InvalidMint.into());
+    }
+    Ok(data[44])
+}
+
+/// Size of a token account of `mint`, given the mint's data. Rejects
+/// Token-2022 mints with extensions that transfers here don't handle, such
+/// as transfer hooks or a permanent delegate.
+pub fn token_account_len(mint_data: &[u8]) -> Result<usize, ProgramError> {
+    if mint_data.len() == MINT_LEN {
+        return Ok(ACCOUNT_LEN);
+    }
+    if mint_data.len() <= ACCOUNT_LEN || mint_data[ACCOUNT_LEN] != ACCOUNT_TYPE_MINT {
+        return Err(CreditSalesError::InvalidMint.into());
+    }
+
+    let mut extensions_len = 0;
+    let mut tlv = &mint_data[ACCOUNT_LEN + 1..];
+    while let Some((header, rest)) = tlv.split_first_chunk::<4>() {
+        let extension = u16::from_le_bytes([header[0], header[1]]);
+        let value_len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        // Uninitialized space after the last extension
+        if extension == 0 {
+            break;
+        }
+        if rest.len() < value_len {
+            return Err(CreditSalesError::InvalidMint.into());
+        }
+
+        let (_, account_extension) = SUPPORTED_MINT_EXTENSIONS
+            .iter()
+            .find(|(supported, _)| *supported == extension)
+            .ok_or(CreditSalesError::UnsupportedMint)?;
+        if let Some(account_extension_len) = account_extension {
+            extensions_len += 4 + account_extension_len;
+        }
+
+        tlv = &rest[value_len..];
+    }
+
+    // Accounts without extensions have no account type byte either
+    if extensions_len == 0 {
+        return Ok(ACCOUNT_LEN);
+    }
+    Ok(ACCOUNT_LEN + 1 + extensions_len)
+}
+
+/// The fields of a token account shared by both token programs.
+pub struct TokenAccountData {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+impl TokenAccountData {
+    /// Reads an initialized token account of `token_program`.
+    pub fn load(account: &AccountInfo, token_program: &Pubkey) -> Result<Self, ProgramError> {
+        if account.owner() != token_program {
+            return Err(CreditSalesError::InvalidTokenAccount.into());
+        }
+
+        let data = account.try_borrow_data()?;
+        let is_account = data.len() == ACCOUNT_LEN
+            || (data.len() > ACCOUNT_LEN && data[ACCOUNT_LEN] == ACCOUNT_TYPE_ACCOUNT);
+        // State 0 is uninitialized
+        if !is_account || data[108] == 0 {
+            return Err(CreditSalesError::InvalidTokenAccount.into());
+        }
+
+        let mut mint = [0; 32];
+        mint.copy_from_slice(&data[0..32]);
+        let mut owner = [0; 32];
+        owner.copy_from_slice(&data[32..64]);
+
+        Ok(Self {
+            mint,
+            owner,
+            amount: read_u64(&data, 64),
+        })
+    }
+}
+
+/// `TransferChecked` through either token program.
+pub struct TransferChecked<'a> {
+    pub token_program: &'a Pubkey,
+    pub from: &'a AccountInfo,
+    pub mint: &'a AccountInfo,
+    pub to: &'a AccountInfo,
+    pub authority: &'a AccountInfo,
+    pub amount: u64,
+    pub decimals: u8,
+}
+
+impl TransferChecked<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        let accounts = [
+            AccountMeta::writable(self.from.key()),
+            AccountMeta::readonly(self.mint.key()),
+            AccountMeta::writable(self.to.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        let mut data = [0; 10];
+        data[0] = 12;
+        data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+        data[9] = self.decimals;
+
+        invoke_signed(
+            &Instruction {
+                program_id: self.token_program,
+                accounts: &accounts,
+                data: &data,
+            },
+            &[self.from, self.mint, self.to, self.authority],
+            signers,
+        )
+    }
+}
+
+/// `InitializeAccount3` through either token program.
+pub struct InitializeAccount3<'a> {
+    pub token_program: &'a Pubkey,
+    pub account: &'a AccountInfo,
+    pub mint: &'a AccountInfo,
+    pub owner: &'a Pubkey,
+}
+
+impl InitializeAccount3<'_> {
+    pub fn invoke(&self) -> ProgramResult {
+        let accounts = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly(self.mint.key()),
+        ];
+
+        let mut data = [0; 33];
+        data[0] = 18;
+        data[1..33].copy_from_slice(self.owner);
+
+        invoke_signed(
+            &Instruction {
+                program_id: self.token_program,
+                accounts: &accounts,
+                data: &data,
+            },
+            &[self.account, self.mint],
+            &[],
+        )
+    }
+}
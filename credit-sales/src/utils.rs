@@ -0,0 +1,96 @@
+use {
+    crate::{
+        errors::CreditSalesError,
+        state::Config,
+        token::{token_account_len, InitializeAccount3, TokenAccountData},
+    },
+    pinocchio::{
+        account_info::AccountInfo,
+        instruction::{Seed, Signer},
+        pubkey::Pubkey,
+        sysvars::{rent::Rent, Sysvar},
+        ProgramResult,
+    },
+    pinocchio_system::instructions::{Allocate, Assign, CreateAccount, Transfer},
+};
+
+/// Creates a rent-exempt PDA of `space` bytes owned by `owner`, funded by
+/// `payer`.
+pub fn create_pda_account(
+    payer: &AccountInfo,
+    account: &AccountInfo,
+    space: usize,
+    owner: &Pubkey,
+    signer: &[Signer],
+) -> ProgramResult {
+    let rent_exempt = Rent::get()?.minimum_balance(space);
+    let lamports = account.lamports();
+
+    if lamports == 0 {
+        CreateAccount {
+            from: payer,
+            to: account,
+            lamports: rent_exempt,
+            space: space as u64,
+            owner,
+        }
+        .invoke_signed(signer)?;
+    } else {
+        // Anyone can send lamports to the address first, which makes
+        // `CreateAccount` fail; top it up and claim it instead
+        if lamports < rent_exempt {
+            Transfer {
+                from: payer,
+                to: account,
+                lamports: rent_exempt - lamports,
+            }
+            .invoke()?;
+        }
+        Allocate {
+            account,
+            space: space as u64,
+        }
+        .invoke_signed(signer)?;
+        Assign { account, owner }.invoke_signed(signer)?;
+    }
+
+    Ok(())
+}
+
+/// Creates the `["treasury", mint]` token account of `token_program`, which
+/// is its own authority, or checks the existing one. `treasury` must be that
+/// PDA. Fails for mints with extensions transfers here don't support.
+pub fn create_or_check_treasury(
+    payer: &AccountInfo,
+    treasury: &AccountInfo,
+    mint: &AccountInfo,
+    token_program: &Pubkey,
+    treasury_bump: u8,
+) -> ProgramResult {
+    let len = token_account_len(&mint.try_borrow_data()?)?;
+
+    if *treasury.owner() == pinocchio_system::ID {
+        let bump = [treasury_bump];
+        let seeds = [
+            Seed::from(Config::TREASURY_SEED),
+            Seed::from(mint.key()),
+            Seed::from(&bump),
+        ];
+        create_pda_account(payer, treasury, len, token_program, &[Signer::from(&seeds)])?;
+
+        return InitializeAccount3 {
+            token_program,
+            account: treasury,
+            mint,
+            owner: treasury.key(),
+        }
+        .invoke();
+    }
+
+    let token_account = TokenAccountData::load(treasury, token_program)?;
+    if &token_account.mint != mint.key() || &token_account.owner != treasury.key() {
+        return Err(CreditSalesError::InvalidAccountData.into());
+    }
+
+    Ok(())
+}
@@ -1,4 +1,6 @@
--- SQLBook: Code
+-- Baseline schema, matching the former `database/init.sql`. Every statement is
+-- idempotent so databases bootstrapped from that script can adopt migrations.
+
 CREATE TABLE IF NOT EXISTS users (
     id SERIAL PRIMARY KEY,
     pubkey VARCHAR NOT NULL UNIQUE,
@@ -7,7 +9,7 @@ CREATE TABLE IF NOT EXISTS users (
 
 CREATE TABLE IF NOT EXISTS credits (
     id SERIAL PRIMARY KEY,
-    user_id INTEGER NOT NULL REFERENCES users (id) UNIQUE, -- UNIQUE constraint here
+    user_id INTEGER NOT NULL REFERENCES users (id) UNIQUE,
     api_key VARCHAR,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     remaining_requests INTEGER NOT NULL DEFAULT 0
@@ -40,34 +42,12 @@ CREATE TABLE IF NOT EXISTS transactions (
 );
 
 CREATE INDEX IF NOT EXISTS idx_user_id ON chats(user_id);
-
-CREATE INDEX idx_users_pubkey ON users(pubkey);
-CREATE INDEX idx_credits_user_id ON credits(user_id);
-CREATE INDEX idx_chats_user_id_created_at ON chats(user_id, created_at);
-
--- INSERT INTO
---     users (pubkey)
--- VALUES (
---         'GtJHNhKQnnJZQTHq2Vh49HpR4yKKJmUonVYbLeS1RPs8'
---     );
-
--- INSERT INTO
---     credits (user_id, api_key)
--- VALUES (
---         1,
---         'WDAO4Z1Z503DWJH7060GIYGR0TWIIPBM'
---     );
-
--- INSERT INTO
---     chats (user_id, input_user, response, tokens_used)
--- VALUES (
---         1,
---         'Hello',
---         'Hi there!',
---         1
---     );
+CREATE INDEX IF NOT EXISTS idx_users_pubkey ON users(pubkey);
+CREATE INDEX IF NOT EXISTS idx_credits_user_id ON credits(user_id);
+CREATE INDEX IF NOT EXISTS idx_chats_user_id_created_at ON chats(user_id, created_at);
 
 INSERT INTO packages (name, price_usdc, requests_amount, description) VALUES
     ('Starter', 10, 20, 'Perfect for getting started with basic queries'),
     ('Basic', 30, 50, 'Great for regular usage with multiple queries'),
-    ('Pro', 50, 80, 'Professional package for power users');
\ No newline at end of file
+    ('Pro', 50, 80, 'Professional package for power users')
+ON CONFLICT (name) DO NOTHING;
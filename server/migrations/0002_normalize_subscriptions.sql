@@ -0,0 +1,50 @@
+-- Moves the `users.subscriptions` JSONB blob into the `subscriptions` table.
+-- Safe to run more than once: the blob column is dropped at the end and the
+-- conversion is skipped when it no longer exists.
+
+ALTER TABLE packages ADD COLUMN IF NOT EXISTS max_subscriptions INTEGER NOT NULL DEFAULT 3;
+
+UPDATE packages SET max_subscriptions = 10 WHERE name = 'Starter';
+UPDATE packages SET max_subscriptions = 25 WHERE name = 'Basic';
+UPDATE packages SET max_subscriptions = 50 WHERE name = 'Pro';
+
+CREATE TABLE IF NOT EXISTS subscriptions (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    kind VARCHAR NOT NULL CHECK (kind IN ('account_trade', 'token_trade', 'new_token')),
+    target_key VARCHAR NOT NULL DEFAULT '',
+    filters JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (user_id, kind, target_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_subscriptions_kind_target ON subscriptions(kind, target_key);
+
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'users' AND column_name = 'subscriptions'
+    ) THEN
+        -- Keys were stored as the method name without its `subscribe` prefix
+        INSERT INTO subscriptions (user_id, kind, target_key)
+        SELECT
+            u.id,
+            CASE s.key
+                WHEN 'AccountTrade' THEN 'account_trade'
+                WHEN 'TokenTrade' THEN 'token_trade'
+                WHEN 'NewToken' THEN 'new_token'
+            END,
+            CASE WHEN s.key = 'NewToken' THEN '' ELSE t.target_key END
+        FROM users u
+        CROSS JOIN LATERAL jsonb_each(u.subscriptions) s
+        CROSS JOIN LATERAL jsonb_array_elements_text(
+            CASE WHEN jsonb_typeof(s.value) = 'array' THEN s.value ELSE '[]'::jsonb END
+        ) AS t(target_key)
+        WHERE s.key IN ('AccountTrade', 'TokenTrade', 'NewToken')
+        ON CONFLICT (user_id, kind, target_key) DO NOTHING;
+
+        ALTER TABLE users DROP COLUMN subscriptions;
+    END IF;
+END $$;
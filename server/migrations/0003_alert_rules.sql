@@ -0,0 +1,30 @@
+-- Alert rules attached to subscriptions, the alerts they trigger and the
+-- price history used by `price_change` rules.
+
+CREATE TABLE IF NOT EXISTS alert_rules (
+    id SERIAL PRIMARY KEY,
+    subscription_id INTEGER NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+    name VARCHAR,
+    condition JSONB NOT NULL,
+    cooldown_seconds INTEGER NOT NULL DEFAULT 300,
+    last_triggered_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS alerts (
+    id SERIAL PRIMARY KEY,
+    rule_id INTEGER NOT NULL REFERENCES alert_rules (id) ON DELETE CASCADE,
+    dedup_key VARCHAR NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (rule_id, dedup_key)
+);
+
+CREATE TABLE IF NOT EXISTS token_prices (
+    mint VARCHAR NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    observed_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (mint, observed_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_alert_rules_subscription_id ON alert_rules(subscription_id);
@@ -0,0 +1,23 @@
+-- API keys move out of `credits.api_key` into their own table and are only
+-- stored hashed. Existing keys keep working: their SHA-256 digest is copied
+-- before the plaintext column is dropped.
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    key_hash VARCHAR(64) NOT NULL UNIQUE,
+    key_prefix VARCHAR(8) NOT NULL,
+    scopes VARCHAR[] NOT NULL DEFAULT '{query,chat,subscriptions}',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    revoked_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_user_id ON api_keys(user_id);
+
+INSERT INTO api_keys (user_id, key_hash, key_prefix)
+SELECT user_id, encode(sha256(convert_to(api_key, 'UTF8')), 'hex'), LEFT(api_key, 8)
+FROM credits
+WHERE api_key IS NOT NULL AND api_key <> ''
+ON CONFLICT (key_hash) DO NOTHING;
+
+ALTER TABLE credits DROP COLUMN IF EXISTS api_key;
@@ -0,0 +1,7 @@
+-- Users can hold several labelled keys that expire, are revoked, or are
+-- rotated into a replacement that takes over after a grace period.
+
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS label VARCHAR;
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP;
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS last_used_at TIMESTAMP;
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS replaced_by INTEGER REFERENCES api_keys (id) ON DELETE SET NULL;
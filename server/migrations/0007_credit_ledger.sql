@@ -0,0 +1,40 @@
+-- Append-only credit ledger. Every change to `credits.remaining_requests` is
+-- recorded here as a signed delta, so the cached balance always equals the sum
+-- of a user's entries. Work is paid for by a `reserve` entry that is later
+-- settled by exactly one `capture` (kept) or `refund` (returned).
+
+CREATE TABLE IF NOT EXISTS credit_ledger (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    kind VARCHAR NOT NULL
+        CHECK (kind IN ('grant', 'purchase', 'reserve', 'capture', 'refund', 'adjustment')),
+    amount INTEGER NOT NULL,
+    reservation_id BIGINT REFERENCES credit_ledger (id),
+    reference VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_credit_ledger_user_id ON credit_ledger(user_id, created_at);
+-- A reservation is settled at most once
+CREATE UNIQUE INDEX IF NOT EXISTS idx_credit_ledger_reservation_id
+    ON credit_ledger(reservation_id) WHERE reservation_id IS NOT NULL;
+
+-- Concurrent requests could overdraw the old balance; clamp it before the
+-- opening entries are written and forbid negative balances from now on.
+UPDATE credits SET remaining_requests = 0 WHERE remaining_requests < 0;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_constraint WHERE conname = 'credits_remaining_requests_non_negative'
+    ) THEN
+        ALTER TABLE credits ADD CONSTRAINT credits_remaining_requests_non_negative
+            CHECK (remaining_requests >= 0);
+    END IF;
+END $$;
+
+INSERT INTO credit_ledger (user_id, kind, amount, reference)
+SELECT c.user_id, 'grant', c.remaining_requests, 'opening_balance'
+FROM credits c
+WHERE c.remaining_requests <> 0
+  AND NOT EXISTS (SELECT 1 FROM credit_ledger l WHERE l.user_id = c.user_id);
@@ -0,0 +1,10 @@
+-- Chats are priced by intent, items hydrated and agent tokens. Packages can
+-- override the default rules (`PricingRules` in `src/pricing.rs`); NULL keeps
+-- the defaults. Each chat records what it cost and why.
+
+ALTER TABLE packages ADD COLUMN IF NOT EXISTS pricing JSONB;
+
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS intent VARCHAR;
+-- Chats before this migration were charged a flat credit
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS credits_charged INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS cost_breakdown JSONB;
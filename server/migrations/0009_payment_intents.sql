@@ -0,0 +1,23 @@
+-- Asynchronous purchases. A client creates an intent, pays it with a USDC
+-- transfer that includes `reference` as a read-only account (Solana Pay
+-- style), and a background job finds the transfer by reference and grants
+-- the package.
+
+CREATE TABLE IF NOT EXISTS payment_intents (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    package_id INTEGER NOT NULL REFERENCES packages (id),
+    amount_usdc NUMERIC(10,2) NOT NULL,
+    reference VARCHAR(44) NOT NULL UNIQUE,
+    status VARCHAR NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'completed', 'expired', 'failed')),
+    signature VARCHAR UNIQUE,
+    failure_reason TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    completed_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_payment_intents_user_id ON payment_intents(user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_payment_intents_pending
+    ON payment_intents(expires_at) WHERE status = 'pending';
@@ -0,0 +1,14 @@
+-- Last seen state of each credit-sales `CreditsAccount`, and how many server
+-- requests it has granted so far, so the indexer only records the difference.
+
+CREATE TABLE IF NOT EXISTS credit_sales_accounts (
+    address VARCHAR(44) PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    credits_amount NUMERIC(20,0) NOT NULL,
+    credits_amount_refunded NUMERIC(20,0) NOT NULL,
+    purchased_at TIMESTAMP,
+    granted_requests INTEGER NOT NULL DEFAULT 0,
+    synced_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_credit_sales_accounts_user_id ON credit_sales_accounts(user_id);
@@ -0,0 +1,31 @@
+-- Packages managed through the admin API can be limited to an activation
+-- window and sold at a percentage discount. Every admin write is recorded in
+-- `admin_audit_log` alongside the change it describes.
+
+ALTER TABLE packages ADD COLUMN IF NOT EXISTS active_from TIMESTAMP;
+ALTER TABLE packages ADD COLUMN IF NOT EXISTS active_until TIMESTAMP;
+ALTER TABLE packages ADD COLUMN IF NOT EXISTS discount_percent INTEGER NOT NULL DEFAULT 0;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_constraint WHERE conname = 'packages_discount_percent_range'
+    ) THEN
+        ALTER TABLE packages ADD CONSTRAINT packages_discount_percent_range
+            CHECK (discount_percent BETWEEN 0 AND 100);
+    END IF;
+END $$;
+
+CREATE TABLE IF NOT EXISTS admin_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    admin_user_id INTEGER NOT NULL REFERENCES users (id),
+    api_key_id INTEGER REFERENCES api_keys (id),
+    action VARCHAR NOT NULL,
+    target_type VARCHAR NOT NULL,
+    target_id VARCHAR NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_created_at ON admin_audit_log(created_at);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_target ON admin_audit_log(target_type, target_id);
@@ -0,0 +1,43 @@
+-- Promo codes grant either a fixed amount of credits or a percentage bonus on
+-- a package purchase. Referrals link a new user to the user who invited them;
+-- both are rewarded once, after the referee's first paid purchase.
+
+CREATE TABLE IF NOT EXISTS promo_codes (
+    id SERIAL PRIMARY KEY,
+    code VARCHAR(32) NOT NULL UNIQUE,
+    kind VARCHAR NOT NULL CHECK (kind IN ('credits', 'bonus_percent')),
+    value INTEGER NOT NULL CHECK (value > 0),
+    -- Restricts the code to purchases of one package
+    package_id INTEGER REFERENCES packages (id) ON DELETE CASCADE,
+    expires_at TIMESTAMP,
+    max_redemptions INTEGER CHECK (max_redemptions > 0),
+    per_user_limit INTEGER NOT NULL DEFAULT 1 CHECK (per_user_limit > 0),
+    redemptions INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    disabled_at TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS promo_redemptions (
+    id SERIAL PRIMARY KEY,
+    promo_code_id INTEGER NOT NULL REFERENCES promo_codes (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- Purchase the code was redeemed with, if any
+    signature VARCHAR,
+    credits INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_promo_redemptions_code_user
+    ON promo_redemptions(promo_code_id, user_id);
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS referral_code VARCHAR(16) UNIQUE;
+
+CREATE TABLE IF NOT EXISTS referrals (
+    referee_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    referrer_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    rewarded_at TIMESTAMP,
+    CHECK (referee_id <> referrer_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_referrals_referrer_id ON referrals(referrer_id);
@@ -0,0 +1,4 @@
+-- credit-sales `CreditsAccount`s now accumulate purchases and count them;
+-- `purchased_at` is the time of the last one.
+
+ALTER TABLE credit_sales_accounts ADD COLUMN IF NOT EXISTS purchase_count NUMERIC(20,0) NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+-- Credits of each credit-sales `CreditsAccount` settled as consumed on-chain
+-- by `ConsumeCredits`.
+
+ALTER TABLE credit_sales_accounts ADD COLUMN IF NOT EXISTS credits_consumed NUMERIC(20,0) NOT NULL DEFAULT 0;
@@ -0,0 +1,26 @@
+-- Last seen state of each credit-sales `PlanAccount`, and the requests used
+-- out of its quota in each monthly window. A plan starting at `period_start`
+-- has windows of 30 days from there until `period_end`.
+
+CREATE TABLE IF NOT EXISTS credit_sales_plans (
+    address VARCHAR(44) PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    plan_id SMALLINT NOT NULL,
+    period_start TIMESTAMP NOT NULL,
+    period_end TIMESTAMP NOT NULL,
+    monthly_quota NUMERIC(20,0) NOT NULL,
+    auto_renew BOOLEAN NOT NULL DEFAULT FALSE,
+    synced_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_credit_sales_plans_user_id ON credit_sales_plans(user_id, period_end);
+
+CREATE TABLE IF NOT EXISTS plan_usage (
+    plan_address VARCHAR(44) NOT NULL REFERENCES credit_sales_plans (address) ON DELETE CASCADE,
+    window_start TIMESTAMP NOT NULL,
+    requests_used INTEGER NOT NULL DEFAULT 0 CHECK (requests_used >= 0),
+    PRIMARY KEY (plan_address, window_start)
+);
+
+-- Chats paid from a plan's quota rather than the credit balance
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS charged_to_plan BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,337 @@
+use {
+    serde::{Deserialize, Deserializer},
+    solana_sdk::pubkey::Pubkey,
+    std::{env, fmt::Display, str::FromStr},
+    thiserror::Error,
+};
+
+/// The official SWQuery USDC receiving wallet
+const DEFAULT_RECIPIENT_WALLET: Pubkey =
+    solana_sdk::pubkey!("BXVjUeXZ5GgbPvqCsUXdGz2G7zsg436GctEC3HkNLABK");
+const DEFAULT_USDC_MINT: Pubkey =
+    solana_sdk::pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("Failed to parse config file {0}: {1}")]
+    Parse(String, toml::de::Error),
+
+    #[error("Invalid value for {0}: {1}")]
+    InvalidEnv(&'static str, String),
+
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+/// Server configuration, loaded once at startup.
+///
+/// Values come from the defaults below, then the optional TOML file given by
+/// `--config <path>` or `SWQUERY_CONFIG`, then environment variables, which
+/// always win.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+
+    pub database_url: String,
+    pub database_max_connections: u32,
+    pub database_acquire_timeout_secs: u64,
+
+    pub rate_limit_max_requests: u32,
+    pub rate_limit_window_secs: u64,
+
+    /// Timeout applied to outgoing HTTP requests (agent, Twitter, token APIs).
+    pub http_timeout_secs: u64,
+
+    /// Domain shown in the Sign-In With Solana message.
+    pub siws_domain: String,
+    pub auth_nonce_ttl_secs: u64,
+    pub session_ttl_secs: u64,
+
+    pub agent_api_url: String,
+    /// Solana RPC endpoint; derived from the Helius key when left empty.
+    pub solana_rpc_url: String,
+    pub twitter_api_url: String,
+    pub token_list_url: String,
+    pub coingecko_api_url: String,
+
+    pub helius_api_key: Option<String>,
+    pub openai_api_key: Option<String>,
+    pub twitter_bearer_token: Option<String>,
+    pub alerts_ingest_secret: Option<String>,
+
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub recipient_wallet: Pubkey,
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub usdc_mint: Pubkey,
+
+    /// How long a payment intent waits for its transfer before expiring.
+    pub payment_intent_ttl_secs: u64,
+    /// How often pending payment intents are looked up on-chain.
+    pub payment_poll_interval_secs: u64,
+
+    /// `credit-sales` program whose `CreditsAccount`s are indexed; the
+    /// indexer is disabled when unset.
+    #[serde(deserialize_with = "deserialize_optional_pubkey")]
+    pub credit_sales_program_id: Option<Pubkey>,
+    pub credit_sales_poll_interval_secs: u64,
+    /// On-chain credits that make up one server request.
+    pub credit_sales_credits_per_request: u64,
+    /// Subscription limit of users who bought credits or a plan from the
+    /// program, when no package they bought allows more.
+    pub credit_sales_max_subscriptions: i64,
+
+    /// Credits granted to the referrer and to the referee once the referee
+    /// makes a first paid purchase.
+    pub referral_referrer_credits: i32,
+    pub referral_referee_credits: i32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 5500,
+            database_url: String::new(),
+            database_max_connections: 5,
+            database_acquire_timeout_secs: 30,
+            rate_limit_max_requests: 100,
+            rate_limit_window_secs: 60,
+            http_timeout_secs: 30,
+            siws_domain: "swquery.xyz".to_string(),
+            auth_nonce_ttl_secs: 300,
+            session_ttl_secs: 3600,
+            agent_api_url: "http://localhost:8000".to_string(),
+            solana_rpc_url: String::new(),
+            twitter_api_url: "https://api.twitter.com".to_string(),
+            token_list_url: "https://cdn.jsdelivr.net/gh/solana-labs/token-list@main/src/tokens/solana.tokenlist.json".to_string(),
+            coingecko_api_url: "https://api.coingecko.com/api/v3".to_string(),
+            helius_api_key: None,
+            openai_api_key: None,
+            twitter_bearer_token: None,
+            alerts_ingest_secret: None,
+            recipient_wallet: DEFAULT_RECIPIENT_WALLET,
+            usdc_mint: DEFAULT_USDC_MINT,
+            payment_intent_ttl_secs: 900,
+            payment_poll_interval_secs: 10,
+            credit_sales_program_id: None,
+            credit_sales_poll_interval_secs: 30,
+            // The program grants 100,000 credits per USDC base unit: $0.50 a request
+            credit_sales_credits_per_request: 50_000_000_000,
+            credit_sales_max_subscriptions: 10,
+            referral_referrer_credits: 10,
+            referral_referee_credits: 5,
+        }
+    }
+}
+
+fn deserialize_pubkey<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Pubkey::from_str(&value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_pubkey<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Pubkey>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .filter(|value| !value.is_empty())
+        .map(|value| Pubkey::from_str(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn env_override<T>(name: &'static str, target: &mut T) -> Result<(), ConfigError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Ok(value) = env::var(name) {
+        *target = value
+            .parse()
+            .map_err(|e: T::Err| ConfigError::InvalidEnv(name, e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn env_override_optional(name: &'static str, target: &mut Option<String>) {
+    if let Ok(value) = env::var(name) {
+        *target = Some(value).filter(|v| !v.is_empty());
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = env::args()
+            .skip_while(|arg| arg != "--config")
+            .nth(1)
+            .or_else(|| env::var("SWQUERY_CONFIG").ok());
+
+        let mut config = match path {
+            Some(path) => {
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| ConfigError::Read(path.clone(), e))?;
+                toml::from_str(&contents).map_err(|e| ConfigError::Parse(path, e))?
+            }
+            None => Self::default(),
+        };
+
+        config.apply_env()?;
+
+        if config.solana_rpc_url.is_empty() {
+            // TEST_HELIUS_API_KEY is what payment verification used before
+            let helius_key = env::var("TEST_HELIUS_API_KEY")
+                .ok()
+                .or_else(|| config.helius_api_key.clone())
+                .unwrap_or_default();
+            config.solana_rpc_url =
+                format!("https://mainnet.helius-rpc.com/?api-key={}", helius_key);
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        env_override("HOST", &mut self.host)?;
+        env_override("PORT", &mut self.port)?;
+        env_override("DATABASE_URL", &mut self.database_url)?;
+        env_override(
+            "DATABASE_MAX_CONNECTIONS",
+            &mut self.database_max_connections,
+        )?;
+        env_override(
+            "DATABASE_ACQUIRE_TIMEOUT_SECS",
+            &mut self.database_acquire_timeout_secs,
+        )?;
+        env_override("RATE_LIMIT_MAX_REQUESTS", &mut self.rate_limit_max_requests)?;
+        env_override("RATE_LIMIT_WINDOW_SECS", &mut self.rate_limit_window_secs)?;
+        env_override("HTTP_TIMEOUT_SECS", &mut self.http_timeout_secs)?;
+        env_override("SIWS_DOMAIN", &mut self.siws_domain)?;
+        env_override("AUTH_NONCE_TTL_SECS", &mut self.auth_nonce_ttl_secs)?;
+        env_override("SESSION_TTL_SECS", &mut self.session_ttl_secs)?;
+        env_override("AGENT_API_URL", &mut self.agent_api_url)?;
+        env_override("SOLANA_RPC_URL", &mut self.solana_rpc_url)?;
+        env_override("TWITTER_API_URL", &mut self.twitter_api_url)?;
+        env_override("TOKEN_LIST_URL", &mut self.token_list_url)?;
+        env_override("COINGECKO_API_URL", &mut self.coingecko_api_url)?;
+        env_override("RECIPIENT_WALLET", &mut self.recipient_wallet)?;
+        env_override("USDC_MINT", &mut self.usdc_mint)?;
+        env_override("PAYMENT_INTENT_TTL_SECS", &mut self.payment_intent_ttl_secs)?;
+        env_override(
+            "PAYMENT_POLL_INTERVAL_SECS",
+            &mut self.payment_poll_interval_secs,
+        )?;
+        env_override(
+            "CREDIT_SALES_POLL_INTERVAL_SECS",
+            &mut self.credit_sales_poll_interval_secs,
+        )?;
+        env_override(
+            "CREDIT_SALES_CREDITS_PER_REQUEST",
+            &mut self.credit_sales_credits_per_request,
+        )?;
+        env_override(
+            "CREDIT_SALES_MAX_SUBSCRIPTIONS",
+            &mut self.credit_sales_max_subscriptions,
+        )?;
+        env_override(
+            "REFERRAL_REFERRER_CREDITS",
+            &mut self.referral_referrer_credits,
+        )?;
+        env_override(
+            "REFERRAL_REFEREE_CREDITS",
+            &mut self.referral_referee_credits,
+        )?;
+        if let Ok(value) = env::var("CREDIT_SALES_PROGRAM_ID") {
+            self.credit_sales_program_id = Some(value)
+                .filter(|v| !v.is_empty())
+                .map(|v| Pubkey::from_str(&v))
+                .transpose()
+                .map_err(|e| ConfigError::InvalidEnv("CREDIT_SALES_PROGRAM_ID", e.to_string()))?;
+        }
+
+        env_override_optional("HELIUS_API_KEY", &mut self.helius_api_key);
+        env_override_optional("OPENAI_API_KEY", &mut self.openai_api_key);
+        env_override_optional("TWITTER_BEARER_TOKEN", &mut self.twitter_bearer_token);
+        env_override_optional("ALERTS_INGEST_SECRET", &mut self.alerts_ingest_secret);
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.database_url.is_empty() {
+            return Err(ConfigError::Invalid("DATABASE_URL must be set".to_string()));
+        }
+        if self.port == 0 {
+            return Err(ConfigError::Invalid("port must be non-zero".to_string()));
+        }
+        if self.database_max_connections == 0 {
+            return Err(ConfigError::Invalid(
+                "database_max_connections must be at least 1".to_string(),
+            ));
+        }
+        if self.rate_limit_max_requests == 0 || self.rate_limit_window_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "rate limit requests and window must be non-zero".to_string(),
+            ));
+        }
+
+        if self.http_timeout_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "http_timeout_secs must be non-zero".to_string(),
+            ));
+        }
+
+        if self.siws_domain.is_empty() {
+            return Err(ConfigError::Invalid("siws_domain must be set".to_string()));
+        }
+        if self.auth_nonce_ttl_secs == 0 || self.session_ttl_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "auth nonce and session TTLs must be non-zero".to_string(),
+            ));
+        }
+
+        if self.payment_intent_ttl_secs == 0 || self.payment_poll_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "payment intent TTL and poll interval must be non-zero".to_string(),
+            ));
+        }
+
+        if self.credit_sales_poll_interval_secs == 0 || self.credit_sales_credits_per_request == 0 {
+            return Err(ConfigError::Invalid(
+                "credit-sales poll interval and credits per request must be non-zero".to_string(),
+            ));
+        }
+
+        if self.credit_sales_max_subscriptions < 0 {
+            return Err(ConfigError::Invalid(
+                "credit_sales_max_subscriptions must not be negative".to_string(),
+            ));
+        }
+
+        if self.referral_referrer_credits < 0 || self.referral_referee_credits < 0 {
+            return Err(ConfigError::Invalid(
+                "referral credits must not be negative".to_string(),
+            ));
+        }
+
+        for (name, url) in [
+            ("agent_api_url", &self.agent_api_url),
+            ("solana_rpc_url", &self.solana_rpc_url),
+            ("twitter_api_url", &self.twitter_api_url),
+            ("token_list_url", &self.token_list_url),
+            ("coingecko_api_url", &self.coingecko_api_url),
+        ] {
+            reqwest::Url::parse(url)
+                .map_err(|e| ConfigError::Invalid(format!("{} is not a valid URL: {}", name, e)))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn listen_addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
@@ -1,22 +1,24 @@
 use {
-    sqlx::{postgres::PgPoolOptions, Pool, Postgres},
-    std::env,
+    crate::config::Config,
+    sqlx::{migrate::Migrator, postgres::PgPoolOptions, Pool, Postgres},
     std::time::Duration,
     tokio::time::sleep,
 };
 
 pub type DbPool = Pool<Postgres>;
 
-pub async fn connect() -> DbPool {
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+/// Versioned migrations from `server/migrations`, embedded at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub async fn connect(config: &Config) -> DbPool {
     let mut attempts = 0;
     let max_attempts = 10;
 
     loop {
         match PgPoolOptions::new()
-            .max_connections(5)
-            .acquire_timeout(Duration::from_secs(30))
-            .connect(&database_url)
+            .max_connections(config.database_max_connections)
+            .acquire_timeout(Duration::from_secs(config.database_acquire_timeout_secs))
+            .connect(&config.database_url)
             .await
         {
             Ok(pool) => return pool,
@@ -37,3 +39,10 @@ pub async fn connect() -> DbPool {
         }
     }
 }
+
+pub async fn run_migrations(pool: &DbPool) {
+    MIGRATOR
+        .run(pool)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to run database migrations: {}", e));
+}
@@ -0,0 +1,303 @@
+use {
+    crate::{
+        config::Config,
+        ledger::{self, EntryKind},
+        promotions,
+    },
+    chrono::{DateTime, NaiveDateTime},
+    credit_sales_client::{CreditsAccount, PlanAccount},
+    rust_decimal::Decimal,
+    solana_account_decoder::UiAccountEncoding,
+    solana_client::{
+        nonblocking::rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::RpcFilterType,
+    },
+    solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey},
+    sqlx::PgPool,
+    std::{sync::Arc, time::Duration},
+    thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum IndexerError {
+    #[error("Failed to fetch program accounts: {0}")]
+    Rpc(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Server requests the account is worth once refunds are taken out.
+/// Consumed credits were already taken from the balance when they were
+/// spent, so they don't change what the account is worth.
+pub fn net_requests(credits: &CreditsAccount, credits_per_request: u64) -> i32 {
+    let net = credits
+        .credits_amount
+        .saturating_sub(credits.credits_amount_refunded);
+    (net / credits_per_request).min(i32::MAX as u64) as i32
+}
+
+fn purchased_at(credits: &CreditsAccount) -> Option<NaiveDateTime> {
+    (credits.timestamp > 0)
+        .then(|| DateTime::from_timestamp(credits.timestamp, 0))
+        .flatten()
+        .map(|time| time.naive_utc())
+}
+
+/// Periodically syncs every `CreditsAccount` of the configured program into
+/// the user's balance, and every `PlanAccount` into `credit_sales_plans`.
+/// Does nothing when no program is configured.
+pub fn spawn_indexer(pool: PgPool, config: Arc<Config>, rpc_client: Arc<RpcClient>) {
+    let Some(program_id) = config.credit_sales_program_id else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(config.credit_sales_poll_interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = sync_accounts(&pool, &config, &rpc_client, &program_id).await {
+                eprintln!("Failed to index credit-sales accounts: {}", e);
+            }
+            if let Err(e) = sync_plans(&pool, &config, &rpc_client, &program_id).await {
+                eprintln!("Failed to index credit-sales plans: {}", e);
+            }
+        }
+    });
+}
+
+/// Finalized accounts of the program with `len` bytes of data, which tells
+/// its account types apart.
+async fn fetch_accounts(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    len: usize,
+) -> Result<Vec<(Pubkey, Account)>, IndexerError> {
+    rpc_client
+        .get_program_accounts_with_config(
+            program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::DataSize(len as u64)]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(CommitmentConfig::finalized()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|e| IndexerError::Rpc(e.to_string()))
+}
+
+async fn sync_accounts(
+    pool: &PgPool,
+    config: &Config,
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+) -> Result<(), IndexerError> {
+    let accounts = fetch_accounts(rpc_client, program_id, CreditsAccount::LEN).await?;
+
+    for (address, account) in accounts {
+        let Some(credits) = CreditsAccount::decode(&account.data) else {
+            eprintln!("Skipping undecodable credits account {}", address);
+            continue;
+        };
+
+        if let Err(e) = sync_account(pool, config, &address, &credits).await {
+            eprintln!("Failed to sync credits account {}: {}", address, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Grants (or, after an on-chain refund, takes back) the difference between
+/// what the account is worth and what it has granted so far. A purchase
+/// counts toward the user's referral like any other paid purchase.
+async fn sync_account(
+    pool: &PgPool,
+    config: &Config,
+    address: &Pubkey,
+    credits: &CreditsAccount,
+) -> Result<(), IndexerError> {
+    let address = address.to_string();
+    let net = net_requests(credits, config.credit_sales_credits_per_request);
+
+    let mut tx = pool.begin().await?;
+
+    let user_id = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO users (pubkey) VALUES ($1)
+         ON CONFLICT (pubkey) DO UPDATE SET pubkey = EXCLUDED.pubkey
+         RETURNING id",
+    )
+    .bind(credits.owner.to_string())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO credit_sales_accounts
+            (address, user_id, credits_amount, credits_amount_refunded)
+         VALUES ($1, $2, 0, 0)
+         ON CONFLICT (address) DO NOTHING",
+    )
+    .bind(&address)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let granted = sqlx::query_scalar::<_, i32>(
+        "SELECT granted_requests FROM credit_sales_accounts WHERE address = $1 FOR UPDATE",
+    )
+    .bind(&address)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let delta = net - granted;
+    if delta > 0 {
+        ledger::record(
+            &mut tx,
+            user_id,
+            EntryKind::Purchase,
+            delta,
+            Some(&format!("credit_sales:{}", address)),
+        )
+        .await?;
+    } else if delta < 0 {
+        // Refunded credits may already be spent; take back what is left
+        let balance = sqlx::query_scalar::<_, i32>(
+            "SELECT remaining_requests FROM credits WHERE user_id = $1 FOR UPDATE",
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .unwrap_or(0);
+
+        let debit = (-delta).min(balance);
+        if debit > 0 {
+            ledger::record(
+                &mut tx,
+                user_id,
+                EntryKind::Adjustment,
+                -debit,
+                Some(&format!("credit_sales_refund:{}", address)),
+            )
+            .await?;
+        }
+        if debit < -delta {
+            eprintln!(
+                "User {} had already spent {} refunded requests of {}",
+                user_id,
+                -delta - debit,
+                address
+            );
+        }
+    }
+
+    sqlx::query(
+        "UPDATE credit_sales_accounts
+         SET user_id = $1, credits_amount = $2, credits_amount_refunded = $3,
+             purchased_at = $4, purchase_count = $5, credits_consumed = $6,
+             granted_requests = $7, synced_at = CURRENT_TIMESTAMP
+         WHERE address = $8",
+    )
+    .bind(user_id)
+    .bind(Decimal::from(credits.credits_amount))
+    .bind(Decimal::from(credits.credits_amount_refunded))
+    .bind(purchased_at(credits))
+    .bind(Decimal::from(credits.purchase_count))
+    .bind(Decimal::from(credits.credits_consumed))
+    .bind(net)
+    .bind(&address)
+    .execute(&mut *tx)
+    .await?;
+
+    if delta > 0 {
+        promotions::reward_referral(&mut tx, config, user_id).await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn sync_plans(
+    pool: &PgPool,
+    config: &Config,
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+) -> Result<(), IndexerError> {
+    let accounts = fetch_accounts(rpc_client, program_id, PlanAccount::LEN).await?;
+
+    for (address, account) in accounts {
+        let Some(plan) = PlanAccount::decode(&account.data) else {
+            eprintln!("Skipping undecodable plan account {}", address);
+            continue;
+        };
+
+        if let Err(e) = sync_plan(pool, config, &address, &plan).await {
+            eprintln!("Failed to sync plan account {}: {}", address, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Mirrors the plan's current state. Its usage is tracked by `plans`, per
+/// monthly window, and survives renewals. A newly seen plan counts toward
+/// the user's referral like any other paid purchase.
+async fn sync_plan(
+    pool: &PgPool,
+    config: &Config,
+    address: &Pubkey,
+    plan: &PlanAccount,
+) -> Result<(), IndexerError> {
+    let (Some(period_start), Some(period_end)) = (
+        DateTime::from_timestamp(plan.period_start, 0),
+        DateTime::from_timestamp(plan.period_end, 0),
+    ) else {
+        eprintln!("Skipping plan account {} with invalid period", address);
+        return Ok(());
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let user_id = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO users (pubkey) VALUES ($1)
+         ON CONFLICT (pubkey) DO UPDATE SET pubkey = EXCLUDED.pubkey
+         RETURNING id",
+    )
+    .bind(plan.owner.to_string())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // xmax is 0 only for rows this statement inserted
+    let inserted = sqlx::query_scalar::<_, bool>(
+        "INSERT INTO credit_sales_plans
+            (address, user_id, plan_id, period_start, period_end, monthly_quota, auto_renew)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (address) DO UPDATE
+         SET user_id = EXCLUDED.user_id, plan_id = EXCLUDED.plan_id,
+             period_start = EXCLUDED.period_start, period_end = EXCLUDED.period_end,
+             monthly_quota = EXCLUDED.monthly_quota, auto_renew = EXCLUDED.auto_renew,
+             synced_at = CURRENT_TIMESTAMP
+         RETURNING xmax = 0",
+    )
+    .bind(address.to_string())
+    .bind(user_id)
+    .bind(plan.plan_id as i16)
+    .bind(period_start.naive_utc())
+    .bind(period_end.naive_utc())
+    .bind(Decimal::from(plan.monthly_quota))
+    .bind(plan.auto_renew)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if inserted {
+        promotions::reward_referral(&mut tx, config, user_id).await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
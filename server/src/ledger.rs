@@ -0,0 +1,297 @@
+use {
+    axum::http::StatusCode,
+    sqlx::{PgConnection, PgPool},
+    std::time::Duration,
+};
+
+/// Reservations older than this are assumed abandoned (e.g. the server
+/// restarted mid-request) and are refunded by the reconciler.
+const STALE_RESERVATION: Duration = Duration::from_secs(10 * 60);
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Kinds of `credit_ledger` entries. Amounts are signed balance deltas:
+/// grants, purchases and refunds add, reservations subtract, and captures
+/// settle a reservation, returning the part that wasn't spent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    Grant,
+    Purchase,
+    Reserve,
+    Capture,
+    Refund,
+    Adjustment,
+}
+
+impl EntryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Grant => "grant",
+            Self::Purchase => "purchase",
+            Self::Reserve => "reserve",
+            Self::Capture => "capture",
+            Self::Refund => "refund",
+            Self::Adjustment => "adjustment",
+        }
+    }
+}
+
+/// Credits held for a unit of work until it is captured or refunded.
+#[derive(Debug)]
+pub struct Reservation {
+    pub id: i64,
+    pub user_id: i32,
+    pub amount: i32,
+    /// Balance left once the reservation was taken
+    pub balance: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct BalanceDrift {
+    pub user_id: i32,
+    pub balance: i64,
+    pub ledger_balance: i64,
+}
+
+/// Applies a signed delta to the user's balance and records it, returning the
+/// new balance. Run it in the same transaction as the writes it accounts for.
+pub async fn record(
+    conn: &mut PgConnection,
+    user_id: i32,
+    kind: EntryKind,
+    amount: i32,
+    reference: Option<&str>,
+) -> Result<i64, sqlx::Error> {
+    let balance = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO credits (user_id, remaining_requests)
+         VALUES ($1, $2)
+         ON CONFLICT (user_id)
+         DO UPDATE SET remaining_requests = credits.remaining_requests + EXCLUDED.remaining_requests
+         RETURNING remaining_requests",
+    )
+    .bind(user_id)
+    .bind(amount)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO credit_ledger (user_id, kind, amount, reference) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(user_id)
+    .bind(kind.as_str())
+    .bind(amount)
+    .bind(reference)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(balance as i64)
+}
+
+/// Holds as much of `max` as the balance allows, but at least `min`, before
+/// running paid work. The balance row is locked so concurrent requests can't
+/// spend the same credits. Callers pass the least the work can cost as `min`
+/// and the most as `max`; a balance in between gets a partial hold, and the
+/// capture then charges at most what was held.
+pub async fn reserve_up_to(
+    pool: &PgPool,
+    user_id: i32,
+    min: i32,
+    max: i32,
+    reference: &str,
+) -> Result<Reservation, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let balance = sqlx::query_scalar::<_, i32>(
+        "SELECT remaining_requests FROM credits WHERE user_id = $1 FOR UPDATE",
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::PAYMENT_REQUIRED, "No credits found".to_string()))?;
+
+    if balance < min {
+        return Err((
+            StatusCode::PAYMENT_REQUIRED,
+            "Insufficient credits".to_string(),
+        ));
+    }
+    let amount = balance.min(max);
+
+    sqlx::query(
+        "UPDATE credits SET remaining_requests = remaining_requests - $1 WHERE user_id = $2",
+    )
+    .bind(amount)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO credit_ledger (user_id, kind, amount, reference)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id",
+    )
+    .bind(user_id)
+    .bind(EntryKind::Reserve.as_str())
+    .bind(-amount)
+    .bind(reference)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Reservation {
+        id,
+        user_id,
+        amount,
+        balance: (balance - amount) as i64,
+    })
+}
+
+/// Spends `charged` of the reserved credits once the work succeeded and
+/// returns the rest. Returns the amount actually charged, which never
+/// exceeds the reservation.
+pub async fn capture(
+    pool: &PgPool,
+    reservation: &Reservation,
+    charged: i32,
+) -> Result<i32, (StatusCode, String)> {
+    let charged = charged.clamp(0, reservation.amount);
+    settle(
+        pool,
+        reservation,
+        EntryKind::Capture,
+        reservation.amount - charged,
+    )
+    .await?;
+    Ok(charged)
+}
+
+/// Returns the reserved credits after the work failed.
+pub async fn refund(pool: &PgPool, reservation: &Reservation) -> Result<(), (StatusCode, String)> {
+    settle(pool, reservation, EntryKind::Refund, reservation.amount).await
+}
+
+/// Settles a reservation exactly once, crediting `amount` back; if the
+/// reconciler already refunded it, this is a no-op.
+async fn settle(
+    pool: &PgPool,
+    reservation: &Reservation,
+    kind: EntryKind,
+    amount: i32,
+) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let settled = sqlx::query(
+        "INSERT INTO credit_ledger (user_id, kind, amount, reservation_id)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (reservation_id) WHERE reservation_id IS NOT NULL DO NOTHING",
+    )
+    .bind(reservation.user_id)
+    .bind(kind.as_str())
+    .bind(amount)
+    .bind(reservation.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .rows_affected()
+        == 1;
+
+    if settled && amount != 0 {
+        sqlx::query(
+            "UPDATE credits SET remaining_requests = remaining_requests + $1 WHERE user_id = $2",
+        )
+        .bind(amount)
+        .bind(reservation.user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Refunds reservations that were never settled. Returns how many balances
+/// were credited back.
+pub async fn release_stale_reservations(
+    pool: &PgPool,
+    older_than: Duration,
+) -> Result<u64, sqlx::Error> {
+    sqlx::query(
+        "WITH stale AS (
+            SELECT r.id, r.user_id, -r.amount AS amount
+            FROM credit_ledger r
+            WHERE r.kind = 'reserve'
+              AND r.created_at < CURRENT_TIMESTAMP - $1 * INTERVAL '1 second'
+              AND NOT EXISTS (SELECT 1 FROM credit_ledger s WHERE s.reservation_id = r.id)
+        ), refunded AS (
+            INSERT INTO credit_ledger (user_id, kind, amount, reservation_id, reference)
+            SELECT user_id, 'refund', amount, id, 'stale_reservation' FROM stale
+            ON CONFLICT (reservation_id) WHERE reservation_id IS NOT NULL DO NOTHING
+            RETURNING user_id, amount
+        )
+        UPDATE credits c
+        SET remaining_requests = c.remaining_requests + r.total
+        FROM (SELECT user_id, SUM(amount) AS total FROM refunded GROUP BY user_id) r
+        WHERE c.user_id = r.user_id",
+    )
+    .bind(older_than.as_secs() as i64)
+    .execute(pool)
+    .await
+    .map(|result| result.rows_affected())
+}
+
+/// Users whose cached balance no longer matches the sum of their entries.
+pub async fn balance_drift(pool: &PgPool) -> Result<Vec<BalanceDrift>, sqlx::Error> {
+    sqlx::query_as::<_, BalanceDrift>(
+        "SELECT c.user_id,
+                c.remaining_requests::bigint AS balance,
+                COALESCE(SUM(l.amount), 0)::bigint AS ledger_balance
+         FROM credits c
+         LEFT JOIN credit_ledger l ON l.user_id = c.user_id
+         GROUP BY c.user_id, c.remaining_requests
+         HAVING c.remaining_requests <> COALESCE(SUM(l.amount), 0)",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Periodically refunds abandoned reservations and reports balances that
+/// drifted from the ledger. Drift is only logged, never corrected silently.
+pub fn spawn_reconciler(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            match release_stale_reservations(&pool, STALE_RESERVATION).await {
+                Ok(0) => {}
+                Ok(users) => println!("Refunded stale credit reservations of {} users", users),
+                Err(e) => eprintln!("Failed to release stale reservations: {}", e),
+            }
+
+            match balance_drift(&pool).await {
+                Ok(drifts) => {
+                    for drift in drifts {
+                        eprintln!(
+                            "Credit balance of user {} is {} but the ledger sums to {}",
+                            drift.user_id, drift.balance, drift.ledger_balance
+                        );
+                    }
+                }
+                Err(e) => eprintln!("Failed to reconcile credit balances: {}", e),
+            }
+        }
+    });
+}
@@ -1,61 +1,176 @@
+mod config;
 mod db;
+mod indexer;
+mod ledger;
 mod middlewares;
 mod models;
+mod payments;
+mod plans;
+mod pricing;
+mod promotions;
 mod routes;
+mod state;
 mod utils;
 
 use {
     axum::{
         http::Method,
         middleware::from_fn_with_state,
-        routing::{get, post},
+        routing::{delete, get, patch, post, put},
         Router,
     },
-    db::connect,
+    config::Config,
+    db::{connect, run_migrations},
     dotenvy::dotenv,
+    middlewares::auth::{require_auth, require_scope, require_session, Scope},
     routes::{
+        admin::{
+            adjust_credits, create_package, create_promo_code, delete_package, disable_promo_code,
+            get_audit_log, list_packages, list_promo_codes, replace_package, search_users,
+        },
         agent::{generate_query, generate_report},
+        alerts::{
+            create_alert_rule, delete_alert_rule, get_alert_rules, get_alerts, ingest_alert_event,
+        },
+        api_keys::{
+            create_user_api_key, get_api_keys, revoke_api_key, rotate_api_key, update_api_key,
+        },
+        auth::{logout, request_nonce, verify_signature},
         chatbot::{chatbot_interact, get_chat_by_id, get_chats_for_user},
         credits::{buy_credits, refund_credits},
         packages::{get_packages, get_user_usage, verify_transaction},
-        users::{create_user, get_usage, get_user_by_pubkey, get_users, manage_subscription},
-        token::{get_token_info, analyze_rug_pull_risk},
-        social::{get_user_by_username, get_followers_by_username, get_following_by_username, get_blocked_by_username, search_posts, user_mention_timeline, get_trends_by_woeid},
+        payment_intents::{create_payment_intent, get_payment_intent, get_payment_intents},
+        promotions::{apply_referral, get_referral, redeem_promo_code},
+        social::{
+            get_blocked_by_username, get_followers_by_username, get_following_by_username,
+            get_trends_by_woeid, get_user_by_username, search_posts, user_mention_timeline,
+        },
+        subscriptions::{
+            delete_subscription, get_subscriptions, manage_subscription, update_subscription,
+        },
+        token::{analyze_rug_pull_risk, get_token_info},
+        users::{create_user, get_plan, get_usage, get_user_by_pubkey},
     },
-    std::time::Duration,
+    state::AppState,
+    std::sync::Arc,
     tower_http::cors::{Any, CorsLayer},
 };
 
-// pub const AGENT_API_URL: &str = "http://agent:8000";
-pub const AGENT_API_URL: &str = "http://localhost:8000";
-
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     tracing_subscriber::fmt::init();
 
-    let pool = connect().await;
+    let config =
+        Arc::new(Config::load().unwrap_or_else(|e| panic!("Failed to load configuration: {}", e)));
+
+    let pool = connect(&config).await;
+    run_migrations(&pool).await;
+
+    if std::env::args().any(|arg| arg == "--migrate-only") {
+        println!("Database migrations applied");
+        return;
+    }
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
         .allow_headers(Any);
 
-    let rate_limiter = middlewares::rate_limiter::RateLimiter::new(100, Duration::from_secs(60));
+    let state = AppState::new(pool, config.clone());
+    ledger::spawn_reconciler(state.pool.clone());
+    payments::spawn_intent_watcher(state.pool.clone(), state.config.clone(), state.rpc.clone());
+    indexer::spawn_indexer(state.pool.clone(), state.config.clone(), state.rpc.clone());
+
+    // Authenticates a session or `x-api-key` and requires the given scope on a router
+    let require = |scope: Scope| from_fn_with_state((state.pool.clone(), scope), require_scope);
 
     let agent_router = Router::new()
         .route("/generate-query", post(generate_query))
-        .route("/generate-report", post(generate_report));
+        .route("/generate-report", post(generate_report))
+        .route_layer(require(Scope::Query));
     let chatbot_router = Router::new()
         .route("/interact", post(chatbot_interact))
         .route("/chats", get(get_chats_for_user))
-        .route("/chats/:id", get(get_chat_by_id));
+        .route("/chats/:id", get(get_chat_by_id))
+        .route_layer(require(Scope::Chat));
+    let subscriptions_router = Router::new()
+        .route(
+            "/:pubkey/subscriptions",
+            get(get_subscriptions).post(manage_subscription),
+        )
+        .route(
+            "/:pubkey/subscriptions/:id",
+            patch(update_subscription).delete(delete_subscription),
+        )
+        .route(
+            "/:pubkey/subscriptions/:id/alerts",
+            get(get_alert_rules).post(create_alert_rule),
+        )
+        .route(
+            "/:pubkey/subscriptions/:id/alerts/:rule_id",
+            delete(delete_alert_rule),
+        )
+        .route("/:pubkey/alerts", get(get_alerts))
+        .route_layer(require(Scope::Subscriptions));
+    let api_keys_router = Router::new()
+        .route(
+            "/:pubkey/api-keys",
+            get(get_api_keys).post(create_user_api_key),
+        )
+        .route(
+            "/:pubkey/api-keys/:id",
+            patch(update_api_key).delete(revoke_api_key),
+        )
+        .route("/:pubkey/api-keys/:id/rotate", post(rotate_api_key))
+        .route("/:pubkey/usage", get(get_usage))
+        .route("/:pubkey/plan", get(get_plan))
+        .route(
+            "/:pubkey/payment-intents",
+            get(get_payment_intents).post(create_payment_intent),
+        )
+        .route("/:pubkey/payment-intents/:id", get(get_payment_intent))
+        .route("/:pubkey/promo-codes/redeem", post(redeem_promo_code))
+        .route("/:pubkey/referral", get(get_referral).post(apply_referral))
+        .route_layer(from_fn_with_state(state.pool.clone(), require_session));
+    let auth_router = Router::new()
+        .route("/nonce", post(request_nonce))
+        .route("/verify", post(verify_signature))
+        .merge(
+            Router::new()
+                .route("/logout", post(logout))
+                .route_layer(from_fn_with_state(state.pool.clone(), require_auth)),
+        );
     let users_router = Router::new()
-        .route("/", get(get_users).post(create_user))
         .route("/:pubkey", get(get_user_by_pubkey))
-        .route("/:pubkey/subscriptions", post(manage_subscription))
-        .route("/usage", post(get_user_usage))
-        .route("/:pubkey/usage", get(get_usage));
+        .merge(
+            Router::new()
+                .route("/", post(create_user))
+                .route_layer(from_fn_with_state(state.pool.clone(), require_session)),
+        )
+        .merge(
+            Router::new()
+                .route("/usage", post(get_user_usage))
+                .route_layer(require(Scope::Query)),
+        )
+        .merge(subscriptions_router)
+        .merge(api_keys_router);
+    let admin_router = Router::new()
+        .route("/packages", get(list_packages).post(create_package))
+        .route("/packages/:id", put(replace_package).delete(delete_package))
+        .route("/users", get(search_users))
+        .route("/users/:pubkey/credits", post(adjust_credits))
+        .route("/promo-codes", get(list_promo_codes).post(create_promo_code))
+        .route("/promo-codes/:id", delete(disable_promo_code))
+        .route("/audit-log", get(get_audit_log))
+        .route_layer(require(Scope::Admin));
     let token_router = Router::new()
         .route("/token_info/:name", get(get_token_info))
         .route("/analyze_rug_pull_risk", post(analyze_rug_pull_risk));
@@ -74,20 +189,25 @@ async fn main() {
         // .route("/credits/refund", post(refund_credits))
         .route("/packages/:pubkey", get(get_packages))
         .route("/packages/verify", post(verify_transaction))
+        .route("/alerts/ingest", post(ingest_alert_event))
+        .nest("/admin", admin_router)
+        .nest("/auth", auth_router)
         .nest("/agent", agent_router)
         .nest("/chatbot", chatbot_router)
         .nest("/users", users_router)
         .nest("/token", token_router)
         .nest("/social", social_router)
         .route("/:api_key/helius", get(|| async { "ok" }))
-        .with_state(pool)
+        .with_state(state.clone())
         .layer(cors)
         .layer(from_fn_with_state(
-            rate_limiter.clone(),
+            state,
             middlewares::rate_limiter::rate_limit_middleware,
         ));
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:5500").await.unwrap();
+    let listener = tokio::net::TcpListener::bind(config.listen_addr())
+        .await
+        .unwrap();
 
     println!("Listening on: http://{}", listener.local_addr().unwrap());
 
@@ -0,0 +1,246 @@
+use {
+    axum::{
+        async_trait,
+        body::Body,
+        extract::{FromRequestParts, State},
+        http::{header::AUTHORIZATION, request::Parts, HeaderMap, Request, StatusCode},
+        middleware::Next,
+        response::Response,
+    },
+    sha2::{Digest, Sha256},
+    sqlx::PgPool,
+    std::str::FromStr,
+};
+
+/// Permissions an API key can carry. `admin` implies every other scope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Query,
+    Chat,
+    Subscriptions,
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Query => "query",
+            Self::Chat => "chat",
+            Self::Subscriptions => "subscriptions",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "query" => Ok(Self::Query),
+            "chat" => Ok(Self::Chat),
+            "subscriptions" => Ok(Self::Subscriptions),
+            "admin" => Ok(Self::Admin),
+            _ => Err(format!("Unknown scope: {}", s)),
+        }
+    }
+}
+
+/// Scopes granted to keys issued to regular users.
+pub const DEFAULT_SCOPES: [Scope; 3] = [Scope::Query, Scope::Chat, Scope::Subscriptions];
+
+/// Identity of the caller, attached to the request by the layers below.
+/// Exactly one of `key_id` and `session_id` is set.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct AuthUser {
+    pub key_id: Option<i32>,
+    #[sqlx(default)]
+    pub session_id: Option<i32>,
+    pub user_id: i32,
+    pub pubkey: String,
+    pub scopes: Vec<String>,
+}
+
+impl AuthUser {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes
+            .iter()
+            .any(|s| s == scope.as_str() || s == Scope::Admin.as_str())
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.has_scope(Scope::Admin)
+    }
+
+    /// Ensures the caller acts on its own account, unless it is an admin.
+    pub fn require_owner(&self, pubkey: &str) -> Result<(), (StatusCode, String)> {
+        if self.pubkey == pubkey || self.is_admin() {
+            Ok(())
+        } else {
+            Err((
+                StatusCode::FORBIDDEN,
+                "API key does not belong to this user".to_string(),
+            ))
+        }
+    }
+}
+
+/// API keys and session tokens are only stored as their SHA-256 hex digest.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Compares a provided secret with the expected one in constant time. Both
+/// are hashed first so their lengths don't leak either.
+pub fn secrets_match(provided: &str, expected: &str) -> bool {
+    let provided = Sha256::digest(provided.as_bytes());
+    let expected = Sha256::digest(expected.as_bytes());
+    provided
+        .iter()
+        .zip(expected.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+pub fn api_key_from_headers(headers: &HeaderMap) -> Result<&str, (StatusCode, String)> {
+    headers
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .ok_or((StatusCode::UNAUTHORIZED, "Missing API key".to_string()))
+}
+
+/// Resolves an active key and records its use in the same statement.
+pub async fn authenticate(pool: &PgPool, api_key: &str) -> Result<AuthUser, (StatusCode, String)> {
+    sqlx::query_as::<_, AuthUser>(
+        "UPDATE api_keys k SET last_used_at = CURRENT_TIMESTAMP
+         FROM users u
+         WHERE u.id = k.user_id
+           AND k.key_hash = $1
+           AND k.revoked_at IS NULL
+           AND (k.expires_at IS NULL OR k.expires_at > CURRENT_TIMESTAMP)
+         RETURNING k.id AS key_id, k.user_id, u.pubkey, k.scopes",
+    )
+    .bind(hash_token(api_key))
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()))
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// Resolves an unexpired wallet session. Sessions act with the default
+/// scopes of a regular user.
+pub async fn authenticate_session(
+    pool: &PgPool,
+    token: &str,
+) -> Result<AuthUser, (StatusCode, String)> {
+    let (session_id, user_id, pubkey) = sqlx::query_as::<_, (i32, i32, String)>(
+        "SELECT s.id, s.user_id, u.pubkey
+         FROM sessions s
+         JOIN users u ON u.id = s.user_id
+         WHERE s.token_hash = $1
+           AND s.revoked_at IS NULL
+           AND s.expires_at > CURRENT_TIMESTAMP",
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((
+        StatusCode::UNAUTHORIZED,
+        "Invalid or expired session".to_string(),
+    ))?;
+
+    Ok(AuthUser {
+        key_id: None,
+        session_id: Some(session_id),
+        user_id,
+        pubkey,
+        scopes: DEFAULT_SCOPES
+            .iter()
+            .map(|s| s.as_str().to_string())
+            .collect(),
+    })
+}
+
+/// A `Bearer` session token takes precedence over `x-api-key`.
+async fn authenticate_request(
+    pool: &PgPool,
+    headers: &HeaderMap,
+) -> Result<AuthUser, (StatusCode, String)> {
+    match bearer_token(headers) {
+        Some(token) => authenticate_session(pool, token).await,
+        None => authenticate(pool, api_key_from_headers(headers)?).await,
+    }
+}
+
+/// Route layer accepting any valid session or API key.
+pub async fn require_auth(
+    State(pool): State<PgPool>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    let user = authenticate_request(&pool, req.headers()).await?;
+
+    req.extensions_mut().insert(user);
+    Ok(next.run(req).await)
+}
+
+/// Route layer for account management: requires a wallet session, so a
+/// leaked API key can't be used to mint new keys. Admin keys are accepted.
+pub async fn require_session(
+    State(pool): State<PgPool>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    let user = authenticate_request(&pool, req.headers()).await?;
+
+    if user.session_id.is_none() && !user.is_admin() {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Sign in with your wallet to use this route".to_string(),
+        ));
+    }
+
+    req.extensions_mut().insert(user);
+    Ok(next.run(req).await)
+}
+
+/// Route layer authenticating a session or API key and requiring `scope`,
+/// e.g. `.route_layer(from_fn_with_state((pool, Scope::Chat), require_scope))`.
+pub async fn require_scope(
+    State((pool, scope)): State<(PgPool, Scope)>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    let user = authenticate_request(&pool, req.headers()).await?;
+
+    if !user.has_scope(scope) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("Credentials are missing the '{}' scope", scope.as_str()),
+        ));
+    }
+
+    req.extensions_mut().insert(user);
+    Ok(next.run(req).await)
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .cloned()
+            .ok_or((StatusCode::UNAUTHORIZED, "Missing credentials".to_string()))
+    }
+}
@@ -0,0 +1,93 @@
+use {
+    crate::pricing::PricingRules,
+    chrono::NaiveDateTime,
+    rust_decimal::Decimal,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+};
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// Resolves the requested page (starting at 1) and its size into the page,
+/// its size and the rows to skip.
+pub fn page_bounds(page: Option<i64>, per_page: Option<i64>) -> (i64, i64, i64) {
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    (page, per_page, (page - 1).saturating_mul(per_page))
+}
+
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+}
+
+/// Body of both package creation and replacement. Omitted optional fields
+/// take their defaults (the free subscription limit, no window, no discount),
+/// so a replacement can also clear an activation window.
+#[derive(Deserialize)]
+pub struct PackageRequest {
+    pub name: String,
+    pub price_usdc: Decimal,
+    pub requests_amount: i32,
+    pub description: String,
+    pub max_subscriptions: Option<i32>,
+    pub pricing: Option<PricingRules>,
+    pub active_from: Option<NaiveDateTime>,
+    pub active_until: Option<NaiveDateTime>,
+    pub discount_percent: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct UserSearchQuery {
+    /// Matches any part of the user's pubkey
+    pub query: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(sqlx::FromRow, Serialize)]
+pub struct AdminUser {
+    pub id: i32,
+    pub pubkey: String,
+    pub remaining_requests: i32,
+    pub active_api_keys: i64,
+}
+
+/// A positive `amount` grants credits, a negative one takes them back.
+#[derive(Deserialize)]
+pub struct AdjustCreditsRequest {
+    pub amount: i32,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct AdjustCreditsResponse {
+    pub remaining_requests: i64,
+}
+
+#[derive(Deserialize)]
+pub struct AuditLogQuery {
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(sqlx::FromRow, Serialize)]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub admin_user_id: i32,
+    pub admin_pubkey: String,
+    pub api_key_id: Option<i32>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub details: Value,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,323 @@
+use {
+    chrono::NaiveDateTime,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    swquery::{models::FullTransaction, utils::transfer_matches_filters},
+};
+
+/// Cooldown applied to rules created without an explicit one.
+pub const DEFAULT_ALERT_COOLDOWN_SECONDS: i32 = 300;
+
+#[derive(sqlx::FromRow, Serialize, Clone, Debug)]
+pub struct AlertRule {
+    pub id: i32,
+    pub subscription_id: i32,
+    pub name: Option<String>,
+    pub condition: Value,
+    pub cooldown_seconds: i32,
+    pub last_triggered_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow, Serialize, Clone, Debug)]
+pub struct Alert {
+    pub id: i32,
+    pub rule_id: i32,
+    pub dedup_key: String,
+    pub payload: Value,
+    pub created_at: NaiveDateTime,
+}
+
+/// Condition stored in `alert_rules.condition`.
+///
+/// * `transfer` - any transfer of a transaction matches all `filters`, e.g.
+///   `[{"field": "mint", "operator": "equals", "value": "SOL"},
+///   {"field": "direction", "operator": "equals", "value": "out"},
+///   {"field": "amount", "operator": "greater_than", "value": 100}]`.
+/// * `price_change` - the token price moved by `percent` (negative for drops)
+///   relative to the oldest price observed within `window_seconds`.
+/// * `holder_share` - a new holder owns more than `percent` of the supply.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    Transfer { filters: Vec<Value> },
+    PriceChange { percent: f64, window_seconds: i64 },
+    HolderShare { percent: f64 },
+}
+
+/// Chain events pushed to the server for rule evaluation.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertEvent {
+    Transaction {
+        address: String,
+        transaction: FullTransaction,
+    },
+    Price {
+        mint: String,
+        price: f64,
+        observed_at: NaiveDateTime,
+    },
+    Holder {
+        mint: String,
+        holder: String,
+        amount: f64,
+        supply: f64,
+        new_holder: bool,
+    },
+}
+
+impl AlertEvent {
+    /// Subscription `(kind, target_key)` pairs this event is relevant to.
+    pub fn targets(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::Transaction {
+                address,
+                transaction,
+            } => {
+                let mut targets = vec![("account_trade", address.clone())];
+                for transfer in transfers(transaction) {
+                    if let Some(mint) = transfer["mint"].as_str() {
+                        targets.push(("token_trade", mint.to_string()));
+                    }
+                }
+                targets.sort();
+                targets.dedup();
+                targets
+            }
+            Self::Price { mint, .. } | Self::Holder { mint, .. } => {
+                vec![("token_trade", mint.clone())]
+            }
+        }
+    }
+
+    /// Key identifying the same occurrence across repeated deliveries.
+    pub fn dedup_key(&self) -> String {
+        match self {
+            Self::Transaction { transaction, .. } => transaction.signature.clone(),
+            Self::Price {
+                mint, observed_at, ..
+            } => format!("{}:{}", mint, observed_at.and_utc().timestamp()),
+            Self::Holder { mint, holder, .. } => format!("{}:{}", mint, holder),
+        }
+    }
+}
+
+fn transfers(transaction: &FullTransaction) -> &[Value] {
+    transaction.details["transfers"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+impl AlertCondition {
+    /// Evaluates the condition against an event. `reference_price` is the
+    /// oldest price inside the rule window and is only used by `price_change`.
+    ///
+    /// Returns the payload to store on the triggered alert.
+    pub fn evaluate(&self, event: &AlertEvent, reference_price: Option<f64>) -> Option<Value> {
+        match (self, event) {
+            (Self::Transfer { filters }, AlertEvent::Transaction { transaction, .. }) => {
+                transfers(transaction)
+                    .iter()
+                    .find(|transfer| transfer_matches_filters(transfer, filters).unwrap_or(false))
+                    .map(|transfer| {
+                        serde_json::json!({
+                            "signature": transaction.signature,
+                            "transfer": transfer,
+                        })
+                    })
+            }
+            (Self::PriceChange { percent, .. }, AlertEvent::Price { mint, price, .. }) => {
+                let reference = reference_price.filter(|p| *p > 0.0)?;
+                let change = (price - reference) / reference * 100.0;
+                let triggered = if *percent < 0.0 {
+                    change <= *percent
+                } else {
+                    change >= *percent
+                };
+
+                triggered.then(|| {
+                    serde_json::json!({
+                        "mint": mint,
+                        "price": price,
+                        "reference_price": reference,
+                        "change_percent": change,
+                    })
+                })
+            }
+            (
+                Self::HolderShare { percent },
+                AlertEvent::Holder {
+                    mint,
+                    holder,
+                    amount,
+                    supply,
+                    new_holder,
+                },
+            ) => {
+                if !new_holder || *supply <= 0.0 {
+                    return None;
+                }
+                let share = amount / supply * 100.0;
+
+                (share > *percent).then(|| {
+                    serde_json::json!({
+                        "mint": mint,
+                        "holder": holder,
+                        "share_percent": share,
+                    })
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateAlertRulePayload {
+    pub name: Option<String>,
+    pub condition: AlertCondition,
+    pub cooldown_seconds: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json, std::collections::HashMap};
+
+    fn transaction_event(transfers: Value) -> AlertEvent {
+        AlertEvent::Transaction {
+            address: "wallet".to_string(),
+            transaction: FullTransaction {
+                signature: "sig".to_string(),
+                slot: 1,
+                timestamp: 0,
+                status: "success".to_string(),
+                details: json!({ "transfers": transfers }),
+                token_metadata: HashMap::new(),
+            },
+        }
+    }
+
+    fn price_event(price: f64) -> AlertEvent {
+        AlertEvent::Price {
+            mint: "mint".to_string(),
+            price,
+            observed_at: NaiveDateTime::default(),
+        }
+    }
+
+    fn holder_event(amount: f64, supply: f64, new_holder: bool) -> AlertEvent {
+        AlertEvent::Holder {
+            mint: "mint".to_string(),
+            holder: "holder".to_string(),
+            amount,
+            supply,
+            new_holder,
+        }
+    }
+
+    #[test]
+    fn transfer_matches_when_one_transfer_passes_all_filters() {
+        let condition = AlertCondition::Transfer {
+            filters: vec![
+                json!({"field": "mint", "operator": "equals", "value": "SOL"}),
+                json!({"field": "amount", "operator": "greater_than", "value": 100}),
+            ],
+        };
+        let event = transaction_event(json!([
+            {"mint": "SOL", "amount": 50},
+            {"mint": "USDC", "amount": 500},
+            {"mint": "sol", "amount": "150"},
+        ]));
+
+        let payload = condition.evaluate(&event, None).expect("Should trigger");
+        assert_eq!(payload["signature"], "sig");
+        assert_eq!(payload["transfer"]["amount"], "150");
+    }
+
+    #[test]
+    fn transfer_without_match_does_not_trigger() {
+        let condition = AlertCondition::Transfer {
+            filters: vec![json!({"field": "amount", "operator": "greater_than", "value": 100})],
+        };
+
+        assert!(condition
+            .evaluate(&transaction_event(json!([{"amount": 10}])), None)
+            .is_none());
+        assert!(condition
+            .evaluate(&transaction_event(json!([])), None)
+            .is_none());
+    }
+
+    #[test]
+    fn transfer_with_invalid_filter_does_not_trigger() {
+        let condition = AlertCondition::Transfer {
+            filters: vec![json!({"field": "amount", "operator": "unknown", "value": 1})],
+        };
+
+        assert!(condition
+            .evaluate(&transaction_event(json!([{"amount": 10}])), None)
+            .is_none());
+    }
+
+    #[test]
+    fn price_change_triggers_in_the_configured_direction() {
+        let rise = AlertCondition::PriceChange {
+            percent: 10.0,
+            window_seconds: 60,
+        };
+        let drop = AlertCondition::PriceChange {
+            percent: -10.0,
+            window_seconds: 60,
+        };
+
+        let payload = rise
+            .evaluate(&price_event(110.0), Some(100.0))
+            .expect("A 10% rise should trigger");
+        assert_eq!(payload["reference_price"], 100.0);
+        assert!(rise.evaluate(&price_event(109.0), Some(100.0)).is_none());
+        assert!(rise.evaluate(&price_event(80.0), Some(100.0)).is_none());
+
+        assert!(drop.evaluate(&price_event(90.0), Some(100.0)).is_some());
+        assert!(drop.evaluate(&price_event(95.0), Some(100.0)).is_none());
+        assert!(drop.evaluate(&price_event(120.0), Some(100.0)).is_none());
+    }
+
+    #[test]
+    fn price_change_needs_a_positive_reference_price() {
+        let condition = AlertCondition::PriceChange {
+            percent: 10.0,
+            window_seconds: 60,
+        };
+
+        assert!(condition.evaluate(&price_event(200.0), None).is_none());
+        assert!(condition.evaluate(&price_event(200.0), Some(0.0)).is_none());
+    }
+
+    #[test]
+    fn holder_share_triggers_for_new_holders_above_the_share() {
+        let condition = AlertCondition::HolderShare { percent: 5.0 };
+
+        let payload = condition
+            .evaluate(&holder_event(60.0, 1_000.0, true), None)
+            .expect("A 6% holder should trigger");
+        assert_eq!(payload["holder"], "holder");
+        assert!(condition
+            .evaluate(&holder_event(50.0, 1_000.0, true), None)
+            .is_none());
+        assert!(condition
+            .evaluate(&holder_event(60.0, 1_000.0, false), None)
+            .is_none());
+        assert!(condition
+            .evaluate(&holder_event(60.0, 0.0, true), None)
+            .is_none());
+    }
+
+    #[test]
+    fn condition_ignores_other_event_kinds() {
+        let condition = AlertCondition::HolderShare { percent: 5.0 };
+
+        assert!(condition.evaluate(&price_event(1.0), Some(1.0)).is_none());
+    }
+}
@@ -0,0 +1,83 @@
+use {
+    chrono::NaiveDateTime,
+    serde::{Deserialize, Serialize},
+};
+
+/// Active (unrevoked, unexpired) keys a user can hold at once.
+pub const MAX_API_KEYS_PER_USER: i64 = 10;
+
+/// How long a rotated key keeps working when no grace period is given.
+pub const DEFAULT_ROTATION_GRACE_SECONDS: i64 = 24 * 60 * 60;
+pub const MAX_ROTATION_GRACE_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// An `api_keys` row; the owner and key hash stay in the database.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct ApiKey {
+    pub id: i32,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub label: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub replaced_by: Option<i32>,
+}
+
+/// An API key as shown to its owner; the key itself is masked.
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    pub id: i32,
+    pub label: Option<String>,
+    pub masked_key: String,
+    pub scopes: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub replaced_by: Option<i32>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id,
+            label: key.label,
+            masked_key: format!("{}{}", key.key_prefix, "*".repeat(24)),
+            scopes: key.scopes,
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+            revoked_at: key.revoked_at,
+            replaced_by: key.replaced_by,
+        }
+    }
+}
+
+/// Returned once, when a key is created or rotated.
+#[derive(Serialize)]
+pub struct CreatedApiKeyResponse {
+    pub api_key: String,
+    #[serde(flatten)]
+    pub key: ApiKeyResponse,
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyPayload {
+    pub label: Option<String>,
+    /// Defaults to the scopes of the key making the request.
+    pub scopes: Option<Vec<String>>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateApiKeyPayload {
+    pub label: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct RotateApiKeyPayload {
+    /// Seconds the old key keeps working, defaults to one day.
+    pub grace_period_seconds: Option<i64>,
+}
@@ -1,4 +1,4 @@
-use chrono::NaiveDateTime;
+use {chrono::NaiveDateTime, serde_json::Value};
 
 #[derive(sqlx::FromRow)]
 pub struct ChatModel {
@@ -8,4 +8,7 @@ pub struct ChatModel {
     pub response: Option<String>,
     pub tokens_used: i64,
     pub created_at: NaiveDateTime,
+    pub intent: Option<String>,
+    pub credits_charged: i32,
+    pub cost_breakdown: Option<Value>,
 }
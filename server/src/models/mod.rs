@@ -1,6 +1,15 @@
+pub mod admin;
+pub mod alert;
+pub mod api_key;
 pub mod chat;
-pub mod credits;
 pub mod package;
+pub mod payment_intent;
+pub mod promotion;
+pub mod session;
+pub mod subscription;
 pub mod user;
 
-pub use {chat::*, credits::*, package::*, user::*};
+pub use {
+    admin::*, alert::*, api_key::*, chat::*, package::*, payment_intent::*, promotion::*,
+    session::*, subscription::*, user::*,
+};
@@ -12,6 +12,29 @@ pub struct Package {
     pub price_usdc: DecimalType,
     pub requests_amount: i32,
     pub description: String,
+    pub max_subscriptions: i32,
+    pub pricing: Option<serde_json::Value>,
+    pub active_from: Option<NaiveDateTime>,
+    pub active_until: Option<NaiveDateTime>,
+    pub discount_percent: i32,
+}
+
+/// Packages that can be bought right now.
+pub const ACTIVE_PACKAGE: &str = "(active_from IS NULL OR active_from <= CURRENT_TIMESTAMP)
+     AND (active_until IS NULL OR active_until > CURRENT_TIMESTAMP)";
+
+impl Package {
+    /// Price after the package's discount, rounded to the cent.
+    pub fn effective_price(&self) -> Decimal {
+        (self.price_usdc * Decimal::from(100 - self.discount_percent) / Decimal::from(100))
+            .round_dp(2)
+    }
+
+    /// Whether the package is windowed or discounted, so its price depends
+    /// on when it was bought.
+    pub fn has_variable_price(&self) -> bool {
+        self.active_from.is_some() || self.active_until.is_some() || self.discount_percent > 0
+    }
 }
 
 #[derive(sqlx::FromRow, Serialize)]
@@ -29,6 +52,7 @@ pub struct VerifyTransactionRequest {
     pub package_id: i32,
     pub signature: String,
     pub user_pubkey: String,
+    pub promo_code: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -37,8 +61,13 @@ pub struct PackageResponse {
     pub name: String,
     #[serde(with = "rust_decimal::serde::float")]
     pub price_usdc: Decimal,
+    /// Price before the discount, set only when the package is discounted
+    #[serde(with = "rust_decimal::serde::float_option")]
+    pub original_price_usdc: Option<Decimal>,
+    pub discount_percent: i32,
     pub description: String,
     pub requests_amount: i32,
+    pub active_until: Option<NaiveDateTime>,
 }
 
 #[derive(Deserialize)]
@@ -64,16 +93,21 @@ pub struct VerifyTransactionResponse {
     pub message: String,
     pub remaining_requests: i64,
     pub package_requests: i32,
+    /// Credits granted by the promo code
+    pub bonus_requests: i32,
 }
 
 impl From<Package> for PackageResponse {
     fn from(package: Package) -> Self {
         Self {
             id: package.id,
+            price_usdc: package.effective_price(),
+            original_price_usdc: (package.discount_percent > 0).then_some(package.price_usdc),
+            discount_percent: package.discount_percent,
             name: package.name,
-            price_usdc: package.price_usdc,
             description: package.description,
             requests_amount: package.requests_amount,
+            active_until: package.active_until,
         }
     }
 }
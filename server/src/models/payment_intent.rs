@@ -0,0 +1,46 @@
+use {
+    chrono::NaiveDateTime,
+    rust_decimal::Decimal,
+    serde::{Deserialize, Serialize},
+};
+
+/// A `payment_intents` row, always loaded for its owner.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct PaymentIntent {
+    pub id: i32,
+    pub package_id: i32,
+    pub amount_usdc: Decimal,
+    pub reference: String,
+    pub status: String,
+    pub signature: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePaymentIntentRequest {
+    pub package_id: i32,
+}
+
+/// Everything the client needs to build the transfer. `reference` must be
+/// added as a read-only, non-signer account of the transfer instruction.
+#[derive(Serialize)]
+pub struct PaymentIntentResponse {
+    pub id: i32,
+    pub package_id: i32,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub amount_usdc: Decimal,
+    pub recipient: String,
+    pub mint: String,
+    pub reference: String,
+    /// Solana Pay transfer request URL for wallets that support it
+    pub payment_url: String,
+    pub status: String,
+    pub signature: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+}
@@ -0,0 +1,59 @@
+use {
+    chrono::NaiveDateTime,
+    serde::{Deserialize, Serialize},
+};
+
+#[derive(sqlx::FromRow, Serialize, Clone, Debug)]
+pub struct PromoCode {
+    pub id: i32,
+    pub code: String,
+    /// `credits` grants `value` credits, `bonus_percent` adds `value` percent
+    /// of the purchased package's requests
+    pub kind: String,
+    pub value: i32,
+    pub package_id: Option<i32>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub max_redemptions: Option<i32>,
+    pub per_user_limit: i32,
+    pub redemptions: i32,
+    pub created_at: NaiveDateTime,
+    pub disabled_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePromoCodeRequest {
+    pub code: String,
+    pub kind: String,
+    pub value: i32,
+    pub package_id: Option<i32>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub max_redemptions: Option<i32>,
+    /// Defaults to 1
+    pub per_user_limit: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct RedeemPromoCodeRequest {
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct RedeemPromoCodeResponse {
+    pub credits_granted: i32,
+    pub remaining_requests: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ApplyReferralRequest {
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct ReferralResponse {
+    pub code: String,
+    /// Link to share; new users open it and apply `code` before buying
+    pub link: String,
+    pub referrals: i64,
+    /// Referrals that made a purchase and earned both sides their credits
+    pub rewarded: i64,
+}
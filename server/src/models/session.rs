@@ -0,0 +1,34 @@
+use {
+    chrono::NaiveDateTime,
+    serde::{Deserialize, Serialize},
+};
+
+#[derive(Deserialize)]
+pub struct NonceRequest {
+    pub pubkey: String,
+}
+
+/// Challenge the wallet must sign verbatim.
+#[derive(Serialize)]
+pub struct NonceResponse {
+    pub nonce: String,
+    pub message: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct VerifySignatureRequest {
+    pub pubkey: String,
+    pub nonce: String,
+    /// Base58 ed25519 signature of the challenge message.
+    pub signature: String,
+}
+
+/// Send as `Authorization: Bearer <token>`.
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub token: String,
+    pub user_id: i32,
+    pub pubkey: String,
+    pub expires_at: NaiveDateTime,
+}
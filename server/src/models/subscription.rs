@@ -0,0 +1,69 @@
+use {
+    chrono::NaiveDateTime,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::str::FromStr,
+};
+
+/// Subscriptions a user can hold when no paid package has been bought yet.
+pub const FREE_SUBSCRIPTION_LIMIT: i64 = 3;
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
+pub struct Subscription {
+    pub id: i32,
+    pub user_id: i32,
+    pub kind: String,
+    pub target_key: String,
+    pub filters: Value,
+    pub created_at: NaiveDateTime,
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    AccountTrade,
+    TokenTrade,
+    NewToken,
+}
+
+impl SubscriptionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AccountTrade => "account_trade",
+            Self::TokenTrade => "token_trade",
+            Self::NewToken => "new_token",
+        }
+    }
+
+    /// Whether subscriptions of this kind are bound to a wallet or mint key.
+    pub fn requires_target(&self) -> bool {
+        !matches!(self, Self::NewToken)
+    }
+}
+
+impl FromStr for SubscriptionKind {
+    type Err = String;
+
+    /// Accepts both the stored names (`account_trade`) and the method suffixes
+    /// sent by the SDK (`AccountTrade` from `subscribeAccountTrade`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "account_trade" | "AccountTrade" => Ok(Self::AccountTrade),
+            "token_trade" | "TokenTrade" => Ok(Self::TokenTrade),
+            "new_token" | "NewToken" => Ok(Self::NewToken),
+            _ => Err(format!("Unknown subscription kind: {}", s)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SubscriptionPayload {
+    pub method: String,
+    pub keys: Option<Vec<String>>,
+    pub filters: Option<Value>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateSubscriptionPayload {
+    pub paused: bool,
+}
@@ -1,20 +1,19 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 
 #[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
 pub struct User {
     pub id: i32,
     pub pubkey: String,
-    pub subscriptions: Value,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct UserWithApiKey {
     pub id: i32,
     pub pubkey: String,
-    pub subscriptions: Value,
-    pub api_key: Option<String>,
+    /// First characters of the user's newest active key; keys are stored
+    /// hashed, so the full key is only returned when it is issued.
+    pub api_key_prefix: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -0,0 +1,662 @@
+use {
+    crate::{
+        config::Config,
+        ledger::{self, EntryKind},
+        promotions,
+    },
+    axum::http::StatusCode,
+    chrono::NaiveDateTime,
+    rust_decimal::{prelude::ToPrimitive, Decimal},
+    solana_client::{
+        nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+        rpc_config::RpcTransactionConfig,
+    },
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature},
+    solana_transaction_status::{
+        option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+        UiTransactionEncoding, UiTransactionTokenBalance,
+    },
+    sqlx::PgPool,
+    std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration},
+    thiserror::Error,
+};
+
+/// Transfers sent just before an intent expires still need time to finalize.
+const FINALIZATION_GRACE_SECS: i64 = 120;
+
+#[derive(Debug, Error)]
+pub enum PaymentError {
+    #[error("Transaction not found or not finalized yet")]
+    NotFound,
+
+    #[error("Failed to fetch transaction: {0}")]
+    Rpc(String),
+
+    #[error("Transaction failed on-chain")]
+    Failed,
+
+    #[error("Transaction is missing {0}")]
+    Malformed(&'static str),
+
+    #[error("Transaction does not match the signature")]
+    SignatureMismatch,
+
+    #[error("Transaction was not paid by {0}")]
+    WrongPayer(Pubkey),
+
+    #[error("Transaction has no {0} transfer to the recipient")]
+    NoTransfer(Pubkey),
+
+    #[error("Expected {expected} base units but the recipient received {received}")]
+    AmountMismatch { expected: u64, received: u64 },
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl PaymentError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Rpc(_) => StatusCode::BAD_GATEWAY,
+            Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl From<PaymentError> for (StatusCode, String) {
+    fn from(e: PaymentError) -> Self {
+        (e.status(), e.to_string())
+    }
+}
+
+/// Transfer a purchase must contain. `amount` is in whole tokens (e.g. USDC)
+/// and is scaled by the mint decimals found in the transaction.
+#[derive(Clone, Debug)]
+pub struct ExpectedPayment {
+    pub signature: Signature,
+    pub payer: Pubkey,
+    /// Wallet owning the token account that receives the payment
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: Decimal,
+}
+
+/// Fetches the transaction at `finalized` commitment, so only transactions
+/// that can no longer be rolled back are returned, and verifies it.
+pub async fn fetch_and_verify(
+    rpc_client: &RpcClient,
+    expected: &ExpectedPayment,
+) -> Result<u64, PaymentError> {
+    // `getTransaction` answers null for unknown and not-yet-finalized
+    // signatures alike, so ask for the status first
+    let status = rpc_client
+        .get_signature_status_with_commitment_and_history(
+            &expected.signature,
+            CommitmentConfig::finalized(),
+            true,
+        )
+        .await
+        .map_err(|e| PaymentError::Rpc(e.to_string()))?;
+    if status.is_none() {
+        return Err(PaymentError::NotFound);
+    }
+
+    let transaction = rpc_client
+        .get_transaction_with_config(
+            &expected.signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(CommitmentConfig::finalized()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await
+        .map_err(|e| PaymentError::Rpc(e.to_string()))?;
+
+    verify_payment(&transaction, expected)
+}
+
+/// Checks that `transaction` succeeded, was paid for by the expected payer
+/// and moved exactly the expected amount of `mint` to the recipient. Returns
+/// the amount received in base units.
+///
+/// Token balances are matched by account index, since `pre_token_balances`
+/// omits accounts created by the transaction itself.
+pub fn verify_payment(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    expected: &ExpectedPayment,
+) -> Result<u64, PaymentError> {
+    let meta = transaction
+        .transaction
+        .meta
+        .as_ref()
+        .ok_or(PaymentError::Malformed("status metadata"))?;
+
+    if meta.err.is_some() {
+        return Err(PaymentError::Failed);
+    }
+
+    let decoded = transaction
+        .transaction
+        .transaction
+        .decode()
+        .ok_or(PaymentError::Malformed("a decodable message"))?;
+
+    if decoded.signatures.first() != Some(&expected.signature) {
+        return Err(PaymentError::SignatureMismatch);
+    }
+
+    // The fee payer is always the first account and always signs
+    if decoded.message.static_account_keys().first() != Some(&expected.payer) {
+        return Err(PaymentError::WrongPayer(expected.payer));
+    }
+
+    let pre = recipient_balances(&meta.pre_token_balances, expected)?;
+    let post = recipient_balances(&meta.post_token_balances, expected)?;
+
+    let Some(decimals) = post.values().map(|(_, decimals)| *decimals).next() else {
+        return Err(PaymentError::NoTransfer(expected.mint));
+    };
+
+    let received = post.iter().try_fold(0u64, |total, (index, (amount, _))| {
+        let before = pre.get(index).map_or(0, |(amount, _)| *amount);
+        amount
+            .checked_sub(before)
+            .and_then(|delta| total.checked_add(delta))
+            .ok_or(PaymentError::NoTransfer(expected.mint))
+    })?;
+
+    let expected_amount = to_base_units(expected.amount, decimals)
+        .ok_or(PaymentError::Malformed("a representable expected amount"))?;
+
+    if received != expected_amount {
+        return Err(PaymentError::AmountMismatch {
+            expected: expected_amount,
+            received,
+        });
+    }
+
+    Ok(received)
+}
+
+/// Balances (base units, decimals) of the recipient's `mint` token accounts,
+/// keyed by account index.
+fn recipient_balances(
+    balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>,
+    expected: &ExpectedPayment,
+) -> Result<HashMap<u8, (u64, u8)>, PaymentError> {
+    let OptionSerializer::Some(balances) = balances else {
+        return Err(PaymentError::Malformed("token balances"));
+    };
+
+    let mint = expected.mint.to_string();
+    let recipient = expected.recipient.to_string();
+
+    balances
+        .iter()
+        .filter(|balance| {
+            balance.mint == mint
+                && matches!(&balance.owner, OptionSerializer::Some(owner) if *owner == recipient)
+        })
+        .map(|balance| {
+            let amount = balance
+                .ui_token_amount
+                .amount
+                .parse::<u64>()
+                .map_err(|_| PaymentError::Malformed("a valid token amount"))?;
+            Ok((
+                balance.account_index,
+                (amount, balance.ui_token_amount.decimals),
+            ))
+        })
+        .collect()
+}
+
+/// Converts a whole-token amount to base units, rejecting amounts with more
+/// precision than the mint supports.
+pub fn to_base_units(amount: Decimal, decimals: u8) -> Option<u64> {
+    let scaled = amount.checked_mul(Decimal::from(10u64.checked_pow(decimals as u32)?))?;
+    if scaled.fract() != Decimal::ZERO {
+        return None;
+    }
+    scaled.to_u64()
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingIntent {
+    id: i32,
+    user_id: i32,
+    package_id: i32,
+    requests_amount: i32,
+    amount_usdc: Decimal,
+    reference: String,
+    expires_at: NaiveDateTime,
+    pubkey: String,
+}
+
+/// Polls pending payment intents until their transfer is found or they
+/// expire.
+pub fn spawn_intent_watcher(pool: PgPool, config: Arc<Config>, rpc_client: Arc<RpcClient>) {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(config.payment_poll_interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = confirm_pending_intents(&pool, &config, &rpc_client).await {
+                eprintln!("Failed to confirm payment intents: {}", e);
+            }
+        }
+    });
+}
+
+async fn confirm_pending_intents(
+    pool: &PgPool,
+    config: &Config,
+    rpc_client: &RpcClient,
+) -> Result<(), PaymentError> {
+    let intents = sqlx::query_as::<_, PendingIntent>(
+        "SELECT i.id, i.user_id, i.package_id, p.requests_amount, i.amount_usdc,
+                i.reference, i.expires_at, u.pubkey
+         FROM payment_intents i
+         JOIN packages p ON p.id = i.package_id
+         JOIN users u ON u.id = i.user_id
+         WHERE i.status = 'pending'
+         ORDER BY i.created_at",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for intent in intents {
+        if let Err(e) = confirm_intent(pool, config, rpc_client, &intent).await {
+            eprintln!("Failed to confirm payment intent {}: {}", intent.id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Looks the intent's reference up on-chain and completes the intent with the
+/// first transfer that verifies. Invalid transfers are reported on the intent
+/// but only fail it once it expires, so the buyer can still pay correctly.
+async fn confirm_intent(
+    pool: &PgPool,
+    config: &Config,
+    rpc_client: &RpcClient,
+    intent: &PendingIntent,
+) -> Result<(), PaymentError> {
+    let reference = Pubkey::from_str(&intent.reference)
+        .map_err(|_| PaymentError::Malformed("a valid reference"))?;
+    let payer =
+        Pubkey::from_str(&intent.pubkey).map_err(|_| PaymentError::Malformed("a valid payer"))?;
+
+    let statuses = rpc_client
+        .get_signatures_for_address_with_config(
+            &reference,
+            GetConfirmedSignaturesForAddress2Config {
+                commitment: Some(CommitmentConfig::finalized()),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|e| PaymentError::Rpc(e.to_string()))?;
+
+    let mut rejection = None;
+
+    // Signatures come newest first; honour the earliest valid payment
+    for status in statuses.iter().rev().filter(|status| status.err.is_none()) {
+        let Ok(signature) = Signature::from_str(&status.signature) else {
+            continue;
+        };
+        let expected = ExpectedPayment {
+            signature,
+            payer,
+            recipient: config.recipient_wallet,
+            mint: config.usdc_mint,
+            amount: intent.amount_usdc,
+        };
+
+        match fetch_and_verify(rpc_client, &expected).await {
+            Ok(_) => return complete_intent(pool, config, intent, &status.signature).await,
+            Err(e @ (PaymentError::NotFound | PaymentError::Rpc(_))) => return Err(e),
+            Err(e) => rejection = Some(e.to_string()),
+        }
+    }
+
+    let expired = intent.expires_at
+        < chrono::Utc::now().naive_utc() - chrono::Duration::seconds(FINALIZATION_GRACE_SECS);
+
+    match (expired, rejection) {
+        (true, Some(reason)) => {
+            sqlx::query(
+                "UPDATE payment_intents SET status = 'failed', failure_reason = $1
+                 WHERE id = $2 AND status = 'pending'",
+            )
+            .bind(reason)
+            .bind(intent.id)
+            .execute(pool)
+            .await?;
+        }
+        (true, None) => {
+            sqlx::query(
+                "UPDATE payment_intents SET status = 'expired' WHERE id = $1 AND status = 'pending'",
+            )
+            .bind(intent.id)
+            .execute(pool)
+            .await?;
+        }
+        (false, Some(reason)) => {
+            sqlx::query(
+                "UPDATE payment_intents SET failure_reason = $1 WHERE id = $2 AND status = 'pending'",
+            )
+            .bind(reason)
+            .bind(intent.id)
+            .execute(pool)
+            .await?;
+        }
+        (false, None) => {}
+    }
+
+    Ok(())
+}
+
+/// Records the purchase and grants the package's credits exactly once.
+async fn complete_intent(
+    pool: &PgPool,
+    config: &Config,
+    intent: &PendingIntent,
+    signature: &str,
+) -> Result<(), PaymentError> {
+    let mut tx = pool.begin().await?;
+
+    let claimed = sqlx::query(
+        "UPDATE payment_intents
+         SET status = 'completed', signature = $1, failure_reason = NULL,
+             completed_at = CURRENT_TIMESTAMP
+         WHERE id = $2 AND status = 'pending'",
+    )
+    .bind(signature)
+    .bind(intent.id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        == 1;
+
+    if !claimed {
+        return Ok(());
+    }
+
+    let recorded = sqlx::query(
+        "INSERT INTO transactions (user_id, package_id, signature, status)
+         VALUES ($1, $2, $3, 'completed')
+         ON CONFLICT (signature) DO NOTHING",
+    )
+    .bind(intent.user_id)
+    .bind(intent.package_id)
+    .bind(signature)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        == 1;
+
+    if !recorded {
+        // Already redeemed through `/packages/verify`
+        drop(tx);
+        sqlx::query(
+            "UPDATE payment_intents
+             SET status = 'failed', failure_reason = 'Transaction has already been processed'
+             WHERE id = $1 AND status = 'pending'",
+        )
+        .bind(intent.id)
+        .execute(pool)
+        .await?;
+        return Ok(());
+    }
+
+    ledger::record(
+        &mut tx,
+        intent.user_id,
+        EntryKind::Purchase,
+        intent.requests_amount,
+        Some(signature),
+    )
+    .await?;
+
+    promotions::reward_referral(&mut tx, config, intent.user_id).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_account_decoder::{
+            parse_account_data::SplTokenAdditionalData, parse_token::token_amount_to_ui_amount_v2,
+        },
+        solana_sdk::{
+            hash::Hash,
+            instruction::{AccountMeta, Instruction, InstructionError},
+            signature::Keypair,
+            signer::Signer,
+            transaction::{Transaction, TransactionError},
+        },
+        solana_transaction_status::{
+            Encodable, EncodedTransactionWithStatusMeta, TransactionStatusMeta,
+            TransactionTokenBalance,
+        },
+    };
+
+    const DECIMALS: u8 = 6;
+    /// Index of the recipient's token account in the fixture's message
+    const RECIPIENT_INDEX: u8 = 2;
+
+    struct Fixture {
+        payer: Keypair,
+        recipient: Pubkey,
+        mint: Pubkey,
+        token_program: Pubkey,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self {
+                payer: Keypair::new(),
+                recipient: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                token_program: Pubkey::new_unique(),
+            }
+        }
+
+        /// A payment of `amount` whole tokens to the fixture's recipient.
+        fn expected(
+            &self,
+            transaction: &EncodedConfirmedTransactionWithStatusMeta,
+            amount: u64,
+        ) -> ExpectedPayment {
+            ExpectedPayment {
+                signature: transaction
+                    .transaction
+                    .transaction
+                    .decode()
+                    .unwrap()
+                    .signatures[0],
+                payer: self.payer.pubkey(),
+                recipient: self.recipient,
+                mint: self.mint,
+                amount: Decimal::from(amount),
+            }
+        }
+
+        fn balance(&self, mint: &Pubkey, owner: &Pubkey, amount: u64) -> TransactionTokenBalance {
+            TransactionTokenBalance {
+                account_index: RECIPIENT_INDEX,
+                mint: mint.to_string(),
+                ui_token_amount: token_amount_to_ui_amount_v2(
+                    amount,
+                    &SplTokenAdditionalData::with_decimals(DECIMALS),
+                ),
+                owner: owner.to_string(),
+                program_id: self.token_program.to_string(),
+            }
+        }
+
+        /// A finalized transfer signed by `payer`, where the recipient's
+        /// token account goes from `before` (`None` when the transaction
+        /// creates it) to `after` base units.
+        fn transaction(
+            &self,
+            payer: &Keypair,
+            before: Option<u64>,
+            after: u64,
+            status: Result<(), TransactionError>,
+        ) -> EncodedConfirmedTransactionWithStatusMeta {
+            let transfer = Instruction::new_with_bytes(
+                self.token_program,
+                &[],
+                vec![
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                ],
+            );
+            let transaction = Transaction::new_signed_with_payer(
+                &[transfer],
+                Some(&payer.pubkey()),
+                &[payer],
+                Hash::default(),
+            );
+
+            let meta = TransactionStatusMeta {
+                status,
+                pre_token_balances: Some(
+                    before
+                        .map(|amount| self.balance(&self.mint, &self.recipient, amount))
+                        .into_iter()
+                        .collect(),
+                ),
+                post_token_balances: Some(vec![self.balance(&self.mint, &self.recipient, after)]),
+                ..TransactionStatusMeta::default()
+            };
+
+            EncodedConfirmedTransactionWithStatusMeta {
+                slot: 1,
+                transaction: EncodedTransactionWithStatusMeta {
+                    transaction: transaction.encode(UiTransactionEncoding::Base64),
+                    meta: Some(meta.into()),
+                    version: None,
+                },
+                block_time: Some(0),
+            }
+        }
+    }
+
+    fn tokens(amount: u64) -> u64 {
+        amount * 10u64.pow(DECIMALS as u32)
+    }
+
+    #[test]
+    fn accepts_the_expected_transfer() {
+        let fixture = Fixture::new();
+        let transaction = fixture.transaction(&fixture.payer, Some(tokens(1)), tokens(11), Ok(()));
+
+        let received = verify_payment(&transaction, &fixture.expected(&transaction, 10)).unwrap();
+
+        assert_eq!(received, tokens(10));
+    }
+
+    #[test]
+    fn accepts_a_recipient_account_created_in_the_same_transaction() {
+        let fixture = Fixture::new();
+        let transaction = fixture.transaction(&fixture.payer, None, tokens(10), Ok(()));
+
+        let received = verify_payment(&transaction, &fixture.expected(&transaction, 10)).unwrap();
+
+        assert_eq!(received, tokens(10));
+    }
+
+    #[test]
+    fn rejects_a_transaction_paid_by_someone_else() {
+        let fixture = Fixture::new();
+        let transaction = fixture.transaction(&Keypair::new(), Some(0), tokens(10), Ok(()));
+
+        let result = verify_payment(&transaction, &fixture.expected(&transaction, 10));
+
+        assert!(
+            matches!(result, Err(PaymentError::WrongPayer(payer)) if payer == fixture.payer.pubkey())
+        );
+    }
+
+    #[test]
+    fn rejects_a_transfer_of_another_mint() {
+        let fixture = Fixture::new();
+        let transaction = fixture.transaction(&fixture.payer, Some(0), tokens(10), Ok(()));
+        let mut expected = fixture.expected(&transaction, 10);
+        expected.mint = Pubkey::new_unique();
+
+        let result = verify_payment(&transaction, &expected);
+
+        assert!(matches!(result, Err(PaymentError::NoTransfer(mint)) if mint == expected.mint));
+    }
+
+    #[test]
+    fn rejects_a_transfer_to_another_recipient() {
+        let fixture = Fixture::new();
+        let transaction = fixture.transaction(&fixture.payer, Some(0), tokens(10), Ok(()));
+        let mut expected = fixture.expected(&transaction, 10);
+        expected.recipient = Pubkey::new_unique();
+
+        let result = verify_payment(&transaction, &expected);
+
+        assert!(matches!(result, Err(PaymentError::NoTransfer(_))));
+    }
+
+    #[test]
+    fn rejects_a_failed_transaction() {
+        let fixture = Fixture::new();
+        let transaction = fixture.transaction(
+            &fixture.payer,
+            Some(0),
+            tokens(10),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(1),
+            )),
+        );
+
+        let result = verify_payment(&transaction, &fixture.expected(&transaction, 10));
+
+        assert!(matches!(result, Err(PaymentError::Failed)));
+    }
+
+    #[test]
+    fn rejects_a_different_amount() {
+        let fixture = Fixture::new();
+        let transaction = fixture.transaction(&fixture.payer, Some(tokens(5)), tokens(14), Ok(()));
+
+        let result = verify_payment(&transaction, &fixture.expected(&transaction, 10));
+
+        assert!(matches!(
+            result,
+            Err(PaymentError::AmountMismatch { expected, received })
+                if expected == tokens(10) && received == tokens(9)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_decreasing_balance() {
+        let fixture = Fixture::new();
+        let transaction = fixture.transaction(&fixture.payer, Some(tokens(20)), tokens(10), Ok(()));
+
+        let result = verify_payment(&transaction, &fixture.expected(&transaction, 10));
+
+        assert!(matches!(result, Err(PaymentError::NoTransfer(_))));
+    }
+
+    #[test]
+    fn base_units_reject_excess_precision() {
+        assert_eq!(to_base_units(Decimal::new(15, 1), 6), Some(1_500_000));
+        assert_eq!(to_base_units(Decimal::new(1, 7), 6), None);
+    }
+}
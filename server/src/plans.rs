@@ -0,0 +1,213 @@
+use {
+    axum::http::StatusCode,
+    chrono::{Duration, NaiveDateTime, Utc},
+    rust_decimal::{prelude::ToPrimitive, Decimal},
+    serde::Serialize,
+    sqlx::PgPool,
+};
+
+/// Length of a plan's monthly window, as the credit-sales `PLAN_PERIOD`.
+pub const PLAN_WINDOW_SECS: i64 = 60 * 60 * 24 * 30;
+
+/// A credit-sales plan whose paid periods cover the current time.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct ActivePlan {
+    pub address: String,
+    pub plan_id: i16,
+    pub period_start: NaiveDateTime,
+    pub period_end: NaiveDateTime,
+    /// On-chain credits per window
+    pub monthly_quota: Decimal,
+    pub auto_renew: bool,
+}
+
+impl ActivePlan {
+    /// Start of the monthly window containing `now`. Windows run back to back
+    /// from `period_start`, so renewals keep the same cycle.
+    pub fn window_start(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let elapsed = (now - self.period_start).num_seconds().max(0);
+        self.period_start + Duration::seconds(elapsed - elapsed % PLAN_WINDOW_SECS)
+    }
+
+    /// Server requests the plan allows per window.
+    pub fn quota_requests(&self, credits_per_request: u64) -> i32 {
+        let quota = self.monthly_quota.to_u64().unwrap_or(0);
+        (quota / credits_per_request).min(i32::MAX as u64) as i32
+    }
+}
+
+/// Plan quota held for a unit of work until it is released or kept.
+#[derive(Debug)]
+pub struct QuotaReservation {
+    pub plan_address: String,
+    pub window_start: NaiveDateTime,
+    pub amount: i32,
+    /// Quota left in the window once the reservation was taken
+    pub remaining: i32,
+}
+
+/// The user's active plan, if any.
+pub async fn active_plan(pool: &PgPool, user_id: i32) -> Result<Option<ActivePlan>, sqlx::Error> {
+    sqlx::query_as::<_, ActivePlan>(
+        "SELECT address, plan_id, period_start, period_end, monthly_quota, auto_renew
+         FROM credit_sales_plans
+         WHERE user_id = $1 AND period_start <= $2 AND period_end > $2
+         ORDER BY period_end DESC
+         LIMIT 1",
+    )
+    .bind(user_id)
+    .bind(Utc::now().naive_utc())
+    .fetch_optional(pool)
+    .await
+}
+
+/// Requests left in the current window of the user's active plan, or `None`
+/// without one.
+pub async fn remaining_quota(
+    pool: &PgPool,
+    user_id: i32,
+    credits_per_request: u64,
+) -> Result<Option<i32>, (StatusCode, String)> {
+    let Some(plan) = active_plan(pool, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Ok(None);
+    };
+
+    requests_remaining(pool, &plan, credits_per_request)
+        .await
+        .map(Some)
+}
+
+/// Requests left in the current window of `plan`.
+pub async fn requests_remaining(
+    pool: &PgPool,
+    plan: &ActivePlan,
+    credits_per_request: u64,
+) -> Result<i32, (StatusCode, String)> {
+    let used = sqlx::query_scalar::<_, i32>(
+        "SELECT requests_used FROM plan_usage WHERE plan_address = $1 AND window_start = $2",
+    )
+    .bind(&plan.address)
+    .bind(plan.window_start(Utc::now().naive_utc()))
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .unwrap_or(0);
+
+    Ok((plan.quota_requests(credits_per_request) - used).max(0))
+}
+
+/// Holds as much of `max` requests of the active plan's window as are left,
+/// but at least `min`, like `ledger::reserve_up_to`. Returns `None` when the
+/// user has no active plan or not enough quota, so the caller can fall back
+/// to their credits.
+pub async fn reserve_quota(
+    pool: &PgPool,
+    user_id: i32,
+    credits_per_request: u64,
+    min: i32,
+    max: i32,
+) -> Result<Option<QuotaReservation>, (StatusCode, String)> {
+    let Some(plan) = active_plan(pool, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Ok(None);
+    };
+    let quota = plan.quota_requests(credits_per_request);
+    let window_start = plan.window_start(Utc::now().naive_utc());
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query(
+        "INSERT INTO plan_usage (plan_address, window_start) VALUES ($1, $2)
+         ON CONFLICT (plan_address, window_start) DO NOTHING",
+    )
+    .bind(&plan.address)
+    .bind(window_start)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Locked so concurrent requests can't use the same quota
+    let used = sqlx::query_scalar::<_, i32>(
+        "SELECT requests_used FROM plan_usage
+         WHERE plan_address = $1 AND window_start = $2 FOR UPDATE",
+    )
+    .bind(&plan.address)
+    .bind(window_start)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let remaining = quota - used;
+    if remaining < min {
+        return Ok(None);
+    }
+    let amount = remaining.min(max);
+
+    sqlx::query(
+        "UPDATE plan_usage SET requests_used = requests_used + $1
+         WHERE plan_address = $2 AND window_start = $3",
+    )
+    .bind(amount)
+    .bind(&plan.address)
+    .bind(window_start)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Some(QuotaReservation {
+        plan_address: plan.address,
+        window_start,
+        amount,
+        remaining: remaining - amount,
+    }))
+}
+
+/// Keeps `charged` of the reserved quota and returns the rest to the window.
+/// Returns the amount actually charged, which never exceeds the reservation.
+/// Quota held by a request that never settles isn't returned; it lapses with
+/// its window.
+pub async fn capture_quota(
+    pool: &PgPool,
+    reservation: &QuotaReservation,
+    charged: i32,
+) -> Result<i32, (StatusCode, String)> {
+    let charged = charged.clamp(0, reservation.amount);
+    release_quota(pool, reservation, reservation.amount - charged).await?;
+    Ok(charged)
+}
+
+/// Returns `amount` of the reserved quota to its window.
+pub async fn release_quota(
+    pool: &PgPool,
+    reservation: &QuotaReservation,
+    amount: i32,
+) -> Result<(), (StatusCode, String)> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    sqlx::query(
+        "UPDATE plan_usage SET requests_used = GREATEST(requests_used - $1, 0)
+         WHERE plan_address = $2 AND window_start = $3",
+    )
+    .bind(amount)
+    .bind(&reservation.plan_address)
+    .bind(reservation.window_start)
+    .execute(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(())
+}
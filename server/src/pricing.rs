@@ -0,0 +1,303 @@
+use {
+    axum::http::StatusCode,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    sqlx::PgPool,
+    std::collections::HashMap,
+};
+
+/// How a chat is priced, stored per package in `packages.pricing`. Fields
+/// missing from the stored JSON keep their defaults.
+///
+/// A chat costs the intent's price plus one credit for every full
+/// `items_per_credit` items hydrated and every full `tokens_per_credit`
+/// agent tokens, capped at `max_charge`. A divisor of 0 disables that part.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PricingRules {
+    pub default_intent_cost: i32,
+    /// Overrides keyed by agent intent, e.g. `getRecentTransactions`
+    pub intent_costs: HashMap<String, i32>,
+    pub items_per_credit: i64,
+    pub tokens_per_credit: i64,
+    pub max_charge: i32,
+}
+
+impl Default for PricingRules {
+    fn default() -> Self {
+        Self {
+            default_intent_cost: 1,
+            intent_costs: HashMap::new(),
+            items_per_credit: 100,
+            tokens_per_credit: 4000,
+            max_charge: 5,
+        }
+    }
+}
+
+/// What a chat actually consumed.
+#[derive(Clone, Debug, Default)]
+pub struct Usage {
+    pub intent: String,
+    pub items: i64,
+    pub query_tokens: i64,
+    pub report_tokens: i64,
+}
+
+/// Itemized price of a chat, recorded on the chat row.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostBreakdown {
+    pub intent: String,
+    pub intent_cost: i32,
+    pub items: i64,
+    pub items_cost: i32,
+    pub query_tokens: i64,
+    pub report_tokens: i64,
+    pub tokens_cost: i32,
+    /// Sum of the parts, capped at `max_charge`
+    pub total: i32,
+}
+
+impl PricingRules {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.default_intent_cost < 0 || self.intent_costs.values().any(|cost| *cost < 0) {
+            return Err("Intent costs can't be negative".to_string());
+        }
+        if self.items_per_credit < 0 || self.tokens_per_credit < 0 {
+            return Err("Pricing divisors can't be negative".to_string());
+        }
+        if self.max_charge < 1 {
+            return Err("max_charge must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn intent_cost(&self, intent: &str) -> i32 {
+        self.intent_costs
+            .get(intent)
+            .copied()
+            .unwrap_or(self.default_intent_cost)
+    }
+
+    /// What the cheapest chat costs: its intent's price with nothing hydrated
+    /// and no tokens, but at least 1 so an empty balance can't chat.
+    pub fn min_charge(&self) -> i32 {
+        self.intent_costs
+            .values()
+            .copied()
+            .fold(self.default_intent_cost, i32::min)
+            .clamp(1, self.max_charge)
+    }
+
+    pub fn price(&self, usage: &Usage) -> CostBreakdown {
+        let per = |amount: i64, divisor: i64| {
+            if divisor > 0 {
+                (amount.max(0) / divisor).min(i32::MAX as i64) as i32
+            } else {
+                0
+            }
+        };
+
+        let intent_cost = self.intent_cost(&usage.intent);
+        let items_cost = per(usage.items, self.items_per_credit);
+        let tokens_cost = per(
+            usage.query_tokens + usage.report_tokens,
+            self.tokens_per_credit,
+        );
+
+        CostBreakdown {
+            intent: usage.intent.clone(),
+            intent_cost,
+            items: usage.items,
+            items_cost,
+            query_tokens: usage.query_tokens,
+            report_tokens: usage.report_tokens,
+            tokens_cost,
+            total: intent_cost
+                .saturating_add(items_cost)
+                .saturating_add(tokens_cost)
+                .min(self.max_charge),
+        }
+    }
+}
+
+/// Rules of the user's most recently purchased package, or the defaults.
+/// Invalid stored rules are logged and ignored rather than failing the chat.
+pub async fn rules_for_user(
+    pool: &PgPool,
+    user_id: i32,
+) -> Result<PricingRules, (StatusCode, String)> {
+    let pricing = sqlx::query_scalar::<_, Option<Value>>(
+        "SELECT p.pricing
+         FROM transactions t
+         JOIN packages p ON p.id = t.package_id
+         WHERE t.user_id = $1 AND t.status = 'completed'
+         ORDER BY t.created_at DESC, t.id DESC
+         LIMIT 1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .flatten();
+
+    let Some(pricing) = pricing else {
+        return Ok(PricingRules::default());
+    };
+
+    match serde_json::from_value::<PricingRules>(pricing) {
+        Ok(rules) if rules.validate().is_ok() => Ok(rules),
+        Ok(rules) => {
+            eprintln!("Ignoring invalid pricing rules {:?}", rules);
+            Ok(PricingRules::default())
+        }
+        Err(e) => {
+            eprintln!("Failed to parse pricing rules: {}", e);
+            Ok(PricingRules::default())
+        }
+    }
+}
+
+/// Number of records the SDK hydrated for a response: transactions,
+/// signatures, tokens and so on.
+pub fn hydrated_items(response: &Value) -> i64 {
+    response
+        .as_array()
+        .or_else(|| response.get("result").and_then(Value::as_array))
+        .map_or(0, |items| items.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    fn usage(intent: &str, items: i64, query_tokens: i64, report_tokens: i64) -> Usage {
+        Usage {
+            intent: intent.to_string(),
+            items,
+            query_tokens,
+            report_tokens,
+        }
+    }
+
+    #[test]
+    fn price_adds_intent_items_and_tokens() {
+        let rules = PricingRules {
+            max_charge: 100,
+            ..PricingRules::default()
+        };
+
+        let cost = rules.price(&usage("getBalance", 250, 3000, 5000));
+
+        assert_eq!(cost.intent_cost, 1);
+        assert_eq!(cost.items_cost, 2);
+        assert_eq!(cost.tokens_cost, 2);
+        assert_eq!(cost.total, 5);
+    }
+
+    #[test]
+    fn price_uses_intent_overrides() {
+        let rules = PricingRules {
+            intent_costs: HashMap::from([("getRecentTransactions".to_string(), 3)]),
+            ..PricingRules::default()
+        };
+
+        assert_eq!(
+            rules
+                .price(&usage("getRecentTransactions", 0, 0, 0))
+                .intent_cost,
+            3
+        );
+        assert_eq!(rules.price(&usage("getBalance", 0, 0, 0)).intent_cost, 1);
+    }
+
+    #[test]
+    fn price_only_counts_full_divisors() {
+        let rules = PricingRules::default();
+
+        let cost = rules.price(&usage("getBalance", 99, 3999, 0));
+
+        assert_eq!(cost.items_cost, 0);
+        assert_eq!(cost.tokens_cost, 0);
+        assert_eq!(cost.total, 1);
+    }
+
+    #[test]
+    fn price_caps_at_max_charge() {
+        let rules = PricingRules::default();
+
+        let cost = rules.price(&usage("getBalance", 10_000, 1_000_000, 1_000_000));
+
+        assert_eq!(cost.items_cost, 100);
+        assert_eq!(cost.tokens_cost, 500);
+        assert_eq!(cost.total, rules.max_charge);
+    }
+
+    #[test]
+    fn price_ignores_disabled_divisors_and_negative_usage() {
+        let rules = PricingRules {
+            items_per_credit: 0,
+            ..PricingRules::default()
+        };
+
+        let cost = rules.price(&usage("getBalance", 1_000, -8000, 0));
+
+        assert_eq!(cost.items_cost, 0);
+        assert_eq!(cost.tokens_cost, 0);
+        assert_eq!(cost.total, 1);
+    }
+
+    #[test]
+    fn price_saturates_instead_of_overflowing() {
+        let rules = PricingRules {
+            default_intent_cost: i32::MAX,
+            items_per_credit: 1,
+            max_charge: i32::MAX,
+            ..PricingRules::default()
+        };
+
+        let cost = rules.price(&usage("getBalance", i64::MAX, 0, 0));
+
+        assert_eq!(cost.items_cost, i32::MAX);
+        assert_eq!(cost.total, i32::MAX);
+    }
+
+    #[test]
+    fn min_charge_is_the_cheapest_intent() {
+        let rules = PricingRules {
+            default_intent_cost: 3,
+            intent_costs: HashMap::from([
+                ("getBalance".to_string(), 2),
+                ("getRecentTransactions".to_string(), 4),
+            ]),
+            ..PricingRules::default()
+        };
+        assert_eq!(rules.min_charge(), 2);
+
+        let free = PricingRules {
+            default_intent_cost: 0,
+            ..PricingRules::default()
+        };
+        assert_eq!(free.min_charge(), 1);
+
+        let capped = PricingRules {
+            default_intent_cost: 10,
+            max_charge: 4,
+            ..PricingRules::default()
+        };
+        assert_eq!(capped.min_charge(), 4);
+    }
+
+    #[test]
+    fn missing_fields_keep_their_defaults() {
+        let rules: PricingRules = serde_json::from_value(json!({ "max_charge": 8 })).unwrap();
+
+        assert_eq!(
+            rules,
+            PricingRules {
+                max_charge: 8,
+                ..PricingRules::default()
+            }
+        );
+    }
+}
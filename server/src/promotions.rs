@@ -0,0 +1,241 @@
+use {
+    crate::{
+        config::Config,
+        ledger::{self, EntryKind},
+        models::{Package, PromoCode},
+    },
+    axum::http::StatusCode,
+    rand::{thread_rng, Rng},
+    sqlx::PgConnection,
+};
+
+pub const REFERRAL_CODE_LENGTH: usize = 8;
+
+/// How a promo code rewards its redeemer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PromoKind {
+    /// A fixed amount of credits
+    Credits,
+    /// A percentage of the purchased package's requests
+    BonusPercent,
+}
+
+impl PromoKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Credits => "credits",
+            Self::BonusPercent => "bonus_percent",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "credits" => Some(Self::Credits),
+            "bonus_percent" => Some(Self::BonusPercent),
+            _ => None,
+        }
+    }
+}
+
+/// Codes are matched case-insensitively and stored uppercase.
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+/// Uppercase alphanumeric code without look-alike characters.
+pub fn generate_referral_code() -> String {
+    const CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    let mut rng = thread_rng();
+    (0..REFERRAL_CODE_LENGTH)
+        .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
+/// Redeems a promo code for the user, on its own or with a purchase (the
+/// package bought and the transaction signature), and grants the credits it
+/// is worth. Run it in the purchase's transaction so a rejected code rolls
+/// the purchase back too.
+pub async fn redeem_promo_code(
+    conn: &mut PgConnection,
+    user_id: i32,
+    code: &str,
+    purchase: Option<(&Package, &str)>,
+) -> Result<i32, (StatusCode, String)> {
+    let code = normalize_code(code);
+
+    // Locked so concurrent redemptions can't exceed the limits
+    let promo = sqlx::query_as::<_, PromoCode>(
+        "SELECT * FROM promo_codes WHERE code = $1 AND disabled_at IS NULL FOR UPDATE",
+    )
+    .bind(&code)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Promo code not found".to_string()))?;
+
+    if promo
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= chrono::Utc::now().naive_utc())
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Promo code has expired".to_string(),
+        ));
+    }
+    if promo
+        .max_redemptions
+        .is_some_and(|max| promo.redemptions >= max)
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            "Promo code has been fully redeemed".to_string(),
+        ));
+    }
+
+    let package = purchase.map(|(package, _)| package);
+    if promo
+        .package_id
+        .is_some_and(|id| package.map(|p| p.id) != Some(id))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Promo code doesn't apply to this package".to_string(),
+        ));
+    }
+
+    let redeemed = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM promo_redemptions WHERE promo_code_id = $1 AND user_id = $2",
+    )
+    .bind(promo.id)
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if redeemed >= promo.per_user_limit as i64 {
+        return Err((
+            StatusCode::CONFLICT,
+            "Promo code has already been redeemed".to_string(),
+        ));
+    }
+
+    let credits = match PromoKind::parse(&promo.kind) {
+        Some(PromoKind::Credits) => promo.value,
+        Some(PromoKind::BonusPercent) => {
+            let package = package.ok_or((
+                StatusCode::BAD_REQUEST,
+                "Promo code applies to a package purchase".to_string(),
+            ))?;
+            (package.requests_amount as i64 * promo.value as i64 / 100).min(i32::MAX as i64) as i32
+        }
+        None => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Unknown promo code kind: {}", promo.kind),
+            ))
+        }
+    };
+
+    sqlx::query(
+        "INSERT INTO promo_redemptions (promo_code_id, user_id, signature, credits)
+         VALUES ($1, $2, $3, $4)",
+    )
+    .bind(promo.id)
+    .bind(user_id)
+    .bind(purchase.map(|(_, signature)| signature))
+    .bind(credits)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query("UPDATE promo_codes SET redemptions = redemptions + 1 WHERE id = $1")
+        .bind(promo.id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if credits > 0 {
+        ledger::record(
+            &mut *conn,
+            user_id,
+            EntryKind::Grant,
+            credits,
+            Some(&format!("promo:{}", code)),
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    Ok(credits)
+}
+
+/// Whether the user has paid for anything: a priced package, bought through
+/// `/packages/verify` or a payment intent, or credits or a plan bought from the
+/// credit-sales program.
+pub async fn has_paid_purchase(conn: &mut PgConnection, user_id: i32) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (
+            SELECT 1 FROM transactions t
+            JOIN packages p ON p.id = t.package_id
+            WHERE t.user_id = $1 AND t.status = 'completed'
+              AND p.price_usdc > 0 AND p.discount_percent < 100
+        ) OR EXISTS (
+            SELECT 1 FROM payment_intents WHERE user_id = $1 AND status = 'completed'
+        ) OR EXISTS (
+            SELECT 1 FROM credit_sales_accounts WHERE user_id = $1 AND purchase_count > 0
+        ) OR EXISTS (
+            SELECT 1 FROM credit_sales_plans WHERE user_id = $1
+        )",
+    )
+    .bind(user_id)
+    .fetch_one(conn)
+    .await
+}
+
+/// Rewards both sides of the user's referral, once, if they have made a paid
+/// purchase. Run it in the purchase's transaction, after recording it.
+pub async fn reward_referral(
+    conn: &mut PgConnection,
+    config: &Config,
+    referee_id: i32,
+) -> Result<(), sqlx::Error> {
+    if !has_paid_purchase(&mut *conn, referee_id).await? {
+        return Ok(());
+    }
+
+    let referrer_id = sqlx::query_scalar::<_, i32>(
+        "UPDATE referrals SET rewarded_at = CURRENT_TIMESTAMP
+         WHERE referee_id = $1 AND rewarded_at IS NULL
+         RETURNING referrer_id",
+    )
+    .bind(referee_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(referrer_id) = referrer_id else {
+        return Ok(());
+    };
+
+    if config.referral_referrer_credits > 0 {
+        ledger::record(
+            &mut *conn,
+            referrer_id,
+            EntryKind::Grant,
+            config.referral_referrer_credits,
+            Some(&format!("referral:{}", referee_id)),
+        )
+        .await?;
+    }
+    if config.referral_referee_credits > 0 {
+        ledger::record(
+            &mut *conn,
+            referee_id,
+            EntryKind::Grant,
+            config.referral_referee_credits,
+            Some(&format!("referred_by:{}", referrer_id)),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
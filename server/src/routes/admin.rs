@@ -0,0 +1,567 @@
+use {
+    super::packages::get_user_id,
+    crate::{
+        ledger::{self, EntryKind},
+        middlewares::auth::AuthUser,
+        models::{
+            page_bounds, AdjustCreditsRequest, AdjustCreditsResponse, AdminUser, AuditLogEntry,
+            AuditLogQuery, CreatePromoCodeRequest, Package, PackageRequest, Page, PromoCode,
+            UserSearchQuery, FREE_SUBSCRIPTION_LIMIT,
+        },
+        promotions::{normalize_code, PromoKind},
+    },
+    axum::{
+        extract::{Path, Query, State},
+        http::StatusCode,
+        Json,
+    },
+    rust_decimal::Decimal,
+    serde_json::{json, Value},
+    sqlx::{PgConnection, PgPool},
+};
+
+const MAX_REASON_LENGTH: usize = 500;
+
+/// Records an admin action. Run it in the same transaction as the change so
+/// one is never kept without the other.
+async fn audit(
+    conn: &mut PgConnection,
+    auth: &AuthUser,
+    action: &str,
+    target_type: &str,
+    target_id: &str,
+    details: Value,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query(
+        "INSERT INTO admin_audit_log
+            (admin_user_id, api_key_id, action, target_type, target_id, details)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(auth.user_id)
+    .bind(auth.key_id)
+    .bind(action)
+    .bind(target_type)
+    .bind(target_id)
+    .bind(details)
+    .execute(conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(())
+}
+
+fn validate_package(payload: &PackageRequest) -> Result<(), (StatusCode, String)> {
+    let invalid = |message: &str| Err((StatusCode::BAD_REQUEST, message.to_string()));
+
+    if payload.name.trim().is_empty() {
+        return invalid("name must not be empty");
+    }
+    if payload.price_usdc < Decimal::ZERO || payload.price_usdc.scale() > 2 {
+        return invalid("price_usdc must be a non-negative amount in cents");
+    }
+    if payload.requests_amount <= 0 {
+        return invalid("requests_amount must be positive");
+    }
+    if payload.max_subscriptions.is_some_and(|max| max < 0) {
+        return invalid("max_subscriptions must not be negative");
+    }
+    if payload
+        .discount_percent
+        .is_some_and(|discount| !(0..=100).contains(&discount))
+    {
+        return invalid("discount_percent must be between 0 and 100");
+    }
+    if let (Some(from), Some(until)) = (payload.active_from, payload.active_until) {
+        if from >= until {
+            return invalid("active_from must be before active_until");
+        }
+    }
+    if let Some(pricing) = &payload.pricing {
+        pricing
+            .validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+
+    Ok(())
+}
+
+fn package_write_error(e: sqlx::Error) -> (StatusCode, String) {
+    match e {
+        sqlx::Error::Database(e) if e.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "A package with this name already exists".to_string(),
+        ),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+async fn lock_package(
+    conn: &mut PgConnection,
+    package_id: i32,
+) -> Result<Package, (StatusCode, String)> {
+    sqlx::query_as::<_, Package>("SELECT * FROM packages WHERE id = $1 FOR UPDATE")
+        .bind(package_id)
+        .fetch_optional(conn)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Package not found".to_string()))
+}
+
+/// Every package, including the ones outside their activation window.
+pub async fn list_packages(
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<Package>>, (StatusCode, String)> {
+    sqlx::query_as::<_, Package>("SELECT * FROM packages ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn create_package(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Json(payload): Json<PackageRequest>,
+) -> Result<(StatusCode, Json<Package>), (StatusCode, String)> {
+    validate_package(&payload)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let package = sqlx::query_as::<_, Package>(
+        "INSERT INTO packages
+            (name, price_usdc, requests_amount, description, max_subscriptions, pricing,
+             active_from, active_until, discount_percent)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING *",
+    )
+    .bind(payload.name.trim())
+    .bind(payload.price_usdc)
+    .bind(payload.requests_amount)
+    .bind(&payload.description)
+    .bind(
+        payload
+            .max_subscriptions
+            .unwrap_or(FREE_SUBSCRIPTION_LIMIT as i32),
+    )
+    .bind(payload.pricing.as_ref().map(|pricing| json!(pricing)))
+    .bind(payload.active_from)
+    .bind(payload.active_until)
+    .bind(payload.discount_percent.unwrap_or(0))
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(package_write_error)?;
+
+    audit(
+        &mut tx,
+        &auth,
+        "package.create",
+        "package",
+        &package.id.to_string(),
+        json!({ "package": package }),
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(package)))
+}
+
+/// Replaces every field of a package. Purchases already made keep the
+/// credits they were granted; pending payment intents keep their amount.
+pub async fn replace_package(
+    State(pool): State<PgPool>,
+    Path(package_id): Path<i32>,
+    auth: AuthUser,
+    Json(payload): Json<PackageRequest>,
+) -> Result<Json<Package>, (StatusCode, String)> {
+    validate_package(&payload)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let before = lock_package(&mut tx, package_id).await?;
+
+    let package = sqlx::query_as::<_, Package>(
+        "UPDATE packages
+         SET name = $1, price_usdc = $2, requests_amount = $3, description = $4,
+             max_subscriptions = $5, pricing = $6, active_from = $7, active_until = $8,
+             discount_percent = $9
+         WHERE id = $10
+         RETURNING *",
+    )
+    .bind(payload.name.trim())
+    .bind(payload.price_usdc)
+    .bind(payload.requests_amount)
+    .bind(&payload.description)
+    .bind(
+        payload
+            .max_subscriptions
+            .unwrap_or(FREE_SUBSCRIPTION_LIMIT as i32),
+    )
+    .bind(payload.pricing.as_ref().map(|pricing| json!(pricing)))
+    .bind(payload.active_from)
+    .bind(payload.active_until)
+    .bind(payload.discount_percent.unwrap_or(0))
+    .bind(package_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(package_write_error)?;
+
+    audit(
+        &mut tx,
+        &auth,
+        "package.update",
+        "package",
+        &package_id.to_string(),
+        json!({ "before": before, "after": package }),
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(package))
+}
+
+/// Only packages nobody has bought or started paying for can be deleted;
+/// end the activation window of the others instead.
+pub async fn delete_package(
+    State(pool): State<PgPool>,
+    Path(package_id): Path<i32>,
+    auth: AuthUser,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let package = lock_package(&mut tx, package_id).await?;
+
+    sqlx::query("DELETE FROM packages WHERE id = $1")
+        .bind(package_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => (
+                StatusCode::CONFLICT,
+                "Package has purchases or payment intents; set active_until instead".to_string(),
+            ),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    audit(
+        &mut tx,
+        &auth,
+        "package.delete",
+        "package",
+        &package_id.to_string(),
+        json!({ "package": package }),
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn search_users(
+    State(pool): State<PgPool>,
+    Query(query): Query<UserSearchQuery>,
+) -> Result<Json<Page<AdminUser>>, (StatusCode, String)> {
+    let (page, per_page, offset) = page_bounds(query.page, query.per_page);
+    // Match the text literally, not as a LIKE pattern
+    let pattern = query.query.as_deref().map(|text| {
+        format!(
+            "%{}%",
+            text.trim()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        )
+    });
+
+    let total = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM users WHERE $1::VARCHAR IS NULL OR pubkey ILIKE $1",
+    )
+    .bind(&pattern)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let users = sqlx::query_as::<_, AdminUser>(
+        "SELECT u.id, u.pubkey,
+                COALESCE(c.remaining_requests, 0) AS remaining_requests,
+                (SELECT COUNT(*) FROM api_keys k
+                 WHERE k.user_id = u.id
+                   AND k.revoked_at IS NULL
+                   AND (k.expires_at IS NULL OR k.expires_at > CURRENT_TIMESTAMP)
+                ) AS active_api_keys
+         FROM users u
+         LEFT JOIN credits c ON c.user_id = u.id
+         WHERE $1::VARCHAR IS NULL OR u.pubkey ILIKE $1
+         ORDER BY u.id
+         LIMIT $2 OFFSET $3",
+    )
+    .bind(&pattern)
+    .bind(per_page)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(Page {
+        items: users,
+        total,
+        page,
+        per_page,
+    }))
+}
+
+/// Grants credits or takes them back, recording the reason in the ledger
+/// entry's reference. A debit can't take the balance below zero.
+pub async fn adjust_credits(
+    State(pool): State<PgPool>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+    Json(payload): Json<AdjustCreditsRequest>,
+) -> Result<Json<AdjustCreditsResponse>, (StatusCode, String)> {
+    let reason = payload.reason.trim();
+    if reason.is_empty() || reason.len() > MAX_REASON_LENGTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "reason is required and at most {} characters",
+                MAX_REASON_LENGTH
+            ),
+        ));
+    }
+    if payload.amount == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "amount must not be zero".to_string(),
+        ));
+    }
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+    let kind = if payload.amount > 0 {
+        EntryKind::Grant
+    } else {
+        EntryKind::Adjustment
+    };
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let remaining_requests = ledger::record(
+        &mut tx,
+        user_id,
+        kind,
+        payload.amount,
+        Some(&format!("admin:{}", reason)),
+    )
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(e) if e.is_check_violation() => (
+            StatusCode::CONFLICT,
+            "The user doesn't have that many credits".to_string(),
+        ),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+
+    audit(
+        &mut tx,
+        &auth,
+        "credits.adjust",
+        "user",
+        &pubkey,
+        json!({
+            "kind": kind.as_str(),
+            "amount": payload.amount,
+            "reason": reason,
+            "remaining_requests": remaining_requests,
+        }),
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(AdjustCreditsResponse { remaining_requests }))
+}
+
+pub async fn list_promo_codes(
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<PromoCode>>, (StatusCode, String)> {
+    sqlx::query_as::<_, PromoCode>("SELECT * FROM promo_codes ORDER BY created_at DESC, id DESC")
+        .fetch_all(&pool)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn create_promo_code(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Json(payload): Json<CreatePromoCodeRequest>,
+) -> Result<(StatusCode, Json<PromoCode>), (StatusCode, String)> {
+    let invalid = |message: &str| Err((StatusCode::BAD_REQUEST, message.to_string()));
+
+    let code = normalize_code(&payload.code);
+    if code.is_empty() || code.len() > 32 || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return invalid("code must be 1 to 32 letters or digits");
+    }
+    let Some(kind) = PromoKind::parse(&payload.kind) else {
+        return invalid("kind must be `credits` or `bonus_percent`");
+    };
+    if payload.value <= 0 {
+        return invalid("value must be positive");
+    }
+    if payload.max_redemptions.is_some_and(|max| max <= 0)
+        || payload.per_user_limit.is_some_and(|limit| limit <= 0)
+    {
+        return invalid("max_redemptions and per_user_limit must be positive");
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let promo = sqlx::query_as::<_, PromoCode>(
+        "INSERT INTO promo_codes
+            (code, kind, value, package_id, expires_at, max_redemptions, per_user_limit)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING *",
+    )
+    .bind(&code)
+    .bind(kind.as_str())
+    .bind(payload.value)
+    .bind(payload.package_id)
+    .bind(payload.expires_at)
+    .bind(payload.max_redemptions)
+    .bind(payload.per_user_limit.unwrap_or(1))
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(e) if e.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "A promo code with this code already exists".to_string(),
+        ),
+        sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+            (StatusCode::NOT_FOUND, "Package not found".to_string())
+        }
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+
+    audit(
+        &mut tx,
+        &auth,
+        "promo_code.create",
+        "promo_code",
+        &promo.id.to_string(),
+        json!({ "promo_code": promo }),
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(promo)))
+}
+
+/// Stops a code from being redeemed; past redemptions are kept.
+pub async fn disable_promo_code(
+    State(pool): State<PgPool>,
+    Path(promo_code_id): Path<i32>,
+    auth: AuthUser,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let code = sqlx::query_scalar::<_, String>(
+        "UPDATE promo_codes SET disabled_at = CURRENT_TIMESTAMP
+         WHERE id = $1 AND disabled_at IS NULL
+         RETURNING code",
+    )
+    .bind(promo_code_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Promo code not found".to_string()))?;
+
+    audit(
+        &mut tx,
+        &auth,
+        "promo_code.disable",
+        "promo_code",
+        &promo_code_id.to_string(),
+        json!({ "code": code }),
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Newest first.
+pub async fn get_audit_log(
+    State(pool): State<PgPool>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Page<AuditLogEntry>>, (StatusCode, String)> {
+    let (page, per_page, offset) = page_bounds(query.page, query.per_page);
+
+    let total = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM admin_audit_log
+         WHERE ($1::VARCHAR IS NULL OR target_type = $1)
+           AND ($2::VARCHAR IS NULL OR target_id = $2)",
+    )
+    .bind(&query.target_type)
+    .bind(&query.target_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let entries = sqlx::query_as::<_, AuditLogEntry>(
+        "SELECT a.id, a.admin_user_id, u.pubkey AS admin_pubkey, a.api_key_id, a.action,
+                a.target_type, a.target_id, a.details, a.created_at
+         FROM admin_audit_log a
+         JOIN users u ON u.id = a.admin_user_id
+         WHERE ($1::VARCHAR IS NULL OR a.target_type = $1)
+           AND ($2::VARCHAR IS NULL OR a.target_id = $2)
+         ORDER BY a.created_at DESC, a.id DESC
+         LIMIT $3 OFFSET $4",
+    )
+    .bind(&query.target_type)
+    .bind(&query.target_id)
+    .bind(per_page)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(Page {
+        items: entries,
+        total,
+        page,
+        per_page,
+    }))
+}
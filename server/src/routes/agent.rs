@@ -1,5 +1,10 @@
 use serde::{Deserialize, Serialize};
 use {
+    crate::{
+        config::Config,
+        middlewares::auth::{api_key_from_headers, AuthUser},
+        plans,
+    },
     axum::{
         extract::State,
         http::{HeaderMap, StatusCode},
@@ -8,6 +13,7 @@ use {
     reqwest::Client,
     serde_json::Value,
     sqlx::PgPool,
+    std::sync::Arc,
 };
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -46,35 +52,31 @@ pub struct QueryResult {
     params: Value,
 }
 
-pub async fn fetch_credit_info(
-    pool: &PgPool,
-    api_key: &str,
-) -> Result<(i32, String, i64, String), (StatusCode, String)> {
-    sqlx::query_as::<_, (i32, String, i64, String)>(
-        "SELECT c.user_id, u.pubkey, c.remaining_requests::bigint, c.api_key 
-         FROM credits c 
-         JOIN users u ON u.id = c.user_id 
-         WHERE c.api_key = $1 LIMIT 1",
+/// Remaining credits of the authenticated user.
+pub async fn fetch_credit_info(pool: &PgPool, user_id: i32) -> Result<i64, (StatusCode, String)> {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT remaining_requests::bigint FROM credits WHERE user_id = $1",
     )
-    .bind(api_key)
+    .bind(user_id)
     .fetch_optional(pool)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()))
+    .ok_or((StatusCode::PAYMENT_REQUIRED, "No credits found".to_string()))
 }
 
 pub async fn send_query_request(
+    client: &Client,
+    agent_api_url: &str,
     payload: &mut QueryRequest,
     api_key: &str,
 ) -> Result<QueryResponse, (StatusCode, String)> {
-    let client = Client::new();
     payload.input_user = payload.input_user.to_lowercase();
 
     // Debug print
     println!("Sending payload to AI agent: {:?}", payload);
 
     let response = client
-        .post(format!("{}/query/generate-query", crate::AGENT_API_URL))
+        .post(format!("{}/query/generate-query", agent_api_url))
         .header("x-api-key", api_key)
         .json(payload)
         .send()
@@ -91,16 +93,14 @@ pub async fn send_query_request(
 }
 
 pub async fn send_query_request_report(
+    client: &Client,
+    agent_api_url: &str,
     payload: &mut QueryRequestReport,
     api_key: &str,
 ) -> Result<QueryResponseReport, (StatusCode, String)> {
-    let client = Client::new();
     payload.input_user = payload.input_user.to_lowercase();
     let response = client
-        .post(format!(
-            "{}/query/generate-visualization",
-            crate::AGENT_API_URL
-        ))
+        .post(format!("{}/query/generate-visualization", agent_api_url))
         .header("x-api-key", api_key)
         .json(payload)
         .send()
@@ -115,60 +115,66 @@ pub async fn send_query_request_report(
 
 pub async fn generate_query(
     State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(client): State<Client>,
+    auth: AuthUser,
     headers: HeaderMap,
     Json(mut payload): Json<QueryRequest>,
 ) -> Result<(StatusCode, Json<QueryResponse>), (StatusCode, String)> {
     println!("Generating query");
-    let api_key = headers
-        .get("x-api-key")
-        .and_then(|v| v.to_str().ok())
-        .ok_or((StatusCode::UNAUTHORIZED, "Missing API key".to_string()))?;
-
-    let credit = fetch_credit_info(&pool, api_key).await?;
-
-    if credit.2 < 1 {
-        return Err((
-            StatusCode::PAYMENT_REQUIRED,
-            "Insufficient credits".to_string(),
-        ));
+    // Forwarded to the agent; wallet sessions don't carry one
+    let api_key = api_key_from_headers(&headers).unwrap_or_default();
+
+    // Quota left on an active plan authorizes the query as well as credits
+    let plan_quota =
+        plans::remaining_quota(&pool, auth.user_id, config.credit_sales_credits_per_request)
+            .await?;
+    if plan_quota.unwrap_or(0) < 1 {
+        let remaining_credits = fetch_credit_info(&pool, auth.user_id).await?;
+
+        if remaining_credits < 1 {
+            return Err((
+                StatusCode::PAYMENT_REQUIRED,
+                "Insufficient credits".to_string(),
+            ));
+        }
     }
 
     println!("Sending query request");
-    let query_response = send_query_request(&mut payload, api_key).await?;
+    let query_response =
+        send_query_request(&client, &config.agent_api_url, &mut payload, api_key).await?;
 
     Ok((StatusCode::OK, Json(query_response)))
 }
 
 pub async fn generate_report(
-    State(_pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(client): State<Client>,
     headers: HeaderMap,
     Json(mut payload): Json<QueryRequestReport>,
 ) -> Result<(StatusCode, Json<QueryResponseReport>), (StatusCode, String)> {
-    let _api_key = headers
-        .get("x-api-key")
-        .and_then(|v| v.to_str().ok())
-        .ok_or((StatusCode::UNAUTHORIZED, "Missing API key".to_string()))?;
+    let _api_key = api_key_from_headers(&headers).unwrap_or_default();
 
     println!("Getting user info");
 
-    let query_response = send_query_request_report(&mut payload, _api_key).await?;
+    let query_response =
+        send_query_request_report(&client, &config.agent_api_url, &mut payload, _api_key).await?;
 
     Ok((StatusCode::OK, Json(query_response)))
 }
 
 pub async fn generate_report_service(
-    _pool: PgPool,
+    config: &Config,
+    client: &Client,
     headers: HeaderMap,
     Json(mut payload): Json<QueryRequestReport>,
 ) -> Result<(StatusCode, Json<QueryResponseReport>), (StatusCode, String)> {
-    let _api_key = headers
-        .get("x-api-key")
-        .and_then(|v| v.to_str().ok())
-        .ok_or((StatusCode::UNAUTHORIZED, "Missing API key".to_string()))?;
+    let _api_key = api_key_from_headers(&headers).unwrap_or_default();
 
     println!("Getting user info");
 
-    let query_response = send_query_request_report(&mut payload, _api_key).await?;
+    let query_response =
+        send_query_request_report(client, &config.agent_api_url, &mut payload, _api_key).await?;
 
     Ok((StatusCode::OK, Json(query_response)))
 }
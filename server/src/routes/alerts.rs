@@ -0,0 +1,374 @@
+use {
+    super::{packages::get_user_id, subscriptions::validate_filters},
+    crate::{
+        config::Config,
+        middlewares::auth::{secrets_match, AuthUser},
+        models::{
+            Alert, AlertCondition, AlertEvent, AlertRule, CreateAlertRulePayload, Subscription,
+            DEFAULT_ALERT_COOLDOWN_SECONDS,
+        },
+    },
+    axum::{
+        extract::{Path, State},
+        http::{HeaderMap, StatusCode},
+        Json,
+    },
+    serde::Serialize,
+    serde_json::Value,
+    sqlx::PgPool,
+    std::sync::Arc,
+};
+
+#[derive(Serialize)]
+pub struct IngestAlertEventResponse {
+    pub triggered: usize,
+}
+
+async fn get_user_subscription(
+    pool: &PgPool,
+    pubkey: &str,
+    subscription_id: i32,
+) -> Result<Subscription, (StatusCode, String)> {
+    let user_id = get_user_id(pool, pubkey).await?;
+
+    sqlx::query_as::<_, Subscription>("SELECT * FROM subscriptions WHERE id = $1 AND user_id = $2")
+        .bind(subscription_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Subscription not found".to_string()))
+}
+
+fn validate_condition(
+    condition: &AlertCondition,
+    subscription: &Subscription,
+) -> Result<(), (StatusCode, String)> {
+    match condition {
+        AlertCondition::Transfer { filters } => {
+            if subscription.kind == "new_token" {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Transfer alerts require an account or token subscription".to_string(),
+                ));
+            }
+            validate_filters(Some(Value::Array(filters.clone())))?;
+        }
+        AlertCondition::PriceChange {
+            percent,
+            window_seconds,
+        } => {
+            if subscription.kind != "token_trade" {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Price alerts require a token subscription".to_string(),
+                ));
+            }
+            if *percent == 0.0 || *window_seconds <= 0 {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Price alerts need a non-zero percent and a positive window".to_string(),
+                ));
+            }
+        }
+        AlertCondition::HolderShare { percent } => {
+            if subscription.kind != "token_trade" {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Holder alerts require a token subscription".to_string(),
+                ));
+            }
+            if *percent <= 0.0 || *percent > 100.0 {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Holder share must be between 0 and 100 percent".to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn create_alert_rule(
+    State(pool): State<PgPool>,
+    Path((pubkey, subscription_id)): Path<(String, i32)>,
+    auth: AuthUser,
+    Json(payload): Json<CreateAlertRulePayload>,
+) -> Result<(StatusCode, Json<AlertRule>), (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let subscription = get_user_subscription(&pool, &pubkey, subscription_id).await?;
+    validate_condition(&payload.condition, &subscription)?;
+
+    let cooldown_seconds = payload
+        .cooldown_seconds
+        .unwrap_or(DEFAULT_ALERT_COOLDOWN_SECONDS);
+    if cooldown_seconds < 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Cooldown cannot be negative".to_string(),
+        ));
+    }
+
+    let condition = serde_json::to_value(&payload.condition)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let rule = sqlx::query_as::<_, AlertRule>(
+        "INSERT INTO alert_rules (subscription_id, name, condition, cooldown_seconds)
+         VALUES ($1, $2, $3, $4)
+         RETURNING *",
+    )
+    .bind(subscription.id)
+    .bind(&payload.name)
+    .bind(&condition)
+    .bind(cooldown_seconds)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+pub async fn get_alert_rules(
+    State(pool): State<PgPool>,
+    Path((pubkey, subscription_id)): Path<(String, i32)>,
+    auth: AuthUser,
+) -> Result<Json<Vec<AlertRule>>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let subscription = get_user_subscription(&pool, &pubkey, subscription_id).await?;
+
+    let rules = sqlx::query_as::<_, AlertRule>(
+        "SELECT * FROM alert_rules WHERE subscription_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(subscription.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(rules))
+}
+
+pub async fn delete_alert_rule(
+    State(pool): State<PgPool>,
+    Path((pubkey, subscription_id, rule_id)): Path<(String, i32, i32)>,
+    auth: AuthUser,
+) -> Result<StatusCode, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let subscription = get_user_subscription(&pool, &pubkey, subscription_id).await?;
+
+    let result = sqlx::query("DELETE FROM alert_rules WHERE id = $1 AND subscription_id = $2")
+        .bind(rule_id)
+        .bind(subscription.id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Alert rule not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Latest alerts triggered by any of the user's rules
+pub async fn get_alerts(
+    State(pool): State<PgPool>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+) -> Result<Json<Vec<Alert>>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let alerts = sqlx::query_as::<_, Alert>(
+        "SELECT a.*
+         FROM alerts a
+         JOIN alert_rules r ON r.id = a.rule_id
+         JOIN subscriptions s ON s.id = r.subscription_id
+         WHERE s.user_id = $1
+         ORDER BY a.created_at DESC
+         LIMIT 100",
+    )
+    .bind(user_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(alerts))
+}
+
+/// Receives chain events from the indexer/webhook and evaluates the alert
+/// rules of every active subscription matching the event.
+pub async fn ingest_alert_event(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    headers: HeaderMap,
+    Json(event): Json<AlertEvent>,
+) -> Result<Json<IngestAlertEventResponse>, (StatusCode, String)> {
+    let secret = config.alerts_ingest_secret.as_deref().ok_or((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Alert ingestion secret not configured".to_string(),
+    ))?;
+    let provided = headers
+        .get("x-ingest-secret")
+        .and_then(|v| v.to_str().ok())
+        .ok_or((
+            StatusCode::UNAUTHORIZED,
+            "Missing ingest secret".to_string(),
+        ))?;
+    if !secrets_match(provided, secret) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Invalid ingest secret".to_string(),
+        ));
+    }
+
+    if let AlertEvent::Price {
+        mint,
+        price,
+        observed_at,
+    } = &event
+    {
+        sqlx::query(
+            "INSERT INTO token_prices (mint, price, observed_at) VALUES ($1, $2, $3)
+             ON CONFLICT (mint, observed_at) DO NOTHING",
+        )
+        .bind(mint)
+        .bind(price)
+        .bind(observed_at)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    let (kinds, target_keys): (Vec<&str>, Vec<String>) = event.targets().into_iter().unzip();
+
+    let rules = sqlx::query_as::<_, AlertRule>(
+        "SELECT r.*
+         FROM alert_rules r
+         JOIN subscriptions s ON s.id = r.subscription_id
+         JOIN UNNEST($1::VARCHAR[], $2::VARCHAR[]) AS t(kind, target_key)
+           ON t.kind = s.kind AND t.target_key = s.target_key
+         WHERE NOT s.paused",
+    )
+    .bind(&kinds)
+    .bind(&target_keys)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let dedup_key = event.dedup_key();
+    let mut triggered = 0;
+
+    for rule in rules {
+        let condition = match serde_json::from_value::<AlertCondition>(rule.condition.clone()) {
+            Ok(condition) => condition,
+            Err(e) => {
+                eprintln!(
+                    "Skipping alert rule {} with invalid condition: {}",
+                    rule.id, e
+                );
+                continue;
+            }
+        };
+
+        let reference_price = match (&condition, &event) {
+            (
+                AlertCondition::PriceChange { window_seconds, .. },
+                AlertEvent::Price {
+                    mint, observed_at, ..
+                },
+            ) => get_reference_price(&pool, mint, *observed_at, *window_seconds).await?,
+            _ => None,
+        };
+
+        let Some(payload) = condition.evaluate(&event, reference_price) else {
+            continue;
+        };
+
+        if trigger_alert(&pool, &rule, &dedup_key, &payload).await? {
+            triggered += 1;
+        }
+    }
+
+    Ok(Json(IngestAlertEventResponse { triggered }))
+}
+
+/// Oldest price observed for the mint within the rule window.
+async fn get_reference_price(
+    pool: &PgPool,
+    mint: &str,
+    observed_at: chrono::NaiveDateTime,
+    window_seconds: i64,
+) -> Result<Option<f64>, (StatusCode, String)> {
+    sqlx::query_scalar::<_, f64>(
+        "SELECT price FROM token_prices
+         WHERE mint = $1
+           AND observed_at >= $2 - $3 * INTERVAL '1 second'
+           AND observed_at <= $2
+         ORDER BY observed_at ASC
+         LIMIT 1",
+    )
+    .bind(mint)
+    .bind(observed_at)
+    .bind(window_seconds)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Records the alert unless the rule is cooling down or the same occurrence
+/// was already recorded. Returns whether a new alert was stored.
+async fn trigger_alert(
+    pool: &PgPool,
+    rule: &AlertRule,
+    dedup_key: &str,
+    payload: &Value,
+) -> Result<bool, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Claim the rule; concurrent deliveries race on this row
+    let claimed = sqlx::query_scalar::<_, i32>(
+        "UPDATE alert_rules SET last_triggered_at = CURRENT_TIMESTAMP
+         WHERE id = $1
+           AND (last_triggered_at IS NULL
+                OR last_triggered_at <= CURRENT_TIMESTAMP - cooldown_seconds * INTERVAL '1 second')
+         RETURNING id",
+    )
+    .bind(rule.id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if claimed.is_none() {
+        return Ok(false);
+    }
+
+    let inserted = sqlx::query(
+        "INSERT INTO alerts (rule_id, dedup_key, payload) VALUES ($1, $2, $3)
+         ON CONFLICT (rule_id, dedup_key) DO NOTHING",
+    )
+    .bind(rule.id)
+    .bind(dedup_key)
+    .bind(payload)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // A duplicate delivery must not consume the cooldown, so roll back
+    if inserted.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(true)
+}
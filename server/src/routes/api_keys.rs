@@ -0,0 +1,319 @@
+use {
+    super::packages::get_user_id,
+    crate::{
+        middlewares::auth::{AuthUser, Scope},
+        models::{
+            ApiKey, ApiKeyResponse, CreateApiKeyPayload, CreatedApiKeyResponse,
+            RotateApiKeyPayload, UpdateApiKeyPayload, DEFAULT_ROTATION_GRACE_SECONDS,
+            MAX_API_KEYS_PER_USER, MAX_ROTATION_GRACE_SECONDS,
+        },
+        utils::create_api_key,
+    },
+    axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        Json,
+    },
+    chrono::{NaiveDateTime, Utc},
+    sqlx::{PgConnection, PgPool},
+    std::str::FromStr,
+};
+
+const MAX_LABEL_LENGTH: usize = 64;
+
+fn validate_label(label: Option<&str>) -> Result<(), (StatusCode, String)> {
+    if label.is_some_and(|label| label.len() > MAX_LABEL_LENGTH) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Label must be at most {} characters", MAX_LABEL_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_expiry(expires_at: Option<NaiveDateTime>) -> Result<(), (StatusCode, String)> {
+    if expires_at.is_some_and(|expires_at| expires_at <= Utc::now().naive_utc()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Expiry must be in the future".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Keys can't be granted scopes the requesting key doesn't have.
+fn validate_scopes(
+    auth: &AuthUser,
+    scopes: Option<Vec<String>>,
+) -> Result<Vec<String>, (StatusCode, String)> {
+    let Some(mut scopes) = scopes else {
+        return Ok(auth.scopes.clone());
+    };
+
+    scopes.sort();
+    scopes.dedup();
+
+    if scopes.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "At least one scope is required".to_string(),
+        ));
+    }
+
+    for scope in &scopes {
+        let scope = Scope::from_str(scope).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        if !auth.has_scope(scope) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                format!("Cannot grant the '{}' scope", scope.as_str()),
+            ));
+        }
+    }
+
+    Ok(scopes)
+}
+
+/// Rejects a new key when the user already holds the maximum number of
+/// active ones. `replacing` is a key being rotated out, which doesn't count.
+/// Locks the user row so concurrent requests can't race past the limit.
+async fn check_key_limit(
+    conn: &mut PgConnection,
+    user_id: i32,
+    replacing: Option<i32>,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let active = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM api_keys
+         WHERE user_id = $1
+           AND id IS DISTINCT FROM $2
+           AND revoked_at IS NULL
+           AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)",
+    )
+    .bind(user_id)
+    .bind(replacing)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if active >= MAX_API_KEYS_PER_USER {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!(
+                "A user can hold at most {} active API keys",
+                MAX_API_KEYS_PER_USER
+            ),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn get_api_keys(
+    State(pool): State<PgPool>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+) -> Result<Json<Vec<ApiKeyResponse>>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let keys = sqlx::query_as::<_, ApiKey>(
+        "SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC, id DESC",
+    )
+    .bind(user_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(keys.into_iter().map(ApiKeyResponse::from).collect()))
+}
+
+pub async fn create_user_api_key(
+    State(pool): State<PgPool>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+    Json(payload): Json<CreateApiKeyPayload>,
+) -> Result<(StatusCode, Json<CreatedApiKeyResponse>), (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    validate_label(payload.label.as_deref())?;
+    validate_expiry(payload.expires_at)?;
+    let scopes = validate_scopes(&auth, payload.scopes)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    check_key_limit(&mut tx, user_id, None).await?;
+
+    let (key, api_key) = create_api_key(
+        &mut tx,
+        user_id,
+        payload.label.as_deref(),
+        &scopes,
+        payload.expires_at,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiKeyResponse {
+            api_key,
+            key: key.into(),
+        }),
+    ))
+}
+
+pub async fn update_api_key(
+    State(pool): State<PgPool>,
+    Path((pubkey, key_id)): Path<(String, i32)>,
+    auth: AuthUser,
+    Json(payload): Json<UpdateApiKeyPayload>,
+) -> Result<Json<ApiKeyResponse>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    validate_label(payload.label.as_deref())?;
+    validate_expiry(payload.expires_at)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    sqlx::query_as::<_, ApiKey>(
+        "UPDATE api_keys
+         SET label = COALESCE($1, label), expires_at = COALESCE($2, expires_at)
+         WHERE id = $3 AND user_id = $4 AND revoked_at IS NULL
+         RETURNING *",
+    )
+    .bind(&payload.label)
+    .bind(payload.expires_at)
+    .bind(key_id)
+    .bind(user_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map(|key| Json(key.into()))
+    .ok_or((StatusCode::NOT_FOUND, "API key not found".to_string()))
+}
+
+pub async fn revoke_api_key(
+    State(pool): State<PgPool>,
+    Path((pubkey, key_id)): Path<(String, i32)>,
+    auth: AuthUser,
+) -> Result<StatusCode, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = CURRENT_TIMESTAMP
+         WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(key_id)
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "API key not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Replaces a key with a new one carrying the same label, scopes and expiry.
+/// The old key keeps working for the grace period so clients can switch over.
+pub async fn rotate_api_key(
+    State(pool): State<PgPool>,
+    Path((pubkey, key_id)): Path<(String, i32)>,
+    auth: AuthUser,
+    payload: Option<Json<RotateApiKeyPayload>>,
+) -> Result<(StatusCode, Json<CreatedApiKeyResponse>), (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let Json(payload) = payload.unwrap_or_default();
+    let grace_period_seconds = payload
+        .grace_period_seconds
+        .unwrap_or(DEFAULT_ROTATION_GRACE_SECONDS);
+    if !(0..=MAX_ROTATION_GRACE_SECONDS).contains(&grace_period_seconds) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Grace period must be between 0 and {} seconds",
+                MAX_ROTATION_GRACE_SECONDS
+            ),
+        ));
+    }
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    check_key_limit(&mut tx, user_id, Some(key_id)).await?;
+
+    let old_key = sqlx::query_as::<_, ApiKey>(
+        "SELECT * FROM api_keys
+         WHERE id = $1
+           AND user_id = $2
+           AND revoked_at IS NULL
+           AND replaced_by IS NULL
+           AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+         FOR UPDATE",
+    )
+    .bind(key_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "API key not found".to_string()))?;
+
+    let (new_key, api_key) = create_api_key(
+        &mut tx,
+        user_id,
+        old_key.label.as_deref(),
+        &old_key.scopes,
+        old_key.expires_at,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // LEAST ignores NULL, so keys without expiry get the grace deadline
+    sqlx::query(
+        "UPDATE api_keys
+         SET expires_at = LEAST(expires_at, CURRENT_TIMESTAMP + $1 * INTERVAL '1 second'),
+             replaced_by = $2
+         WHERE id = $3",
+    )
+    .bind(grace_period_seconds)
+    .bind(new_key.id)
+    .bind(old_key.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiKeyResponse {
+            api_key,
+            key: new_key.into(),
+        }),
+    ))
+}
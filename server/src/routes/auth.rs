@@ -0,0 +1,180 @@
+use {
+    crate::{
+        config::Config,
+        middlewares::auth::{hash_token, AuthUser},
+        models::{NonceRequest, NonceResponse, SessionResponse, VerifySignatureRequest},
+        utils::generate_token,
+    },
+    axum::{extract::State, http::StatusCode, Json},
+    chrono::{NaiveDateTime, SecondsFormat, Utc},
+    solana_sdk::{pubkey::Pubkey, signature::Signature},
+    sqlx::PgPool,
+    std::{str::FromStr, sync::Arc},
+};
+
+/// Sign-In With Solana message. The client signs these exact bytes, so the
+/// server stores the message instead of rebuilding it on verification.
+fn sign_in_message(
+    domain: &str,
+    pubkey: &str,
+    nonce: &str,
+    issued_at: NaiveDateTime,
+    expires_at: NaiveDateTime,
+) -> String {
+    format!(
+        "{} wants you to sign in with your Solana account:\n{}\n\nSign in to SWquery.\n\nNonce: {}\nIssued At: {}\nExpiration Time: {}",
+        domain,
+        pubkey,
+        nonce,
+        issued_at.and_utc().to_rfc3339_opts(SecondsFormat::Secs, true),
+        expires_at.and_utc().to_rfc3339_opts(SecondsFormat::Secs, true),
+    )
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<Pubkey, (StatusCode, String)> {
+    Pubkey::from_str(pubkey).map_err(|_| (StatusCode::BAD_REQUEST, "Invalid pubkey".to_string()))
+}
+
+pub async fn request_nonce(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<NonceRequest>,
+) -> Result<Json<NonceResponse>, (StatusCode, String)> {
+    parse_pubkey(&payload.pubkey)?;
+
+    let nonce = generate_token(32);
+    let issued_at = Utc::now().naive_utc();
+    let expires_at = issued_at + chrono::Duration::seconds(config.auth_nonce_ttl_secs as i64);
+    let message = sign_in_message(
+        &config.siws_domain,
+        &payload.pubkey,
+        &nonce,
+        issued_at,
+        expires_at,
+    );
+
+    // Old challenges are only kept around for a day
+    sqlx::query("DELETE FROM auth_nonces WHERE expires_at < CURRENT_TIMESTAMP - INTERVAL '1 day'")
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query(
+        "INSERT INTO auth_nonces (nonce, pubkey, message, created_at, expires_at)
+         VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(&nonce)
+    .bind(&payload.pubkey)
+    .bind(&message)
+    .bind(issued_at)
+    .bind(expires_at)
+    .execute(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(NonceResponse {
+        nonce,
+        message,
+        expires_at,
+    }))
+}
+
+/// Verifies the signed challenge and opens a session for the wallet,
+/// creating the user on first sign-in.
+pub async fn verify_signature(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<VerifySignatureRequest>,
+) -> Result<(StatusCode, Json<SessionResponse>), (StatusCode, String)> {
+    let pubkey = parse_pubkey(&payload.pubkey)?;
+    let signature = Signature::from_str(&payload.signature).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Invalid signature format".to_string(),
+        )
+    })?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Consume the nonce; it is only released again if verification fails
+    let message = sqlx::query_scalar::<_, String>(
+        "UPDATE auth_nonces SET used_at = CURRENT_TIMESTAMP
+         WHERE nonce = $1
+           AND pubkey = $2
+           AND used_at IS NULL
+           AND expires_at > $3
+         RETURNING message",
+    )
+    .bind(&payload.nonce)
+    .bind(&payload.pubkey)
+    .bind(Utc::now().naive_utc())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((
+        StatusCode::UNAUTHORIZED,
+        "Invalid or expired nonce".to_string(),
+    ))?;
+
+    if !signature.verify(pubkey.as_ref(), message.as_bytes()) {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid signature".to_string()));
+    }
+
+    let user_id = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO users (pubkey) VALUES ($1)
+         ON CONFLICT (pubkey) DO UPDATE SET pubkey = EXCLUDED.pubkey
+         RETURNING id",
+    )
+    .bind(&payload.pubkey)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let token = generate_token(48);
+    let expires_at = sqlx::query_scalar::<_, NaiveDateTime>(
+        "INSERT INTO sessions (user_id, token_hash, expires_at)
+         VALUES ($1, $2, CURRENT_TIMESTAMP + $3 * INTERVAL '1 second')
+         RETURNING expires_at",
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(config.session_ttl_secs as i64)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SessionResponse {
+            token,
+            user_id,
+            pubkey: payload.pubkey,
+            expires_at,
+        }),
+    ))
+}
+
+pub async fn logout(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let session_id = auth.session_id.ok_or((
+        StatusCode::BAD_REQUEST,
+        "Only wallet sessions can be signed out".to_string(),
+    ))?;
+
+    sqlx::query("UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(session_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,16 +1,24 @@
 use {
-    super::agent::{generate_report_service, QueryRequestReport},
-    crate::{models::ChatModel, routes::agent::fetch_credit_info},
+    super::agent::{fetch_credit_info, generate_report_service, QueryRequestReport},
+    crate::{
+        config::Config,
+        ledger::{self, Reservation},
+        middlewares::auth::{api_key_from_headers, AuthUser},
+        models::ChatModel,
+        plans::{self, QuotaReservation},
+        pricing::{self, CostBreakdown, Usage},
+    },
     axum::{
         extract::{Path, State},
         http::{HeaderMap, StatusCode},
         Json,
     },
     chrono::NaiveDateTime,
+    reqwest::Client,
     serde::{Deserialize, Serialize, Serializer},
     sqlx::PgPool,
-    std::time::Duration,
-    swquery::{client::Network, SWqueryClient},
+    std::sync::Arc,
+    swquery::SWqueryClient,
 };
 
 fn serialize_naive_date_time<S>(date: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error>
@@ -28,6 +36,7 @@ pub struct GetChatsResponse {
     pub input_user: String,
     pub response: Option<String>,
     pub tokens_used: i64,
+    pub credits_charged: i32,
     #[serde(serialize_with = "serialize_naive_date_time")]
     pub created_at: NaiveDateTime,
 }
@@ -35,16 +44,15 @@ pub struct GetChatsResponse {
 /// Retrieve all chats for a specific user
 pub async fn get_chats_for_user(
     State(pool): State<PgPool>,
-    user_pubkey: String,
+    auth: AuthUser,
 ) -> Result<(StatusCode, Json<Vec<GetChatsResponse>>), (StatusCode, String)> {
     let chats = sqlx::query_as::<_, ChatModel>(
         "SELECT c.* 
          FROM chats c
-         JOIN users u ON u.id = c.user_id
-         WHERE u.pubkey = $1
+         WHERE c.user_id = $1
          ORDER BY c.created_at DESC",
     )
-    .bind(user_pubkey)
+    .bind(auth.user_id)
     .fetch_all(&pool)
     .await
     .map_err(|e| {
@@ -63,6 +71,7 @@ pub async fn get_chats_for_user(
             input_user: chat.input_user,
             response: chat.response,
             tokens_used: chat.tokens_used,
+            credits_charged: chat.credits_charged,
             created_at: chat.created_at,
         })
         .collect();
@@ -76,20 +85,27 @@ pub struct ChatDetailsResponse {
     pub input_user: String,
     pub response: Option<String>,
     pub tokens_used: i64,
+    pub intent: Option<String>,
+    pub credits_charged: i32,
+    pub cost_breakdown: Option<serde_json::Value>,
     pub created_at: NaiveDateTime,
 }
 
 /// Retrieve details of a specific chat
 pub async fn get_chat_by_id(
     State(pool): State<PgPool>,
+    auth: AuthUser,
     Path(chat_id): Path<i32>, // Extracts the chat ID from the path
 ) -> Result<(StatusCode, Json<ChatDetailsResponse>), (StatusCode, String)> {
     let chat = sqlx::query_as::<_, ChatModel>(
-        "SELECT id, input_user, response, tokens_used, created_at 
+        "SELECT id, user_id, input_user, response, tokens_used, created_at,
+                intent, credits_charged, cost_breakdown
          FROM chats 
-         WHERE id = $1",
+         WHERE id = $1 AND (user_id = $2 OR $3)",
     )
     .bind(chat_id)
+    .bind(auth.user_id)
+    .bind(auth.is_admin())
     .fetch_optional(&pool)
     .await
     .map_err(|e| {
@@ -108,6 +124,9 @@ pub async fn get_chat_by_id(
                 input_user: chat.input_user,
                 response: chat.response,
                 tokens_used: chat.tokens_used,
+                intent: chat.intent,
+                credits_charged: chat.credits_charged,
+                cost_breakdown: chat.cost_breakdown,
                 created_at: chat.created_at,
             }),
         ))
@@ -125,6 +144,12 @@ pub struct ChatRequest {
 #[derive(Serialize)]
 pub struct ChatResponse {
     pub credits: i64,
+    pub credits_charged: i32,
+    /// Requests taken from the active plan's quota instead of the credits
+    pub plan_requests_charged: i32,
+    /// Quota left in the plan's current window, if a plan paid for the chat
+    pub plan_requests_remaining: Option<i32>,
+    pub cost: CostBreakdown,
     pub response: serde_json::Value,
     pub metadata: Option<serde_json::Value>,
     pub report: String,
@@ -133,41 +158,143 @@ pub struct ChatResponse {
 
 pub async fn chatbot_interact(
     State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(http): State<Client>,
+    State(swquery): State<SWqueryClient>,
+    auth: AuthUser,
     headers: HeaderMap,
     Json(payload): Json<ChatRequest>,
 ) -> Result<(StatusCode, Json<ChatResponse>), (StatusCode, String)> {
     println!("Chat request: {:#?}", payload);
 
-    let api_key = headers
-        .get("x-api-key")
-        .and_then(|v| v.to_str().ok())
-        .ok_or((StatusCode::UNAUTHORIZED, "Missing API key".to_string()))?;
-
-    // Fetch credit info first to verify user has access
-    let (status, credit, remaining_credits, api_key_str) =
-        fetch_credit_info(&pool, api_key).await?;
-
-    // Load environment variables
-    let helius_api_key = std::env::var("HELIUS_API_KEY").map_err(|_| {
-        (
+    if config.helius_api_key.is_none() {
+        return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             "Helius API key not configured".to_string(),
-        )
-    })?;
+        ));
+    }
 
-    let openai_api_key = std::env::var("OPENAI_API_KEY").map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "OpenAI API key not configured".to_string(),
+    let openai_api_key = config.openai_api_key.clone().ok_or((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "OpenAI API key not configured".to_string(),
+    ))?;
+
+    let rules = pricing::rules_for_user(&pool, auth.user_id).await?;
+
+    // The price is only known afterwards, so hold up to the maximum charge,
+    // but at least what the cheapest chat costs; nothing is spent unless the
+    // whole chat succeeds. With less than the maximum left, the hold is
+    // partial and the chat is charged at most what was held, so users can
+    // spend their last credits. An active plan's quota is used before the
+    // credit balance.
+    let hold = match plans::reserve_quota(
+        &pool,
+        auth.user_id,
+        config.credit_sales_credits_per_request,
+        rules.min_charge(),
+        rules.max_charge,
+    )
+    .await?
+    {
+        Some(quota) => Hold::Plan(quota),
+        None => Hold::Credits(
+            ledger::reserve_up_to(
+                &pool,
+                auth.user_id,
+                rules.min_charge(),
+                rules.max_charge,
+                "chatbot_interact",
+            )
+            .await?,
+        ),
+    };
+
+    let result = async {
+        let (response, usage) =
+            interact(&config, &http, &swquery, headers, &payload, openai_api_key).await?;
+        let cost = rules.price(&usage);
+        let charged = cost.total.min(hold.amount());
+        record_chat(
+            &pool,
+            auth.user_id,
+            &payload.input_user,
+            &response,
+            &usage,
+            &cost,
+            charged,
+            matches!(hold, Hold::Plan(_)),
         )
-    })?;
+        .await?;
+        Ok::<_, (StatusCode, String)>((response, cost, charged))
+    }
+    .await;
 
-    let swquery_client = SWqueryClient::new(
-        api_key.to_string(),
-        Some(helius_api_key),
-        Some(Duration::from_secs(30)),
-        Some(Network::Mainnet),
-    );
+    match result {
+        Ok((mut response, cost, charged)) => {
+            match &hold {
+                Hold::Credits(reservation) => {
+                    let charged = ledger::capture(&pool, reservation, charged).await?;
+                    response.credits = reservation.balance + (reservation.amount - charged) as i64;
+                    response.credits_charged = charged;
+                }
+                Hold::Plan(quota) => {
+                    let charged = plans::capture_quota(&pool, quota, charged).await?;
+                    // Plan holders may never have bought credits
+                    response.credits = fetch_credit_info(&pool, auth.user_id).await.unwrap_or(0);
+                    response.plan_requests_charged = charged;
+                    response.plan_requests_remaining =
+                        Some(quota.remaining + quota.amount - charged);
+                }
+            }
+            response.cost = cost;
+            Ok((StatusCode::OK, Json(response)))
+        }
+        Err(e) => {
+            if let Err(refund_error) = hold.release(&pool).await {
+                eprintln!("Failed to release {:?}: {:?}", hold, refund_error);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// What pays for a chat while it runs.
+#[derive(Debug)]
+enum Hold {
+    Credits(Reservation),
+    Plan(QuotaReservation),
+}
+
+impl Hold {
+    fn amount(&self) -> i32 {
+        match self {
+            Self::Credits(reservation) => reservation.amount,
+            Self::Plan(quota) => quota.amount,
+        }
+    }
+
+    /// Returns everything held after the chat failed.
+    async fn release(&self, pool: &PgPool) -> Result<(), (StatusCode, String)> {
+        match self {
+            Self::Credits(reservation) => ledger::refund(pool, reservation).await,
+            Self::Plan(quota) => plans::release_quota(pool, quota, quota.amount).await,
+        }
+    }
+}
+
+/// Runs the query and the report, returning what they consumed.
+async fn interact(
+    config: &Config,
+    http: &Client,
+    swquery: &SWqueryClient,
+    headers: HeaderMap,
+    payload: &ChatRequest,
+    openai_api_key: String,
+) -> Result<(ChatResponse, Usage), (StatusCode, String)> {
+    // Forwarded to the agent; wallet sessions don't carry one
+    let api_key = api_key_from_headers(&headers).unwrap_or_default();
+
+    let swquery_client = swquery.with_api_key(api_key);
 
     let query_result = swquery_client
         .query(&payload.input_user, &payload.address)
@@ -180,67 +307,71 @@ pub async fn chatbot_interact(
             )
         })?;
 
-    let user_id = sqlx::query_scalar::<_, i32>("SELECT id FROM users WHERE pubkey = $1")
-        .bind(&payload.address)
-        .fetch_one(&pool)
-        .await
-        .map_err(|e| {
-            eprintln!("Database error: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to get user ID".to_string(),
-            )
-        })?;
-
-    // Deduct credits here
-    sqlx::query(
-        "UPDATE credits SET remaining_requests = remaining_requests - 1 WHERE user_id = $1",
-    )
-    .bind(user_id)
-    .execute(&pool)
-    .await
-    .map_err(|e| {
-        eprintln!("Database error: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to deduct credits".to_string(),
-        )
-    })?;
-
     let metadata = query_result.response.get("metadata").cloned();
 
     let report_input = QueryRequestReport {
         input_user: query_result.response.clone().to_string(),
         address: payload.address.clone(),
         chatted: payload.input_user.clone(),
-        openai_key: openai_api_key.clone(),
+        openai_key: openai_api_key,
     };
 
-    let report = generate_report_service(pool.clone(), headers, axum::Json(report_input)).await?;
+    let (_, Json(report)) =
+        generate_report_service(config, http, headers, axum::Json(report_input)).await?;
 
-    // Get User ID
-    let user_id = sqlx::query_scalar::<_, i32>("SELECT id FROM users WHERE pubkey = $1")
-        .bind(&payload.address)
-        .fetch_one(&pool)
-        .await
-        .map_err(|e| {
-            eprintln!("Database error: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to get user ID".to_string(),
-            )
-        })?;
+    let usage = Usage {
+        items: pricing::hydrated_items(&query_result.response),
+        intent: query_result.intent,
+        query_tokens: query_result.tokens,
+        report_tokens: report.tokens,
+    };
+
+    Ok((
+        ChatResponse {
+            // Set by the caller once the credits are captured
+            credits: 0,
+            credits_charged: 0,
+            plan_requests_charged: 0,
+            plan_requests_remaining: None,
+            cost: CostBreakdown::default(),
+            response: query_result.response,
+            response_type: query_result.response_type,
+            metadata,
+            report: report.result,
+        },
+        usage,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn record_chat(
+    pool: &PgPool,
+    user_id: i32,
+    input_user: &str,
+    response: &ChatResponse,
+    usage: &Usage,
+    cost: &CostBreakdown,
+    charged: i32,
+    charged_to_plan: bool,
+) -> Result<(), (StatusCode, String)> {
+    let cost_breakdown = serde_json::to_value(cost)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    // Create a chat record
     sqlx::query(
-        "INSERT INTO chats (user_id, input_user, response, tokens_used) 
-         VALUES ($1, $2, $3, $4) RETURNING id",
+        "INSERT INTO chats
+            (user_id, input_user, response, tokens_used, intent, credits_charged, cost_breakdown,
+             charged_to_plan)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
     )
     .bind(user_id)
-    .bind(&payload.input_user)
-    .bind(query_result.response.to_string())
-    .bind(1000)
-    .execute(&pool)
+    .bind(input_user)
+    .bind(response.response.to_string())
+    .bind(usage.query_tokens + usage.report_tokens)
+    .bind(&usage.intent)
+    .bind(charged)
+    .bind(cost_breakdown)
+    .bind(charged_to_plan)
+    .execute(pool)
     .await
     .map_err(|e| {
         eprintln!("Database error: {}", e);
@@ -250,14 +381,5 @@ pub async fn chatbot_interact(
         )
     })?;
 
-    Ok((
-        StatusCode::OK,
-        Json(ChatResponse {
-            credits: remaining_credits,
-            response: query_result.response,
-            response_type: query_result.response_type,
-            metadata,
-            report: report.1.result.clone(),
-        }),
-    ))
+    Ok(())
 }
@@ -1,5 +1,8 @@
 use {
-    crate::models::{CreditModel, User},
+    crate::{
+        ledger::{self, EntryKind},
+        models::User,
+    },
     axum::{extract::State, http::StatusCode, Json},
     serde::{Deserialize, Serialize},
     sqlx::PgPool,
@@ -30,7 +33,7 @@ pub async fn buy_credits(
     Json(payload): Json<BuyCredits>,
 ) -> Result<(StatusCode, Json<CreditResponse>), (StatusCode, String)> {
     // let user = sqlx::query_as::<_, User>("SELECT id, pubkey, pump_portal_payload FROM users WHERE pubkey = $1")
-    let user = sqlx::query_as::<_, User>("SELECT id, pubkey, subscriptions FROM users WHERE pubkey = $1")
+    let user = sqlx::query_as::<_, User>("SELECT id, pubkey FROM users WHERE pubkey = $1")
         .bind(&payload.user_pubkey)
         .fetch_optional(&pool)
         .await
@@ -41,14 +44,14 @@ pub async fn buy_credits(
 
     match user {
         Some(user) => {
-            let api_key = crate::utils::generate_api_key();
-            match update_or_insert_credits(&pool, user.id, payload.amount, &api_key).await {
+            match update_or_insert_credits(&pool, user.id, payload.amount).await {
                 Ok(credit) => Ok((
                     StatusCode::CREATED,
                     Json(CreditResponse {
                         user_pubkey: payload.user_pubkey,
-                        new_balance: credit.balance,
-                        api_key: Some(api_key),
+                        new_balance: credit,
+                        // Keys are issued through the `/users/:pubkey/api-keys` routes
+                        api_key: None,
                     }),
                 )),
                 Err(e) => Err(e),
@@ -69,30 +72,32 @@ async fn update_or_insert_credits(
     pool: &PgPool,
     user_id: i32,
     amount: i64,
-    api_key: &str,
-) -> Result<CreditModel, (StatusCode, String)> {
-    let credit = sqlx::query_as::<_, CreditModel>(
-        "INSERT INTO credits (user_id, balance, api_key) 
-         VALUES ($1, $2, $3)
-         ON CONFLICT (user_id) 
-         DO UPDATE SET balance = credits.balance + EXCLUDED.balance,
-                      api_key = COALESCE(credits.api_key, EXCLUDED.api_key)
-         RETURNING *",
-    )
-    .bind(user_id)
-    .bind(amount)
-    .bind(api_key)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| {
-        eprintln!("Credits operation error: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update credits".to_string(),
-        )
-    })?;
-
-    Ok(credit)
+) -> Result<i64, (StatusCode, String)> {
+    let amount = i32::try_from(amount)
+        .ok()
+        .filter(|amount| *amount > 0)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid amount".to_string()))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let balance = ledger::record(&mut tx, user_id, EntryKind::Grant, amount, Some("buy_credits"))
+        .await
+        .map_err(|e| {
+            eprintln!("Credits operation error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to update credits".to_string(),
+            )
+        })?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(balance)
 }
 
 #[derive(Deserialize)]
@@ -114,7 +119,7 @@ pub async fn refund_credits(
     let mut balance = 0;
 
     if let Some(user) = user {
-        match update_credit_balance(&pool, user.id as i64, payload.amount).await {
+        match update_credit_balance(&pool, user.id, payload.amount).await {
             Ok(new_balance) => balance = new_balance,
             Err(e) => return Err(e),
         }
@@ -131,32 +136,48 @@ pub async fn refund_credits(
 
 async fn update_credit_balance(
     pool: &PgPool,
-    user_id: i64,
+    user_id: i32,
     amount: i64,
 ) -> Result<i64, (StatusCode, String)> {
-    let credit =
-        sqlx::query_as::<_, CreditModel>("SELECT * FROM credits WHERE user_id = $1 FOR UPDATE")
-            .bind(user_id)
-            .fetch_optional(pool)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if let Some(credit) = credit {
-        if credit.balance < amount {
-            return Err((StatusCode::BAD_REQUEST, "Insufficient balance".to_string()));
-        }
+    let amount = i32::try_from(amount)
+        .ok()
+        .filter(|amount| *amount > 0)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid amount".to_string()))?;
 
-        let credit = sqlx::query_as::<_, CreditModel>(
-            "UPDATE credits SET balance = balance - $1 WHERE user_id = $2 RETURNING *",
-        )
-        .bind(amount)
-        .bind(user_id)
-        .fetch_one(pool)
+    let mut tx = pool
+        .begin()
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-        return Ok(credit.balance);
+    let balance = sqlx::query_scalar::<_, i32>(
+        "SELECT remaining_requests FROM credits WHERE user_id = $1 FOR UPDATE",
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let Some(balance) = balance else {
+        return Ok(0);
+    };
+
+    if balance < amount {
+        return Err((StatusCode::BAD_REQUEST, "Insufficient balance".to_string()));
     }
 
-    Ok(0)
+    let balance = ledger::record(
+        &mut tx,
+        user_id,
+        EntryKind::Adjustment,
+        -amount,
+        Some("refund_credits"),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(balance)
 }
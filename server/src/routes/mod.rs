@@ -1,7 +1,14 @@
+pub mod admin;
 pub mod agent;
+pub mod alerts;
+pub mod api_keys;
+pub mod auth;
 pub mod chatbot;
 pub mod credits;
 pub mod users;
 pub mod packages;
+pub mod payment_intents;
+pub mod promotions;
 pub mod token;
-pub mod social;
\ No newline at end of file
+pub mod social;
+pub mod subscriptions;
\ No newline at end of file
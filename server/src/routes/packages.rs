@@ -1,25 +1,33 @@
 use {
-    crate::models::{
-        Package, PackageResponse, VerifyTransactionRequest, VerifyTransactionResponse, GetUserUsageRequest, GetUserUsageResponse, ChatQuantity
+    crate::{
+        config::Config,
+        ledger::{self, EntryKind},
+        middlewares::auth::AuthUser,
+        models::{
+            Package, PackageResponse, VerifyTransactionRequest, ACTIVE_PACKAGE, VerifyTransactionResponse, GetUserUsageRequest, GetUserUsageResponse, ChatQuantity
+        },
+        payments::{self, ExpectedPayment},
+        promotions,
     },
-    axum::{extract::State, http::{StatusCode, HeaderMap}, Json, extract::Path},
-    rust_decimal::Decimal as DecimalType,
-    solana_client::rpc_client::RpcClient,
+    axum::{extract::State, http::StatusCode, Json, extract::Path},
+    solana_client::nonblocking::rpc_client::RpcClient,
     solana_sdk::{pubkey::Pubkey, signature::Signature},
-    solana_transaction_status::UiTransactionEncoding,
     sqlx::PgPool,
     std::str::FromStr,
-    std::time::Duration,
+    std::sync::Arc,
 };
 
 pub async fn get_packages(
     Path(pubkey): Path<String>,
     State(pool): State<PgPool>,
 ) -> Result<Json<Vec<PackageResponse>>, (StatusCode, String)> {
-    let mut packages = sqlx::query_as::<_, Package>("SELECT * FROM packages")
-        .fetch_all(&pool)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut packages = sqlx::query_as::<_, Package>(&format!(
+        "SELECT * FROM packages WHERE {} ORDER BY id",
+        ACTIVE_PACKAGE
+    ))
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // If this is the free trial package, check if user has any transactions
     if packages.iter().any(|p| p.name == "Free Trial") {
@@ -50,13 +58,7 @@ pub async fn get_packages(
 
     // Convert Package to PackageResponse
     let package_responses = packages.into_iter()
-        .map(|p| PackageResponse {
-            id: p.id,
-            name: p.name,
-            description: p.description,
-            price_usdc: p.price_usdc,
-            requests_amount: p.requests_amount,
-        })
+        .map(PackageResponse::from)
         .collect::<Vec<PackageResponse>>();
 
     Ok(Json(package_responses))
@@ -64,16 +66,34 @@ pub async fn get_packages(
 
 pub async fn verify_transaction(
     State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(rpc_client): State<Arc<RpcClient>>,
     Json(payload): Json<VerifyTransactionRequest>,
 ) -> Result<(StatusCode, Json<VerifyTransactionResponse>), (StatusCode, String)> {
-    // Get package details
-    let package = get_package(&pool, payload.package_id).await?;
+    // Get package details. What a windowed or discounted package cost depends
+    // on when it was bought, so those are only sold through payment intents,
+    // which fix the amount up front.
+    let package = find_package(&pool, payload.package_id).await?;
+    if package.has_variable_price() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "This package can only be bought through a payment intent".to_string(),
+        ));
+    }
 
     // Get user ID
     let user_id = get_user_id(&pool, &payload.user_pubkey).await?;
 
     // Process the transaction
-    process_transaction(&pool, user_id, &package, &payload.signature).await?;
+    let bonus_requests = process_transaction(
+        &pool,
+        &config,
+        &rpc_client,
+        user_id,
+        &package,
+        &payload,
+    )
+    .await?;
 
     // Get updated credit balance
     let remaining_requests =
@@ -90,19 +110,35 @@ pub async fn verify_transaction(
                 .to_string(),
             remaining_requests: remaining_requests as i64,
             package_requests: package.requests_amount,
+            bonus_requests,
         }),
     ))
 }
 
-async fn get_package(pool: &PgPool, package_id: i32) -> Result<Package, (StatusCode, String)> {
+/// Looks up a package that is on sale right now.
+pub async fn get_package(pool: &PgPool, package_id: i32) -> Result<Package, (StatusCode, String)> {
+    sqlx::query_as::<_, Package>(&format!(
+        "SELECT * FROM packages WHERE id = $1 AND {}",
+        ACTIVE_PACKAGE
+    ))
+    .bind(package_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Package not available".to_string()))
+}
+
+/// Looks up a package whether or not it is on sale.
+async fn find_package(pool: &PgPool, package_id: i32) -> Result<Package, (StatusCode, String)> {
     sqlx::query_as::<_, Package>("SELECT * FROM packages WHERE id = $1")
         .bind(package_id)
-        .fetch_one(pool)
+        .fetch_optional(pool)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Package not found".to_string()))
 }
 
-async fn get_user_id(pool: &PgPool, pubkey: &str) -> Result<i32, (StatusCode, String)> {
+pub async fn get_user_id(pool: &PgPool, pubkey: &str) -> Result<i32, (StatusCode, String)> {
     sqlx::query_scalar::<_, i32>("SELECT id FROM users WHERE pubkey = $1")
         .bind(pubkey)
         .fetch_optional(pool)
@@ -111,12 +147,19 @@ async fn get_user_id(pool: &PgPool, pubkey: &str) -> Result<i32, (StatusCode, St
         .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))
 }
 
+/// Verifies and records the purchase, returning the credits granted by the
+/// promo code. A rejected code fails the whole purchase, which can then be
+/// verified again without it.
 async fn process_transaction(
     pool: &PgPool,
+    config: &Config,
+    rpc_client: &RpcClient,
     user_id: i32,
     package: &Package,
-    signature: &str,
-) -> Result<(), (StatusCode, String)> {
+    payload: &VerifyTransactionRequest,
+) -> Result<i32, (StatusCode, String)> {
+    let signature = payload.signature.as_str();
+
     // Check if transaction already exists
     let existing_tx = sqlx::query_scalar::<_, bool>(
         "SELECT EXISTS(SELECT 1 FROM transactions WHERE signature = $1)",
@@ -134,29 +177,33 @@ async fn process_transaction(
     }
 
     // Verify the transaction first
-    let is_valid = verify_solana_transaction(signature, package.price_usdc).await?;
-    if !is_valid {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Invalid transaction amount".to_string(),
-        ));
-    }
+    let expected = ExpectedPayment {
+        signature: Signature::from_str(signature).map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Invalid signature format".to_string(),
+            )
+        })?,
+        payer: Pubkey::from_str(&payload.user_pubkey)
+            .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid pubkey".to_string()))?,
+        recipient: config.recipient_wallet,
+        mint: config.usdc_mint,
+        amount: package.effective_price(),
+    };
+    payments::fetch_and_verify(rpc_client, &expected).await?;
 
     let mut tx = pool
         .begin()
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    // Insert or update credits record
-    sqlx::query(
-        "INSERT INTO credits (user_id, remaining_requests) 
-         VALUES ($1, $2) 
-         ON CONFLICT (user_id) 
-         DO UPDATE SET remaining_requests = credits.remaining_requests + $2",
+    ledger::record(
+        &mut tx,
+        user_id,
+        EntryKind::Purchase,
+        package.requests_amount,
+        Some(signature),
     )
-    .bind(user_id)
-    .bind(package.requests_amount)
-    .execute(&mut *tx)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
@@ -170,130 +217,39 @@ async fn process_transaction(
     .bind("completed")
     .execute(&mut *tx)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    tx.commit()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    Ok(())
-}
-
-async fn verify_solana_transaction(
-    signature: &str,
-    expected_amount: DecimalType,
-) -> Result<bool, (StatusCode, String)> {
-    const MAX_RETRIES: u32 = 20;
-    const RETRY_DELAY_MS: u64 = 2000; // 2 seconds
-
-    // Parse the signature
-    let signature = Signature::from_str(signature).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            "Invalid signature format".to_string(),
-        )
+    .map_err(|e| match e {
+        // A concurrent request verified the same signature first
+        sqlx::Error::Database(e) if e.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "This transaction has already been processed".to_string(),
+        ),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
     })?;
 
-    // Create RPC client
-    let rpc_client = RpcClient::new(format!(
-        "https://mainnet.helius-rpc.com/?api-key={}",
-        std::env::var("TEST_HELIUS_API_KEY").unwrap_or_default()
-    ));
-
-    // The official SWQuery USDC receiving wallet
-    let expected_recipient = Pubkey::from_str("BXVjUeXZ5GgbPvqCsUXdGz2G7zsg436GctEC3HkNLABK")
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Invalid recipient address".to_string(),
-            )
-        })?;
-
-    let usdc_mint =
-        Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Invalid USDC mint".to_string(),
-            )
-        })?;
-
-    for attempt in 0..MAX_RETRIES {
-        match rpc_client.get_transaction(&signature, UiTransactionEncoding::Json) {
-            Ok(transaction) => {
-                if let Some(meta) = transaction.transaction.meta {
-                    let pre_balances = meta.pre_token_balances.unwrap();
-                    let post_balances = meta.post_token_balances.unwrap();
-
-                    // Find the recipient's balance change
-                    for (pre, post) in pre_balances.iter().zip(post_balances.iter()) {
-                        let (mint, owner) = (&pre.mint, &post.owner);
-                        if mint == &usdc_mint.to_string()
-                            && owner.clone().unwrap() == expected_recipient.to_string()
-                        {
-                            let pre_amount = pre.ui_token_amount.ui_amount.unwrap_or(0.0);
-                            let post_amount = post.ui_token_amount.ui_amount.unwrap_or(0.0);
-                            let amount_change = (post_amount - pre_amount).abs();
-
-                            println!("Found transfer to recipient: {} USDC", amount_change);
-                            println!("Expected: {} USDC", expected_amount);
-                            println!("Recipient: {:?}", owner);
-
-                            // Compare with expected amount with some tolerance
-                            if (rust_decimal::Decimal::try_from(amount_change).unwrap()
-                                - expected_amount)
-                                .abs()
-                                < rust_decimal::Decimal::new(1, 2)
-                            {
-                                return Ok(true);
-                            }
-                        }
-                    }
-                }
-
-                // If we found the transaction but amounts don't match
-                if attempt == MAX_RETRIES - 1 {
-                    return Ok(false);
-                }
-            }
-            Err(e) => {
-                println!(
-                    "Attempt {}: Failed to fetch transaction: {}",
-                    attempt + 1,
-                    e
-                );
-                if attempt == MAX_RETRIES - 1 {
-                    return Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Failed to fetch transaction after {} attempts", MAX_RETRIES),
-                    ));
-                }
-            }
+    let bonus_requests = match payload.promo_code.as_deref() {
+        Some(code) => {
+            promotions::redeem_promo_code(&mut tx, user_id, code, Some((package, signature)))
+                .await?
         }
+        None => 0,
+    };
 
-        // Wait before retrying
-        tokio::time::sleep(Duration::from_millis(RETRY_DELAY_MS)).await;
-    }
+    promotions::reward_referral(&mut tx, config, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(false)
+    Ok(bonus_requests)
 }
 
 pub async fn get_user_usage(
     State(pool): State<PgPool>,
-    headers: HeaderMap,
+    auth: AuthUser,
 ) -> Result<Json<GetUserUsageResponse>, (StatusCode, String)> {
-    let api_key = headers
-        .get("x-api-key")
-        .and_then(|v| v.to_str().ok())
-        .ok_or((StatusCode::UNAUTHORIZED, "Missing API key".to_string()))?;
-
-    // Get user ID via API key
-    let user_id = sqlx::query_scalar::<_, i32>(
-        "SELECT user_id FROM credits WHERE api_key = $1"
-    ).bind(api_key)
-    .fetch_optional(&pool)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()))?;
+    let user_id = auth.user_id;
 
     let remaining_requests = sqlx::query_scalar::<_, i32>(
         "SELECT remaining_requests FROM credits WHERE user_id = $1"
@@ -0,0 +1,178 @@
+use {
+    super::packages::{get_package, get_user_id},
+    crate::{
+        config::Config,
+        middlewares::auth::AuthUser,
+        models::{CreatePaymentIntentRequest, PaymentIntent, PaymentIntentResponse},
+    },
+    axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        Json,
+    },
+    rust_decimal::Decimal,
+    solana_sdk::signature::{Keypair, Signer},
+    sqlx::PgPool,
+    std::sync::Arc,
+};
+
+/// Pending intents a user can hold at once; each one is polled on-chain.
+const MAX_PENDING_INTENTS: i64 = 5;
+
+fn intent_response(intent: PaymentIntent, config: &Config) -> PaymentIntentResponse {
+    let recipient = config.recipient_wallet.to_string();
+    let mint = config.usdc_mint.to_string();
+    let payment_url = format!(
+        "solana:{}?amount={}&spl-token={}&reference={}&label=SWquery",
+        recipient,
+        intent.amount_usdc.normalize(),
+        mint,
+        intent.reference
+    );
+
+    PaymentIntentResponse {
+        id: intent.id,
+        package_id: intent.package_id,
+        amount_usdc: intent.amount_usdc,
+        recipient,
+        mint,
+        reference: intent.reference,
+        payment_url,
+        status: intent.status,
+        signature: intent.signature,
+        failure_reason: intent.failure_reason,
+        created_at: intent.created_at,
+        expires_at: intent.expires_at,
+        completed_at: intent.completed_at,
+    }
+}
+
+/// Returns immediately with the transfer to make; credits are granted by the
+/// background watcher once the transfer is finalized.
+pub async fn create_payment_intent(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+    Json(payload): Json<CreatePaymentIntentRequest>,
+) -> Result<(StatusCode, Json<PaymentIntentResponse>), (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let package = get_package(&pool, payload.package_id).await?;
+    // The amount is fixed now, so a discount ending mid-payment still applies
+    let amount_usdc = package.effective_price();
+
+    if amount_usdc <= Decimal::ZERO {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "This package can't be purchased".to_string(),
+        ));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Serialize intent creation per user so the limit can't be raced past
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let pending = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM payment_intents WHERE user_id = $1 AND status = 'pending'",
+    )
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if pending >= MAX_PENDING_INTENTS {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            format!(
+                "A user can hold at most {} pending payment intents",
+                MAX_PENDING_INTENTS
+            ),
+        ));
+    }
+
+    // A fresh random key nobody else can have used in a transfer
+    let reference = Keypair::new().pubkey().to_string();
+
+    let intent = sqlx::query_as::<_, PaymentIntent>(
+        "INSERT INTO payment_intents (user_id, package_id, amount_usdc, reference, expires_at)
+         VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP + $5 * INTERVAL '1 second')
+         RETURNING *",
+    )
+    .bind(user_id)
+    .bind(package.id)
+    .bind(amount_usdc)
+    .bind(&reference)
+    .bind(config.payment_intent_ttl_secs as i64)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(intent_response(intent, &config))))
+}
+
+pub async fn get_payment_intents(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+) -> Result<Json<Vec<PaymentIntentResponse>>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let intents = sqlx::query_as::<_, PaymentIntent>(
+        "SELECT * FROM payment_intents WHERE user_id = $1 ORDER BY created_at DESC, id DESC",
+    )
+    .bind(user_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(
+        intents
+            .into_iter()
+            .map(|intent| intent_response(intent, &config))
+            .collect(),
+    ))
+}
+
+/// Polled by the client until `status` leaves `pending`.
+pub async fn get_payment_intent(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path((pubkey, intent_id)): Path<(String, i32)>,
+    auth: AuthUser,
+) -> Result<Json<PaymentIntentResponse>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    sqlx::query_as::<_, PaymentIntent>(
+        "SELECT * FROM payment_intents WHERE id = $1 AND user_id = $2",
+    )
+    .bind(intent_id)
+    .bind(user_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map(|intent| Json(intent_response(intent, &config)))
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Payment intent not found".to_string(),
+    ))
+}
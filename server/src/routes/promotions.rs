@@ -0,0 +1,179 @@
+use {
+    super::packages::get_user_id,
+    crate::{
+        config::Config,
+        middlewares::auth::AuthUser,
+        models::{
+            ApplyReferralRequest, RedeemPromoCodeRequest, RedeemPromoCodeResponse, ReferralResponse,
+        },
+        promotions::{self, generate_referral_code, normalize_code},
+    },
+    axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        Json,
+    },
+    sqlx::PgPool,
+    std::sync::Arc,
+};
+
+/// Redeems a promo code worth a fixed amount of credits. Bonus codes are
+/// redeemed with a purchase through `/packages/verify` instead.
+pub async fn redeem_promo_code(
+    State(pool): State<PgPool>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+    Json(payload): Json<RedeemPromoCodeRequest>,
+) -> Result<Json<RedeemPromoCodeResponse>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let credits_granted =
+        promotions::redeem_promo_code(&mut tx, user_id, &payload.code, None).await?;
+
+    let remaining_requests = sqlx::query_scalar::<_, i32>(
+        "SELECT COALESCE(
+            (SELECT remaining_requests FROM credits WHERE user_id = $1), 0)",
+    )
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(RedeemPromoCodeResponse {
+        credits_granted,
+        remaining_requests: remaining_requests as i64,
+    }))
+}
+
+/// Returns the user's referral code and link, creating the code on first use.
+pub async fn get_referral(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+) -> Result<Json<ReferralResponse>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let mut code =
+        sqlx::query_scalar::<_, Option<String>>("SELECT referral_code FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Retry on the unlikely collision with another user's code
+    for _ in 0..3 {
+        if code.is_some() {
+            break;
+        }
+        code = sqlx::query_scalar::<_, String>(
+            "UPDATE users SET referral_code = COALESCE(referral_code, $1)
+             WHERE id = $2
+             RETURNING referral_code",
+        )
+        .bind(generate_referral_code())
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .map(Some)
+        .or_else(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => Ok(None),
+            e => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        })?;
+    }
+    let code = code.ok_or((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed to generate a referral code".to_string(),
+    ))?;
+
+    let (referrals, rewarded) = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT COUNT(*), COUNT(rewarded_at) FROM referrals WHERE referrer_id = $1",
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ReferralResponse {
+        link: format!("https://{}/?ref={}", config.siws_domain, code),
+        code,
+        referrals,
+        rewarded,
+    }))
+}
+
+/// Links the user to a referrer. Only possible once, and before the user's
+/// first paid purchase, which rewards both of them.
+pub async fn apply_referral(
+    State(pool): State<PgPool>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+    Json(payload): Json<ApplyReferralRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let referrer_id = sqlx::query_scalar::<_, i32>("SELECT id FROM users WHERE referral_code = $1")
+        .bind(normalize_code(&payload.code))
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Referral code not found".to_string()))?;
+
+    if referrer_id == user_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "You can't refer yourself".to_string(),
+        ));
+    }
+
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let has_purchased = promotions::has_paid_purchase(&mut conn, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if has_purchased {
+        return Err((
+            StatusCode::CONFLICT,
+            "Referrals only apply before the first purchase".to_string(),
+        ));
+    }
+
+    let applied = sqlx::query(
+        "INSERT INTO referrals (referee_id, referrer_id) VALUES ($1, $2)
+         ON CONFLICT (referee_id) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(referrer_id)
+    .execute(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .rows_affected()
+        == 1;
+
+    if !applied {
+        return Err((
+            StatusCode::CONFLICT,
+            "A referral has already been applied".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::CREATED)
+}
@@ -1,27 +1,29 @@
 use axum::{extract::{Path, State}, http::StatusCode, Json};
 use serde_json::Value;
-use sqlx::PgPool;
-
-pub const TWITTER_API_URL: &str = "https://api.twitter.com";
+use std::sync::Arc;
+use crate::config::Config;
+
+fn twitter_bearer(config: &Config) -> Result<String, (StatusCode, String)> {
+    config.twitter_bearer_token.clone().ok_or((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Twitter bearer token not configured".to_string(),
+    ))
+}
 
 // Search Posts
 // GET https://api.twitter.com/2/tweets/search/recent?query=Near%20Protocol&max_results=10&tweet.fields=created_at,author_id
 // Authorization: Bearer YOUR_BEARER_TOKEN
 // query should be a parameter for the method
 pub async fn search_posts(
+    State(config): State<Arc<Config>>,
+    State(client): State<reqwest::Client>,
     Path(query): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, String)> {
-    let client = reqwest::Client::new();
-    let bearer = std::env::var("TWITTER_BEARER_TOKEN").map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Missing TWITTER_BEARER_TOKEN environment variable".to_string(),
-        )
-    })?;
+    let bearer = twitter_bearer(&config)?;
 
     let url = format!(
         "{}/2/tweets/search/recent?query={}&max_results=10&tweet.fields=created_at,author_id",
-        TWITTER_API_URL, query
+        config.twitter_api_url, query
     );
 
 
@@ -44,19 +46,15 @@ pub async fn search_posts(
 //   --header 'Authorization: Bearer <token>'
 // pub fn user_mention_timeline()
 pub async fn user_mention_timeline(
+    State(config): State<Arc<Config>>,
+    State(client): State<reqwest::Client>,
     Path(user_id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, String)> {
-    let client = reqwest::Client::new();
-    let bearer = std::env::var("TWITTER_BEARER_TOKEN").map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Missing TWITTER_BEARER_TOKEN environment variable".to_string(),
-        )
-    })?;
+    let bearer = twitter_bearer(&config)?;
 
-    let url = format!("{}/2/users/{}/mentions", TWITTER_API_URL, "1871356182830391296"); // Mocked User Id to not spend my request per day to get user id
+    let url = format!("{}/2/users/{}/mentions", config.twitter_api_url, "1871356182830391296"); // Mocked User Id to not spend my request per day to get user id
 
-    // if let Some(user_id) = fetch_user_by_username(&client, &bearer, &username).await {
+    // if let Some(user_id) = fetch_user_by_username(&client, &config.twitter_api_url, &bearer, &username).await {
         let resp = client
             .get(&url)
             .bearer_auth(&bearer)
@@ -77,16 +75,14 @@ pub async fn user_mention_timeline(
 // curl --request GET \
 //   --url https://api.twitter.com/2/users/by/username/{username} \
 //   --header 'Authorization: Bearer <token>'
-pub async fn get_user_by_username(Path(username): Path<String>) -> Result<Json<Value>, (StatusCode, String)> {
-    let client = reqwest::Client::new();
-    let bearer = std::env::var("TWITTER_BEARER_TOKEN").map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Missing TWITTER_BEARER_TOKEN environment variable".to_string(),
-        )
-    })?;
-
-    match fetch_user_by_username(&client, &bearer, &username).await {
+pub async fn get_user_by_username(
+    State(config): State<Arc<Config>>,
+    State(client): State<reqwest::Client>,
+    Path(username): Path<String>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let bearer = twitter_bearer(&config)?;
+
+    match fetch_user_by_username(&client, &config.twitter_api_url, &bearer, &username).await {
         Some(json) => Ok(Json(json)),
         None => Err((StatusCode::NOT_FOUND, "User not found".to_string())),
     }
@@ -94,10 +90,11 @@ pub async fn get_user_by_username(Path(username): Path<String>) -> Result<Json<V
 
 async fn fetch_user_by_username(
     client: &reqwest::Client,
+    api_url: &str,
     bearer: &str,
     username: &str,
 ) -> Option<Value> {
-    let url = format!("{}/2/users/by/username/{}", TWITTER_API_URL, username);
+    let url = format!("{}/2/users/by/username/{}", api_url, username);
     let resp = client
         .get(&url)
         .bearer_auth(bearer)
@@ -116,18 +113,14 @@ async fn fetch_user_by_username(
 //   --url https://api.twitter.com/2/users/{id}/followers \
 //   --header 'Authorization: Bearer <token>'
 pub async fn get_followers_by_username(
+    State(config): State<Arc<Config>>,
+    State(client): State<reqwest::Client>,
     Path(username): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, String)> {
-    let client = reqwest::Client::new();
-    let bearer = std::env::var("TWITTER_BEARER_TOKEN").map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Missing TWITTER_BEARER_TOKEN environment variable".to_string(),
-        )
-    })?;
+    let bearer = twitter_bearer(&config)?;
 
-    // if let Some(user_id) = fetch_user_by_username(&client, &bearer, &username).await {
-        let url = format!("{}/2/users/{}/followers", TWITTER_API_URL, "1871356182830391296"); // Mocked User Id to not spend my request per day to get user id
+    // if let Some(user_id) = fetch_user_by_username(&client, &config.twitter_api_url, &bearer, &username).await {
+        let url = format!("{}/2/users/{}/followers", config.twitter_api_url, "1871356182830391296"); // Mocked User Id to not spend my request per day to get user id
 
         let resp = client
             .get(&url)
@@ -150,18 +143,14 @@ pub async fn get_followers_by_username(
 //   --url https://api.twitter.com/2/users/{id}/following \
 //   --header 'Authorization: Bearer <token>'
 pub async fn get_following_by_username(
+    State(config): State<Arc<Config>>,
+    State(client): State<reqwest::Client>,
     Path(username): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, String)> {
-    let client = reqwest::Client::new();
-    let bearer = std::env::var("TWITTER_BEARER_TOKEN").map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Missing TWITTER_BEARER_TOKEN environment variable".to_string(),
-        )
-    })?;
+    let bearer = twitter_bearer(&config)?;
 
-    // if let Some(user_id) = fetch_user_by_username(&client, &bearer, &username).await {
-        let url = format!("{}/2/users/{}/following", TWITTER_API_URL, "1871356182830391296"); // Mocked User Id to not spend my request per day to get user id
+    // if let Some(user_id) = fetch_user_by_username(&client, &config.twitter_api_url, &bearer, &username).await {
+        let url = format!("{}/2/users/{}/following", config.twitter_api_url, "1871356182830391296"); // Mocked User Id to not spend my request per day to get user id
 
         let resp = client
             .get(&url)
@@ -184,18 +173,14 @@ pub async fn get_following_by_username(
 //   --url https://api.twitter.com/2/users/{id}/blocking \
 //   --header 'Authorization: Bearer <token>'
 pub async fn get_blocked_by_username(
+    State(config): State<Arc<Config>>,
+    State(client): State<reqwest::Client>,
     Path(username): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, String)> {
-    let client = reqwest::Client::new();
-    let bearer = std::env::var("TWITTER_BEARER_TOKEN").map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Missing TWITTER_BEARER_TOKEN environment variable".to_string(),
-        )
-    })?;
+    let bearer = twitter_bearer(&config)?;
 
-    // if let Some(user_id) = fetch_user_by_username(&client, &bearer, &username).await {
-        let url = format!("{}/2/users/{}/blocking", TWITTER_API_URL, "1871356182830391296"); // Mocked User Id to not spend my request per day to get user id
+    // if let Some(user_id) = fetch_user_by_username(&client, &config.twitter_api_url, &bearer, &username).await {
+        let url = format!("{}/2/users/{}/blocking", config.twitter_api_url, "1871356182830391296"); // Mocked User Id to not spend my request per day to get user id
 
         let resp = client
             .get(&url)
@@ -218,17 +203,13 @@ pub async fn get_blocked_by_username(
 //   --url https://api.twitter.com/2/trends/by/woeid/{woeid} \
 //   --header 'Authorization: Bearer <token>'
 pub async fn get_trends_by_woeid(
+    State(config): State<Arc<Config>>,
+    State(client): State<reqwest::Client>,
     Path(woeid): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, String)> {
-    let client = reqwest::Client::new();
-    let bearer = std::env::var("TWITTER_BEARER_TOKEN").map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Missing TWITTER_BEARER_TOKEN environment variable".to_string(),
-        )
-    })?;
-
-    let url = format!("{}/2/trends/by/woeid/{}", TWITTER_API_URL, "2450091"); // Mocked Woeid to the PoC
+    let bearer = twitter_bearer(&config)?;
+
+    let url = format!("{}/2/trends/by/woeid/{}", config.twitter_api_url, "2450091"); // Mocked Woeid to the PoC
 
     let resp = client
         .get(&url)
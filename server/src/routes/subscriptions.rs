@@ -0,0 +1,285 @@
+use {
+    super::packages::get_user_id,
+    crate::{
+        config::Config,
+        middlewares::auth::AuthUser,
+        models::{
+            Subscription, SubscriptionKind, SubscriptionPayload, UpdateSubscriptionPayload,
+            FREE_SUBSCRIPTION_LIMIT,
+        },
+    },
+    axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        Json,
+    },
+    serde_json::Value,
+    sqlx::{PgConnection, PgPool},
+    std::{str::FromStr, sync::Arc},
+};
+
+/// Operators accepted in subscription filters. Mirrors the per-transfer
+/// operators understood by `swquery::utils::apply_filters`; set-relative ones
+/// such as `biggest` make no sense for a stream and are rejected.
+const SUPPORTED_FILTER_OPERATORS: [&str; 7] = [
+    "equals",
+    "contains",
+    "starts_with",
+    "ends_with",
+    "greater_than",
+    "less_than",
+    "between",
+];
+
+pub fn validate_filters(filters: Option<Value>) -> Result<Value, (StatusCode, String)> {
+    let filters = match filters {
+        None | Some(Value::Null) => return Ok(serde_json::json!([])),
+        Some(filters) => filters,
+    };
+
+    let list = filters.as_array().ok_or((
+        StatusCode::BAD_REQUEST,
+        "Filters must be an array".to_string(),
+    ))?;
+
+    for filter in list {
+        if filter["field"].as_str().is_none() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Filter must have a 'field' key".to_string(),
+            ));
+        }
+
+        let operator = filter["operator"].as_str().ok_or((
+            StatusCode::BAD_REQUEST,
+            "Filter must have an 'operator' key".to_string(),
+        ))?;
+        if !SUPPORTED_FILTER_OPERATORS.contains(&operator) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported operator: {}", operator),
+            ));
+        }
+    }
+
+    Ok(filters)
+}
+
+/// Highest subscription limit among the packages the user has bought, or
+/// `credit_sales_max_subscriptions` if they bought credits or a plan from the
+/// credit-sales program and that allows more.
+async fn get_subscription_limit(
+    conn: &mut PgConnection,
+    config: &Config,
+    user_id: i32,
+) -> Result<i64, (StatusCode, String)> {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT GREATEST(
+            COALESCE(
+                (SELECT MAX(p.max_subscriptions)::BIGINT
+                 FROM transactions t
+                 JOIN packages p ON p.id = t.package_id
+                 WHERE t.user_id = $1 AND t.status = 'completed'),
+                $2
+            ),
+            CASE WHEN EXISTS (
+                SELECT 1 FROM credit_sales_accounts WHERE user_id = $1 AND purchase_count > 0
+            ) OR EXISTS (
+                SELECT 1 FROM credit_sales_plans WHERE user_id = $1
+            ) THEN $3 ELSE $2 END
+        )",
+    )
+    .bind(user_id)
+    .bind(FREE_SUBSCRIPTION_LIMIT)
+    .bind(config.credit_sales_max_subscriptions)
+    .fetch_one(conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+async fn list_subscriptions(
+    pool: &PgPool,
+    user_id: i32,
+) -> Result<Vec<Subscription>, (StatusCode, String)> {
+    sqlx::query_as::<_, Subscription>(
+        "SELECT * FROM subscriptions WHERE user_id = $1 ORDER BY created_at DESC, id DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn manage_subscription(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+    Json(payload): Json<SubscriptionPayload>,
+) -> Result<(StatusCode, Json<Vec<Subscription>>), (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let (subscribe, method_key) = if let Some(key) = payload.method.strip_prefix("unsubscribe") {
+        (false, key)
+    } else if let Some(key) = payload.method.strip_prefix("subscribe") {
+        (true, key)
+    } else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown subscription method: {}", payload.method),
+        ));
+    };
+
+    let kind = SubscriptionKind::from_str(method_key).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let mut keys = payload.keys.unwrap_or_default();
+    keys.retain(|key| !key.is_empty());
+    keys.sort();
+    keys.dedup();
+
+    if kind.requires_target() && keys.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "At least one key is required for this subscription".to_string(),
+        ));
+    }
+    if !kind.requires_target() {
+        keys = vec![String::new()];
+    }
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    if subscribe {
+        let filters = validate_filters(payload.filters)?;
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        // Serialize subscribes per user so the limit can't be raced past
+        sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        let limit = get_subscription_limit(&mut tx, &config, user_id).await?;
+
+        let current =
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM subscriptions WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        let existing = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM subscriptions
+             WHERE user_id = $1 AND kind = $2 AND target_key = ANY($3)",
+        )
+        .bind(user_id)
+        .bind(kind.as_str())
+        .bind(&keys)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        // Only keys that are not subscribed yet count towards the limit
+        if current + keys.len() as i64 - existing > limit {
+            return Err((
+                StatusCode::FORBIDDEN,
+                format!("Subscription limit of {} reached for current plan", limit),
+            ));
+        }
+
+        sqlx::query(
+            "INSERT INTO subscriptions (user_id, kind, target_key, filters)
+             SELECT $1, $2, UNNEST($3::VARCHAR[]), $4
+             ON CONFLICT (user_id, kind, target_key)
+             DO UPDATE SET filters = EXCLUDED.filters",
+        )
+        .bind(user_id)
+        .bind(kind.as_str())
+        .bind(&keys)
+        .bind(&filters)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    } else {
+        sqlx::query(
+            "DELETE FROM subscriptions WHERE user_id = $1 AND kind = $2 AND target_key = ANY($3)",
+        )
+        .bind(user_id)
+        .bind(kind.as_str())
+        .bind(&keys)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    let subscriptions = list_subscriptions(&pool, user_id).await?;
+
+    Ok((StatusCode::OK, Json(subscriptions)))
+}
+
+pub async fn get_subscriptions(
+    State(pool): State<PgPool>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+) -> Result<Json<Vec<Subscription>>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+    let subscriptions = list_subscriptions(&pool, user_id).await?;
+
+    Ok(Json(subscriptions))
+}
+
+pub async fn update_subscription(
+    State(pool): State<PgPool>,
+    Path((pubkey, subscription_id)): Path<(String, i32)>,
+    auth: AuthUser,
+    Json(payload): Json<UpdateSubscriptionPayload>,
+) -> Result<Json<Subscription>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    sqlx::query_as::<_, Subscription>(
+        "UPDATE subscriptions SET paused = $1 WHERE id = $2 AND user_id = $3 RETURNING *",
+    )
+    .bind(payload.paused)
+    .bind(subscription_id)
+    .bind(user_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map(Json)
+    .ok_or((StatusCode::NOT_FOUND, "Subscription not found".to_string()))
+}
+
+pub async fn delete_subscription(
+    State(pool): State<PgPool>,
+    Path((pubkey, subscription_id)): Path<(String, i32)>,
+    auth: AuthUser,
+) -> Result<StatusCode, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+
+    let result = sqlx::query("DELETE FROM subscriptions WHERE id = $1 AND user_id = $2")
+        .bind(subscription_id)
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Subscription not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
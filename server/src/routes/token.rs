@@ -1,9 +1,11 @@
-use axum::{extract::Path, http::StatusCode, Json, Router, routing::get, response::IntoResponse};
+use axum::{extract::{Path, State}, http::StatusCode, Json, Router, routing::get, response::IntoResponse};
 use serde_json::Value;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use reqwest::Client;
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::sync::Arc;
+use crate::config::Config;
 
 #[derive(Debug, Deserialize)]
 struct TokenInfo {
@@ -25,10 +27,14 @@ pub struct RugPullResponse {
 }
 
 
-pub async fn get_token_info(Path(token_name): Path<String>) -> Result<Json<Value>, StatusCode> {
+pub async fn get_token_info(
+    State(config): State<Arc<Config>>,
+    State(client): State<Client>,
+    Path(token_name): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
     println!("Fetching token info for {}", token_name);
     
-    let token_address = match fetch_token_address(&token_name).await {
+    let token_address = match fetch_token_address(&client, &config.token_list_url, &token_name).await {
         Some(address) => address,
         None => {
             println!("Token {} not found in Solana token list", token_name);
@@ -36,7 +42,7 @@ pub async fn get_token_info(Path(token_name): Path<String>) -> Result<Json<Value
         }
     };
     
-    match fetch_market_data(&token_address).await {
+    match fetch_market_data(&client, &config.coingecko_api_url, &token_address).await {
         Some(data) => Ok(Json(data)),
         None => {
             println!("Failed to fetch market data for token {}", token_name);
@@ -45,9 +51,8 @@ pub async fn get_token_info(Path(token_name): Path<String>) -> Result<Json<Value
     }
 }
 
-async fn fetch_token_address(token_name: &str) -> Option<String> {
-    let url = "https://cdn.jsdelivr.net/gh/solana-labs/token-list@main/src/tokens/solana.tokenlist.json";
-    let response: Value = reqwest::get(url).await.ok()?.json().await.ok()?;
+async fn fetch_token_address(client: &Client, token_list_url: &str, token_name: &str) -> Option<String> {
+    let response: Value = client.get(token_list_url).send().await.ok()?.json().await.ok()?;
 
     let tokens = response.get("tokens")?.as_array()?; 
     
@@ -65,22 +70,18 @@ async fn fetch_token_address(token_name: &str) -> Option<String> {
         })
 }
 
-async fn fetch_market_data(contract_address: &str) -> Option<Value> {
-    let url = format!("https://api.coingecko.com/api/v3/coins/solana/contract/{}", contract_address);
-    let response = reqwest::get(&url).await.ok()?.json::<Value>().await.ok();
+async fn fetch_market_data(client: &Client, coingecko_api_url: &str, contract_address: &str) -> Option<Value> {
+    let url = format!("{}/coins/solana/contract/{}", coingecko_api_url, contract_address);
+    let response = client.get(&url).send().await.ok()?.json::<Value>().await.ok();
         
     response
 }
 
 pub async fn analyze_rug_pull_risk(
+    State(rpc_client): State<Arc<RpcClient>>,
+    State(client): State<Client>,
     Json(payload): Json<RugPullRequest>,
 ) -> impl IntoResponse {
-    let rpc_client = RpcClient::new(format!(
-        "https://mainnet.helius-rpc.com/?api-key={}",
-        std::env::var("TEST_HELIUS_API_KEY").unwrap_or_default()
-    ));
-
-    let client = Client::new();
 
     let response = client
         .get(format!(
@@ -1,13 +1,22 @@
 use {
-    crate::models::{User, UserWithApiKey},
+    super::packages::get_user_id,
+    crate::{
+        config::Config,
+        ledger::{self, EntryKind},
+        middlewares::auth::AuthUser,
+        models::{User, UserWithApiKey},
+        plans::{self, ActivePlan},
+    },
     axum::{
         extract::{Path, State},
         http::StatusCode,
         Json,
     },
+    chrono::{NaiveDateTime, Utc},
     rust_decimal::Decimal,
     serde::{Deserialize, Serialize},
     sqlx::PgPool,
+    std::sync::Arc,
 };
 
 #[derive(Deserialize)]
@@ -15,17 +24,23 @@ pub struct CreateUser {
     pub pubkey: String,
 }
 
-#[derive(Deserialize)]
-pub struct SubscriptionPayload {
-    method: String,
-    keys: Option<Vec<String>>,
-}
-
 #[derive(Debug, Serialize)]
 pub struct UsageResponse {
     remaining_credits: i64,
     last_transaction: Option<Transaction>,
     total_spent_usdc: Decimal,
+    /// Only present on the first call, when the trial key is issued
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlanResponse {
+    #[serde(flatten)]
+    plan: ActivePlan,
+    /// Start of the plan's current monthly window
+    window_start: NaiveDateTime,
+    requests_remaining: i32,
 }
 
 #[derive(Debug, Serialize, sqlx::FromRow)]
@@ -38,8 +53,11 @@ pub struct Transaction {
 
 pub async fn create_user(
     State(pool): State<PgPool>,
+    auth: AuthUser,
     Json(payload): Json<CreateUser>,
 ) -> Result<(StatusCode, Json<User>), (StatusCode, String)> {
+    auth.require_owner(&payload.pubkey)?;
+
     // Ensure pubkey respects Solana public key length
     // if payload.pubkey.len() != 44 {
     //     return Err((StatusCode::BAD_REQUEST, "Invalid pubkey length".into()));
@@ -47,7 +65,7 @@ pub async fn create_user(
 
     // Check if user already exists
     if let Some(existing_user) =
-        sqlx::query_as::<_, User>("SELECT id, pubkey, subscriptions FROM users WHERE pubkey = $1")
+        sqlx::query_as::<_, User>("SELECT id, pubkey FROM users WHERE pubkey = $1")
             .bind(&payload.pubkey)
             .fetch_optional(&pool)
             .await
@@ -58,206 +76,114 @@ pub async fn create_user(
             Json(User {
                 id: existing_user.id,
                 pubkey: existing_user.pubkey,
-                subscriptions: existing_user.subscriptions,
             }),
         ));
     }
 
     // Insert new user
-    let user = sqlx::query_as::<_, User>(
-        "INSERT INTO users (pubkey) VALUES ($1) RETURNING id, pubkey, subscriptions",
-    )
-    .bind(&payload.pubkey)
-    .fetch_one(&pool)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to insert user: {}", e),
-        )
-    })?;
+    let user =
+        sqlx::query_as::<_, User>("INSERT INTO users (pubkey) VALUES ($1) RETURNING id, pubkey")
+            .bind(&payload.pubkey)
+            .fetch_one(&pool)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to insert user: {}", e),
+                )
+            })?;
 
     Ok((
         StatusCode::CREATED,
         Json(User {
             id: user.id,
             pubkey: user.pubkey,
-            subscriptions: user.subscriptions,
         }),
     ))
 }
 
-pub async fn get_users(State(pool): State<PgPool>) -> Json<Vec<User>> {
-    let users = sqlx::query_as::<_, User>("SELECT id, pubkey, subscriptions FROM users")
-        .fetch_all(&pool)
-        .await
-        .expect("Failed to fetch users")
-        .into_iter()
-        .map(|user| User {
-            id: user.id,
-            pubkey: user.pubkey,
-            subscriptions: user.subscriptions,
-        })
-        .collect();
-
-    Json(users)
-}
-
 pub async fn get_user_by_pubkey(
     State(pool): State<PgPool>,
     Path(pubkey): Path<String>,
 ) -> Result<Json<UserWithApiKey>, (StatusCode, String)> {
-    let user =
-        sqlx::query_as::<_, User>("SELECT id, pubkey, subscriptions FROM users WHERE pubkey = $1")
-            .bind(&pubkey)
-            .fetch_optional(&pool)
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to query user: {}", e),
-                )
-            })?;
+    let user = sqlx::query_as::<_, User>("SELECT id, pubkey FROM users WHERE pubkey = $1")
+        .bind(&pubkey)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to query user: {}", e),
+            )
+        })?;
 
     if user.is_none() {
         return Err((StatusCode::NOT_FOUND, "User not found".into()));
     }
 
-    // Get user API-key
-    let user_api_key =
-        sqlx::query_scalar::<_, String>("SELECT api_key FROM credits WHERE user_id = $1")
-            .bind(user.clone().unwrap().id)
-            .fetch_optional(&pool)
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to query user API-key: {}", e),
-                )
-            })?;
+    // Get user API-key prefix
+    let user_api_key_prefix = sqlx::query_scalar::<_, String>(
+        "SELECT key_prefix FROM api_keys
+         WHERE user_id = $1
+           AND revoked_at IS NULL
+           AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+         ORDER BY created_at DESC
+         LIMIT 1",
+    )
+    .bind(user.clone().unwrap().id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to query user API-key: {}", e),
+        )
+    })?;
 
     if let Some(user) = user {
         Ok(Json(UserWithApiKey {
             id: user.id,
             pubkey: user.pubkey,
-            subscriptions: user.subscriptions,
-            api_key: user_api_key,
+            api_key_prefix: user_api_key_prefix,
         }))
     } else {
         Err((StatusCode::NOT_FOUND, "User not found".into()))
     }
 }
 
-pub async fn manage_subscription(
+pub async fn get_usage(
     State(pool): State<PgPool>,
     Path(pubkey): Path<String>,
-    Json(payload): Json<SubscriptionPayload>,
-) -> Result<(StatusCode, String), (StatusCode, String)> {
-    let user =
-        sqlx::query_as::<_, User>("SELECT id, pubkey, subscriptions FROM users WHERE pubkey = $1")
-            .bind(&pubkey)
-            .fetch_optional(&pool)
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Error querying user: {}", e),
-                )
-            })?;
-
-    if let Some(mut user) = user {
-        let mut subscriptions = if user.subscriptions.is_null() {
-            serde_json::json!({})
-        } else {
-            user.subscriptions.clone()
-        };
-
-        if let Some(keys) = payload.keys {
-            if payload.method.starts_with("subscribe") {
-                let method_key = payload.method.strip_prefix("subscribe").unwrap();
-
-                if !subscriptions.get(method_key).is_some() {
-                    subscriptions[method_key] = serde_json::json!([]);
-                }
-
-                let list = subscriptions
-                    .get_mut(method_key)
-                    .unwrap()
-                    .as_array_mut()
-                    .unwrap();
-
-                for key in keys {
-                    if !list.contains(&serde_json::json!(key)) {
-                        list.push(serde_json::json!(key));
-                    }
-                }
-            } else if payload.method.starts_with("unsubscribe") {
-                let method_key = payload.method.strip_prefix("unsubscribe").unwrap();
-                if let Some(list) = subscriptions
-                    .get_mut(method_key)
-                    .and_then(|v| v.as_array_mut())
-                {
-                    list.retain(|v| !keys.contains(&v.as_str().unwrap_or_default().to_string()));
-                }
-            }
-        }
+    auth: AuthUser,
+) -> Result<Json<UsageResponse>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
 
-        let updated_user = sqlx::query_as::<_, User>(
-            "UPDATE users SET subscriptions = $1 WHERE pubkey = $2 RETURNING id, pubkey, subscriptions",
-        )
-        .bind(serde_json::to_value(subscriptions).unwrap())
+    // First try to get the user, if not exists, create it
+    let user = sqlx::query_as::<_, User>("SELECT id, pubkey FROM users WHERE pubkey = $1")
         .bind(&pubkey)
-        .fetch_one(&pool)
+        .fetch_optional(&pool)
         .await
         .map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error updating subscriptions: {}", e),
+                format!("Error querying user: {}", e),
             )
         })?;
 
-        Ok((
-            StatusCode::OK,
-            format!("Subscriptions updated for user: {}", updated_user.pubkey),
-        ))
-    } else {
-        Err((StatusCode::NOT_FOUND, "User not found".to_string()))
-    }
-}
-
-pub async fn get_usage(
-    State(pool): State<PgPool>,
-    Path(pubkey): Path<String>,
-) -> Result<Json<UsageResponse>, (StatusCode, String)> {
-    // First try to get the user, if not exists, create it
-    let user =
-        sqlx::query_as::<_, User>("SELECT id, pubkey, subscriptions FROM users WHERE pubkey = $1")
-            .bind(&pubkey)
-            .fetch_optional(&pool)
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Error querying user: {}", e),
-                )
-            })?;
-
     let user = match user {
         Some(user) => user,
         None => {
             // Insert new user
-            sqlx::query_as::<_, User>(
-                "INSERT INTO users (pubkey) VALUES ($1) RETURNING id, pubkey, subscriptions",
-            )
-            .bind(&pubkey)
-            .fetch_one(&pool)
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to insert user: {}", e),
-                )
-            })?
+            sqlx::query_as::<_, User>("INSERT INTO users (pubkey) VALUES ($1) RETURNING id, pubkey")
+                .bind(&pubkey)
+                .fetch_one(&pool)
+                .await
+                .map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Failed to insert user: {}", e),
+                    )
+                })?
         }
     };
 
@@ -279,6 +205,8 @@ pub async fn get_usage(
         )
     })?;
 
+    let mut api_key = None;
+
     // If first time user, create credits entry with 3 trial credits and record the transaction
     if !has_activity {
         let mut tx = pool.begin().await.map_err(|e| {
@@ -304,21 +232,25 @@ pub async fn get_usage(
             )
         })?;
 
-        // Insert credits
-        sqlx::query(
-            "INSERT INTO credits (user_id, remaining_requests, api_key) 
-             VALUES ($1, 3, $2)",
-        )
-        .bind(user.id)
-        .bind(crate::utils::generate_api_key())
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error inserting trial credits: {}", e),
-            )
-        })?;
+        ledger::record(&mut tx, user.id, EntryKind::Grant, 3, Some("free_trial"))
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error inserting trial credits: {}", e),
+                )
+            })?;
+
+        api_key = Some(
+            crate::utils::issue_api_key(&mut tx, user.id)
+                .await
+                .map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Error issuing API key: {}", e),
+                    )
+                })?,
+        );
 
         // Record free trial transaction
         sqlx::query(
@@ -402,5 +334,30 @@ pub async fn get_usage(
         remaining_credits: remaining_credits as i64,
         last_transaction,
         total_spent_usdc,
+        api_key,
+    }))
+}
+
+/// The user's active credit-sales plan and the requests left in its window.
+pub async fn get_plan(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(pubkey): Path<String>,
+    auth: AuthUser,
+) -> Result<Json<PlanResponse>, (StatusCode, String)> {
+    auth.require_owner(&pubkey)?;
+
+    let user_id = get_user_id(&pool, &pubkey).await?;
+    let plan = plans::active_plan(&pool, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "No active plan".to_string()))?;
+    let requests_remaining =
+        plans::requests_remaining(&pool, &plan, config.credit_sales_credits_per_request).await?;
+
+    Ok(Json(PlanResponse {
+        window_start: plan.window_start(Utc::now().naive_utc()),
+        plan,
+        requests_remaining,
     }))
 }
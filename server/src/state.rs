@@ -0,0 +1,97 @@
+use {
+    crate::{config::Config, middlewares::rate_limiter::RateLimiter},
+    axum::extract::FromRef,
+    reqwest::Client,
+    solana_client::nonblocking::rpc_client::RpcClient,
+    sqlx::PgPool,
+    std::{sync::Arc, time::Duration},
+    swquery::{client::Network, SWqueryClient},
+};
+
+/// State shared by every handler. Handlers extract only the parts they need,
+/// e.g. `State<PgPool>` or `State<Client>`.
+///
+/// Clients are created once and reused so connections are pooled across
+/// requests. Tests can build the struct directly with fakes, for instance an
+/// `RpcClient::new_mock` RPC client.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub config: Arc<Config>,
+    pub http: Client,
+    /// SDK client without an API key; use `with_api_key` per request.
+    pub swquery: SWqueryClient,
+    pub rpc: Arc<RpcClient>,
+    pub rate_limiter: RateLimiter,
+}
+
+impl AppState {
+    pub fn new(pool: PgPool, config: Arc<Config>) -> Self {
+        let timeout = Duration::from_secs(config.http_timeout_secs);
+
+        let http = Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        let swquery = SWqueryClient::new(
+            String::new(),
+            config.helius_api_key.clone(),
+            Some(timeout),
+            Some(Network::Mainnet),
+        )
+        .with_http_client(http.clone());
+
+        let rpc = Arc::new(RpcClient::new(config.solana_rpc_url.clone()));
+
+        let rate_limiter = RateLimiter::new(
+            config.rate_limit_max_requests,
+            Duration::from_secs(config.rate_limit_window_secs),
+        );
+
+        Self {
+            pool,
+            config,
+            http,
+            swquery,
+            rpc,
+            rate_limiter,
+        }
+    }
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Client {
+    fn from_ref(state: &AppState) -> Self {
+        state.http.clone()
+    }
+}
+
+impl FromRef<AppState> for SWqueryClient {
+    fn from_ref(state: &AppState) -> Self {
+        state.swquery.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RpcClient> {
+    fn from_ref(state: &AppState) -> Self {
+        state.rpc.clone()
+    }
+}
+
+impl FromRef<AppState> for RateLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        state.rate_limiter.clone()
+    }
+}
@@ -1,13 +1,69 @@
+use {
+    crate::{
+        middlewares::auth::{hash_token, DEFAULT_SCOPES},
+        models::ApiKey,
+    },
+    chrono::NaiveDateTime,
+};
+
 // Add function to generate API key
 pub fn generate_api_key() -> String {
+    generate_token(32)
+}
+
+/// Random alphanumeric string, used for API keys, nonces and session tokens.
+pub fn generate_token(len: usize) -> String {
     use rand::{thread_rng, Rng};
     const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
     let mut rng = thread_rng();
-    let key: String = (0..32)
+    let key: String = (0..len)
         .map(|_| {
             let idx = rng.gen_range(0..CHARSET.len());
             CHARSET[idx] as char
         })
         .collect();
     key
-}
\ No newline at end of file
+}
+
+/// Creates an API key with the default scopes for the user. Only its hash is
+/// stored, so the returned plaintext must be handed to the user right away.
+pub async fn issue_api_key(
+    conn: &mut sqlx::PgConnection,
+    user_id: i32,
+) -> Result<String, sqlx::Error> {
+    let scopes: Vec<String> = DEFAULT_SCOPES
+        .iter()
+        .map(|s| s.as_str().to_string())
+        .collect();
+
+    create_api_key(conn, user_id, None, &scopes, None)
+        .await
+        .map(|(_, key)| key)
+}
+
+/// Inserts a new key and returns its row together with the plaintext key.
+pub async fn create_api_key(
+    conn: &mut sqlx::PgConnection,
+    user_id: i32,
+    label: Option<&str>,
+    scopes: &[String],
+    expires_at: Option<NaiveDateTime>,
+) -> Result<(ApiKey, String), sqlx::Error> {
+    let key = generate_api_key();
+
+    let row = sqlx::query_as::<_, ApiKey>(
+        "INSERT INTO api_keys (user_id, key_hash, key_prefix, label, scopes, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING *",
+    )
+    .bind(user_id)
+    .bind(hash_token(&key))
+    .bind(&key[..8])
+    .bind(label)
+    .bind(scopes)
+    .bind(expires_at)
+    .fetch_one(conn)
+    .await?;
+
+    Ok((row, key))
+}
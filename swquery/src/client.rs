@@ -24,7 +24,7 @@ pub enum Network {
 /// SWqueryClient is the main entry point for using this SDK to interact with
 /// the Solana RPC via the Helius API and a custom Agent API. It provides typed
 /// methods for various RPC calls.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SWqueryClient {
     /// The Helius API key for RPC calls.
     pub helius_key: Option<String>,
@@ -41,6 +41,10 @@ pub struct SWqueryClient {
 pub struct SWqueryResponse {
     pub response: serde_json::Value,
     pub response_type: String,
+    /// The RPC method chosen by the agent, e.g. `getRecentTransactions`.
+    pub intent: String,
+    /// LLM tokens the agent reported spending on the query.
+    pub tokens: i64,
 }
 
 impl SWqueryClient {
@@ -71,6 +75,24 @@ impl SWqueryClient {
         }
     }
 
+    /// Replaces the internal reqwest client, e.g. to share a connection pool
+    /// with the rest of an application.
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Returns a copy of this client that authenticates with `api_key`.
+    ///
+    /// The copy shares the underlying connection pool, so it is cheap to
+    /// create one per request.
+    pub fn with_api_key(&self, api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            ..self.clone()
+        }
+    }
+
     /// Gets or fetches the Helius API key
     async fn get_helius_key(&self) -> Result<String, SdkError> {
         if let Some(key) = &self.helius_key {
@@ -544,6 +566,8 @@ impl SWqueryClient {
         Ok(SWqueryResponse {
             response,
             response_type: res_type.to_string(),
+            intent: response_type.to_string(),
+            tokens: result["tokens"].as_i64().unwrap_or(0),
         })
     }
 
@@ -401,116 +401,7 @@ pub fn apply_filters(
                 let mut matches_all_filters = true;
 
                 for filter in filter_list {
-                    let field = filter["field"].as_str().ok_or_else(|| {
-                        SdkError::InvalidInput("Filter must have a 'field' key".to_string())
-                    })?;
-                    let operator = filter["operator"].as_str().ok_or_else(|| {
-                        SdkError::InvalidInput("Filter must have an 'operator' key".to_string())
-                    })?;
-                    let value = &filter["value"];
-
-                    // Extract and parse the field value from the transfer
-                    let field_value = transfer.get(field);
-                    let numeric_field_value = field_value.and_then(|v| {
-                        if let Some(s) = v.as_str() {
-                            s.parse::<f64>().ok()
-                        } else {
-                            v.as_f64()
-                        }
-                    });
-
-                    println!(
-                        "    Field: {}, Value in Transfer: {:?}, Parsed Value: {:?}, Expected Value: {:?}",
-                        field, field_value, numeric_field_value, value
-                    );
-
-                    let matches_filter = match operator {
-                        "equals" => field_value.and_then(|v| v.as_str()).map_or(false, |v| {
-                            v.to_lowercase() == value.as_str().unwrap_or("").to_lowercase()
-                        }),
-
-                        "contains" => field_value.and_then(|v| v.as_str()).map_or(false, |v| {
-                            v.to_lowercase()
-                                .contains(&value.as_str().unwrap_or("").to_lowercase())
-                        }),
-
-                        "starts_with" => field_value.and_then(|v| v.as_str()).map_or(false, |v| {
-                            v.to_lowercase()
-                                .starts_with(&value.as_str().unwrap_or("").to_lowercase())
-                        }),
-
-                        "ends_with" => field_value.and_then(|v| v.as_str()).map_or(false, |v| {
-                            v.to_lowercase()
-                                .ends_with(&value.as_str().unwrap_or("").to_lowercase())
-                        }),
-
-                        "greater_than" => {
-                            let threshold = value.as_f64().ok_or_else(|| {
-                                SdkError::InvalidInput(
-                                    "Value must be a number for 'greater_than'".to_string(),
-                                )
-                            })?;
-                            numeric_field_value.map_or(false, |v| v > threshold)
-                        }
-
-                        "less_than" => {
-                            let threshold = value.as_f64().ok_or_else(|| {
-                                SdkError::InvalidInput(
-                                    "Value must be a number for 'less_than'".to_string(),
-                                )
-                            })?;
-                            numeric_field_value.map_or(false, |v| v < threshold)
-                        }
-
-                        "between" => {
-                            let range = value.as_array().ok_or_else(|| {
-                                SdkError::InvalidInput(
-                                    "Value must be an array for 'between'".to_string(),
-                                )
-                            })?;
-                            if range.len() != 2 {
-                                return Err(SdkError::InvalidInput(
-                                    "Value for 'between' must have exactly two elements"
-                                        .to_string(),
-                                ));
-                            }
-                            let start = range[0].as_f64().ok_or_else(|| {
-                                SdkError::InvalidInput(
-                                    "Start value for 'between' must be a number".to_string(),
-                                )
-                            })?;
-                            let end = range[1].as_f64().ok_or_else(|| {
-                                SdkError::InvalidInput(
-                                    "End value for 'between' must be a number".to_string(),
-                                )
-                            })?;
-                            numeric_field_value.map_or(false, |v| v >= start && v <= end)
-                        }
-
-                        "biggest" => {
-                            // Use precomputed global maximum for this field
-                            if let Some(&global_max) = global_maxes.get(field) {
-                                numeric_field_value.map_or(false, |v| v == global_max)
-                            } else {
-                                false
-                            }
-                        }
-
-                        "smallest" => {
-                            // For "smallest", similarly compute a global minimum if needed.
-                            // This example focuses on "biggest". Implement similarly for "smallest".
-                            return Err(SdkError::InvalidInput(
-                                "'smallest' operator not implemented".to_string(),
-                            ));
-                        }
-
-                        _ => {
-                            return Err(SdkError::InvalidInput(format!(
-                                "Unsupported operator: {}",
-                                operator
-                            )))
-                        }
-                    };
+                    let matches_filter = filter_matches(transfer, filter, &global_maxes)?;
 
                     println!("    Matches Filter: {}", matches_filter);
 
@@ -538,6 +429,126 @@ pub fn apply_filters(
     Ok(filtered_transactions)
 }
 
+/// Evaluates a single `{field, operator, value}` filter against one transfer.
+///
+/// `global_maxes` holds the precomputed maximum per field for the `biggest`
+/// operator; without an entry for the field, `biggest` never matches.
+pub fn filter_matches(
+    transfer: &Value,
+    filter: &Value,
+    global_maxes: &HashMap<String, f64>,
+) -> Result<bool, SdkError> {
+    let field = filter["field"]
+        .as_str()
+        .ok_or_else(|| SdkError::InvalidInput("Filter must have a 'field' key".to_string()))?;
+    let operator = filter["operator"]
+        .as_str()
+        .ok_or_else(|| SdkError::InvalidInput("Filter must have an 'operator' key".to_string()))?;
+    let value = &filter["value"];
+
+    // Extract and parse the field value from the transfer
+    let field_value = transfer.get(field);
+    let numeric_field_value = field_value.and_then(|v| {
+        if let Some(s) = v.as_str() {
+            s.parse::<f64>().ok()
+        } else {
+            v.as_f64()
+        }
+    });
+
+    let matches_filter = match operator {
+        "equals" => field_value.and_then(|v| v.as_str()).map_or(false, |v| {
+            v.to_lowercase() == value.as_str().unwrap_or("").to_lowercase()
+        }),
+
+        "contains" => field_value.and_then(|v| v.as_str()).map_or(false, |v| {
+            v.to_lowercase()
+                .contains(&value.as_str().unwrap_or("").to_lowercase())
+        }),
+
+        "starts_with" => field_value.and_then(|v| v.as_str()).map_or(false, |v| {
+            v.to_lowercase()
+                .starts_with(&value.as_str().unwrap_or("").to_lowercase())
+        }),
+
+        "ends_with" => field_value.and_then(|v| v.as_str()).map_or(false, |v| {
+            v.to_lowercase()
+                .ends_with(&value.as_str().unwrap_or("").to_lowercase())
+        }),
+
+        "greater_than" => {
+            let threshold = value.as_f64().ok_or_else(|| {
+                SdkError::InvalidInput("Value must be a number for 'greater_than'".to_string())
+            })?;
+            numeric_field_value.map_or(false, |v| v > threshold)
+        }
+
+        "less_than" => {
+            let threshold = value.as_f64().ok_or_else(|| {
+                SdkError::InvalidInput("Value must be a number for 'less_than'".to_string())
+            })?;
+            numeric_field_value.map_or(false, |v| v < threshold)
+        }
+
+        "between" => {
+            let range = value.as_array().ok_or_else(|| {
+                SdkError::InvalidInput("Value must be an array for 'between'".to_string())
+            })?;
+            if range.len() != 2 {
+                return Err(SdkError::InvalidInput(
+                    "Value for 'between' must have exactly two elements".to_string(),
+                ));
+            }
+            let start = range[0].as_f64().ok_or_else(|| {
+                SdkError::InvalidInput("Start value for 'between' must be a number".to_string())
+            })?;
+            let end = range[1].as_f64().ok_or_else(|| {
+                SdkError::InvalidInput("End value for 'between' must be a number".to_string())
+            })?;
+            numeric_field_value.map_or(false, |v| v >= start && v <= end)
+        }
+
+        "biggest" => {
+            // Use precomputed global maximum for this field
+            if let Some(&global_max) = global_maxes.get(field) {
+                numeric_field_value.map_or(false, |v| v == global_max)
+            } else {
+                false
+            }
+        }
+
+        "smallest" => {
+            // For "smallest", similarly compute a global minimum if needed.
+            // This example focuses on "biggest". Implement similarly for "smallest".
+            return Err(SdkError::InvalidInput(
+                "'smallest' operator not implemented".to_string(),
+            ));
+        }
+
+        _ => {
+            return Err(SdkError::InvalidInput(format!(
+                "Unsupported operator: {}",
+                operator
+            )))
+        }
+    };
+
+    Ok(matches_filter)
+}
+
+/// Returns whether a single transfer satisfies every filter, using the same
+/// semantics as [`apply_filters`].
+pub fn transfer_matches_filters(transfer: &Value, filters: &[Value]) -> Result<bool, SdkError> {
+    let global_maxes = HashMap::new();
+    for filter in filters {
+        if !filter_matches(transfer, filter, &global_maxes)? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
 fn extract_field_from_details(details: &Value, field: &str) -> Option<Value> {
     // Direct match using JSON pointer
     if let Some(value) = details.pointer(field) {